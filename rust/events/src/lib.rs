@@ -0,0 +1,57 @@
+pub mod reader;
+pub use reader::*;
+
+pub mod record;
+pub use record::*;
+
+use std::ffi::{CStr, CString, c_char};
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReadParams {
+    path: String,
+    /// The `offset` of the previous batch, to follow a file that is still being written
+    #[serde(default)]
+    offset: u64,
+    /// `"tensor_flow"` or `"mind_spore"`, told by the file name by default
+    format: Option<EventFormat>,
+}
+
+/// Read the records of an event file from `offset` to its end.
+/// Returns a [`Batch`], or `null` if the file can't be opened.
+#[unsafe(no_mangle)]
+pub extern "C" fn read_events_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
+    };
+
+    let ReadParams { path, offset, format } = serde_json::from_str::<ReadParams>(data).unwrap();
+    let format = format.unwrap_or_else(|| EventFormat::from_path(&path));
+
+    let ret = match EventFile::open_at(&path, format, offset) {
+        Ok(mut file) => Some(file.poll()),
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", path, e);
+            None
+        }
+    };
+
+    let json_string = serde_json::to_string(&ret).unwrap();
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn free_events_string_rs(s: *mut c_char) {
+    if s.is_null() {
+        return;
+    }
+
+    unsafe {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use parser::tfrecord::{FOOTER_LEN, HEADER_LEN, RecordError, check_data, check_header};
+use serde::Serialize;
+
+use crate::record::{EventFormat, Record, decode_event};
+
+/// ## Explanation
+/// Reads TFRecord framed records from a file that may still be written to.
+///
+/// A record that isn't completely written yet is not consumed, [`Next::Truncated`] is returned
+/// and the offset stays at its start, so a later call picks it up once the writer has appended
+/// the rest.
+///
+/// ## Note
+/// A record whose data fails its CRC is skipped, its length is covered by a CRC of its own.
+/// A length failing its CRC is fatal: the reader stays at that record, as the next one can't
+/// be located.
+pub struct RecordReader<R> {
+    inner: R,
+    offset: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Next {
+    Record(Vec<u8>),
+    /// The file ends on a record boundary
+    End,
+    /// The file ends inside the record starting at the current offset
+    Truncated,
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Record(RecordError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "{}", e),
+            ReadError::Record(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ReadError {}
+
+impl From<io::Error> for ReadError {
+    fn from(value: io::Error) -> Self {
+        ReadError::Io(value)
+    }
+}
+
+impl<R: Read + Seek> RecordReader<R> {
+    /// Starts reading at `offset`, which must be a record boundary, e.g. an offset returned earlier.
+    pub fn new(mut inner: R, offset: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(offset))?;
+        Ok(Self { inner, offset })
+    }
+
+    /// The offset of the first record not read yet.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn next_record(&mut self) -> Result<Next, ReadError> {
+        let offset = self.offset;
+        let mut header = [0u8; HEADER_LEN];
+        match read_full(&mut self.inner, &mut header)? {
+            0 => return Ok(Next::End),
+            n if n < header.len() => return self.rewind(),
+            _ => {}
+        }
+        let len = match check_header(&header, offset as usize) {
+            Ok(len) => len,
+            Err(e) => {
+                self.inner.seek(SeekFrom::Start(offset))?;
+                return Err(ReadError::Record(e));
+            }
+        };
+
+        let mut data = vec![];
+        (&mut self.inner).take(len).read_to_end(&mut data)?;
+        let mut footer = [0u8; FOOTER_LEN];
+        if (data.len() as u64) < len || read_full(&mut self.inner, &mut footer)? < footer.len() {
+            return self.rewind();
+        }
+
+        self.offset = offset + (HEADER_LEN + FOOTER_LEN) as u64 + len;
+        check_data(&data, &footer, offset as usize).map_err(ReadError::Record)?;
+        Ok(Next::Record(data))
+    }
+
+    fn rewind(&mut self) -> Result<Next, ReadError> {
+        self.inner.seek(SeekFrom::Start(self.offset))?;
+        Ok(Next::Truncated)
+    }
+}
+
+/// Like `read_exact`, but returns how many bytes were read when the input ends early.
+fn read_full(inner: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match inner.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+/// The records appended since the previous poll.
+#[derive(Debug, Default, Serialize)]
+pub struct Batch {
+    pub records: Vec<Record>,
+    /// Where the next poll starts
+    pub offset: u64,
+    /// Whether the file ends inside a record, which is the case while it's being written
+    pub truncated: bool,
+    /// Corrupted records and records failing to decode, they are skipped
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// ## Explanation
+/// Follows an event file of a running job, each [`EventFile::poll`] returns the records
+/// written since the previous one.
+pub struct EventFile {
+    reader: RecordReader<BufReader<File>>,
+    format: EventFormat,
+}
+
+impl EventFile {
+    /// Opens `path` from the beginning, the format is told by the file name.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let format = EventFormat::from_path(&path.as_ref().to_string_lossy());
+        Self::open_at(path, format, 0)
+    }
+
+    /// Resumes reading at an offset returned by a previous [`Batch`].
+    pub fn open_at(path: impl AsRef<Path>, format: EventFormat, offset: u64) -> io::Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(Self { reader: RecordReader::new(file, offset)?, format })
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.reader.offset()
+    }
+
+    pub fn poll(&mut self) -> Batch {
+        let mut batch = Batch::default();
+        loop {
+            match self.reader.next_record() {
+                Ok(Next::Record(data)) => match decode_event(self.format, &data) {
+                    Ok(records) => batch.records.extend(records),
+                    Err(e) => batch.errors.push(format!("undecodable event: {}", e)),
+                },
+                Ok(Next::End) => break,
+                Ok(Next::Truncated) => {
+                    batch.truncated = true;
+                    break;
+                }
+                Err(e @ ReadError::Record(RecordError::DataCrc { .. })) => {
+                    batch.errors.push(e.to_string())
+                }
+                Err(e) => {
+                    batch.errors.push(e.to_string());
+                    break;
+                }
+            }
+        }
+        batch.offset = self.reader.offset();
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use parser::tfrecord::write_record;
+
+    use super::*;
+    use crate::{Value, record::tests::tf_scalar_event};
+
+    #[test]
+    fn test_tail_following() {
+        let mut file = vec![];
+        write_record(&mut file, b"first");
+        let second = file.len();
+        write_record(&mut file, b"second");
+
+        // the writer has flushed only a part of the second record
+        for end in [second + 3, file.len() - 1] {
+            let mut reader = RecordReader::new(Cursor::new(file[..end].to_vec()), 0).unwrap();
+            assert_eq!(Next::Record(b"first".to_vec()), reader.next_record().unwrap());
+            assert_eq!(Next::Truncated, reader.next_record().unwrap());
+            assert_eq!(second as u64, reader.offset());
+
+            // the rest arrives
+            reader.inner.get_mut().extend_from_slice(&file[end..]);
+            assert_eq!(Next::Record(b"second".to_vec()), reader.next_record().unwrap());
+            assert_eq!(Next::End, reader.next_record().unwrap());
+            assert_eq!(file.len() as u64, reader.offset());
+        }
+
+        let mut reader = RecordReader::new(Cursor::new(file.clone()), second as u64).unwrap();
+        assert_eq!(Next::Record(b"second".to_vec()), reader.next_record().unwrap());
+    }
+
+    #[test]
+    fn test_corrupted_records() {
+        let mut file = vec![];
+        write_record(&mut file, b"first");
+        let second = file.len();
+        write_record(&mut file, b"second");
+
+        let mut corrupted = file.clone();
+        corrupted[HEADER_LEN] ^= 1;
+        let mut reader = RecordReader::new(Cursor::new(corrupted), 0).unwrap();
+        assert!(matches!(
+            reader.next_record(),
+            Err(ReadError::Record(RecordError::DataCrc { offset: 0 }))
+        ));
+        assert_eq!(Next::Record(b"second".to_vec()), reader.next_record().unwrap());
+
+        let mut corrupted = file.clone();
+        corrupted[second] ^= 1;
+        let mut reader = RecordReader::new(Cursor::new(corrupted), 0).unwrap();
+        assert_eq!(Next::Record(b"first".to_vec()), reader.next_record().unwrap());
+        for _ in 0..2 {
+            assert!(matches!(
+                reader.next_record(),
+                Err(ReadError::Record(RecordError::LengthCrc { offset })) if offset == second
+            ));
+        }
+    }
+
+    #[test]
+    fn test_poll_growing_file() {
+        let path =
+            std::env::temp_dir().join(format!("events.out.tfevents.{}.poll", std::process::id()));
+        let mut data = vec![];
+        write_record(&mut data, &tf_scalar_event(1, 0.5));
+        write_record(&mut data, b"not an event");
+        let second = data.len();
+        write_record(&mut data, &tf_scalar_event(2, 0.25));
+
+        std::fs::write(&path, &data[..second + 4]).unwrap();
+        let mut file = EventFile::open(&path).unwrap();
+        let batch = file.poll();
+        assert_eq!(
+            vec![Value::Scalar { value: 0.5 }],
+            batch.records.iter().map(|r| r.value.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!((second as u64, true, 1), (batch.offset, batch.truncated, batch.errors.len()));
+
+        std::fs::write(&path, &data).unwrap();
+        let batch = file.poll();
+        assert_eq!(
+            (2, Value::Scalar { value: 0.25 }),
+            (batch.records[0].step, batch.records[0].value.clone())
+        );
+        assert_eq!((data.len() as u64, false), (batch.offset, batch.truncated));
+        assert!(file.poll().records.is_empty());
+
+        // resuming from the offset of a previous batch
+        let batch =
+            EventFile::open_at(&path, EventFormat::TensorFlow, second as u64).unwrap().poll();
+        assert_eq!(1, batch.records.len());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,435 @@
+use parser::{Element, irpb, tensorboard};
+use prost::{DecodeError, Message};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventFormat {
+    /// `tensorboard.Event` of `event.proto`
+    TensorFlow,
+    /// `mindspore.irpb.Event` of `mindspore_summary.proto`
+    MindSpore,
+}
+
+impl EventFormat {
+    /// MindSpore summary files are named `events.out.events.summary.<time>.<host>_MS`,
+    /// anything else is taken as a TensorFlow `events.out.tfevents.*` file.
+    pub fn from_path(path: &str) -> Self {
+        if path.contains(".summary.") { EventFormat::MindSpore } else { EventFormat::TensorFlow }
+    }
+}
+
+/// One summary value of an event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    pub tag: String,
+    pub step: i64,
+    pub wall_time: f64,
+    #[serde(flatten)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Value {
+    Scalar { value: f64 },
+    Histogram(Histogram),
+    Tensor(Tensor),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub num: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sum_squares: Option<f64>,
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bucket {
+    pub left: f64,
+    pub right: f64,
+    pub count: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tensor {
+    /// The name of the framework's data type, e.g. `DT_FLOAT`
+    pub dtype: String,
+    pub dims: Vec<i64>,
+    /// ## Note
+    /// Empty for non-numeric data types, and for values stored in a way the reader doesn't know.
+    pub values: Vec<f64>,
+}
+
+/// Decodes the data of one record into the values of its summary.
+/// Events without a summary (file version, graph, session log...) give nothing.
+///
+/// ## Note
+/// Images, audio and loss landscapes are left out.
+pub fn decode_event(format: EventFormat, data: &[u8]) -> Result<Vec<Record>, DecodeError> {
+    match format {
+        EventFormat::TensorFlow => decode_tf_event(data),
+        EventFormat::MindSpore => decode_ms_event(data),
+    }
+}
+
+fn decode_tf_event(data: &[u8]) -> Result<Vec<Record>, DecodeError> {
+    use tensorboard::summary::value::Value::*;
+
+    let tensorboard::Event { wall_time, step, what, .. } = tensorboard::Event::decode(data)?;
+    let Some(tensorboard::event::What::Summary(summary)) = what else {
+        return Ok(vec![]);
+    };
+
+    let records = summary.value.into_iter().filter_map(|v| {
+        let value = match v.value? {
+            SimpleValue(value) => Value::Scalar { value: value as f64 },
+            Histo(histo) => Value::Histogram(tf_histogram(histo)),
+            Tensor(tensor) => {
+                let plugin = v.metadata.as_ref().and_then(|m| m.plugin_data.as_ref());
+                tf_tensor_value(plugin.map_or("", |p| p.plugin_name.as_str()), tensor)
+            }
+            _ => return None,
+        };
+        Some(Record { tag: v.tag, step, wall_time, value })
+    });
+    Ok(records.collect())
+}
+
+fn tf_histogram(histo: tensorboard::HistogramProto) -> Histogram {
+    // bucket `i` counts the values in `[bucket_limit[i - 1], bucket_limit[i])`
+    let lefts = std::iter::once(histo.min).chain(histo.bucket_limit.iter().copied());
+    let buckets = lefts
+        .zip(&histo.bucket_limit)
+        .zip(&histo.bucket)
+        .map(|((left, &right), &count)| Bucket { left, right, count })
+        .collect();
+    Histogram {
+        min: histo.min,
+        max: histo.max,
+        num: histo.num,
+        sum: Some(histo.sum),
+        sum_squares: Some(histo.sum_squares),
+        buckets,
+    }
+}
+
+/// ## Explanation
+/// TF2 writes every summary as a tensor, the plugin of the metadata tells what it is:
+/// a rank 0 tensor for `scalars`, a `[k, 3]` tensor of `(left, right, count)` for `histograms`.
+fn tf_tensor_value(plugin: &str, tensor: tensorboard::TensorProto) -> Value {
+    let dtype = tensorboard::DataType::try_from(tensor.dtype).unwrap_or_default();
+    let elem = match dtype {
+        tensorboard::DataType::DtBool => Some(Element::Bool),
+        tensorboard::DataType::DtInt8 => Some(Element::I8),
+        tensorboard::DataType::DtInt16 => Some(Element::I16),
+        tensorboard::DataType::DtInt32 => Some(Element::I32),
+        tensorboard::DataType::DtInt64 => Some(Element::I64),
+        tensorboard::DataType::DtUint8 => Some(Element::U8),
+        tensorboard::DataType::DtUint16 => Some(Element::U16),
+        tensorboard::DataType::DtUint32 => Some(Element::U32),
+        tensorboard::DataType::DtUint64 => Some(Element::U64),
+        tensorboard::DataType::DtHalf => Some(Element::F16),
+        tensorboard::DataType::DtBfloat16 => Some(Element::Bf16),
+        tensorboard::DataType::DtFloat => Some(Element::F32),
+        tensorboard::DataType::DtDouble => Some(Element::F64),
+        _ => None,
+    };
+    let dims: Vec<i64> = tensor.tensor_shape.iter().flat_map(|s| &s.dim).map(|d| d.size).collect();
+
+    let values = match elem {
+        Some(elem) if !tensor.tensor_content.is_empty() => elem.read_all(&tensor.tensor_content),
+        Some(Element::F32) => tensor.float_val.iter().map(|&v| v as f64).collect(),
+        Some(Element::F64) => tensor.double_val,
+        Some(elem @ (Element::F16 | Element::Bf16)) => {
+            tensor.half_val.iter().map(|&v| elem.read(&(v as u16).to_le_bytes())).collect()
+        }
+        Some(Element::I64) => tensor.int64_val.iter().map(|&v| v as f64).collect(),
+        Some(Element::U32) => tensor.uint32_val.iter().map(|&v| v as f64).collect(),
+        Some(Element::U64) => tensor.uint64_val.iter().map(|&v| v as f64).collect(),
+        Some(Element::Bool) => tensor.bool_val.iter().map(|&v| v as u8 as f64).collect(),
+        Some(Element::I8 | Element::I16 | Element::I32 | Element::U8 | Element::U16) => {
+            tensor.int_val.iter().map(|&v| v as f64).collect()
+        }
+        _ => vec![],
+    };
+
+    match plugin {
+        "scalars" if dims.is_empty() && values.len() == 1 => Value::Scalar { value: values[0] },
+        "histograms" if dims.len() == 2 && dims[1] == 3 && !values.is_empty() => {
+            let buckets: Vec<Bucket> = values
+                .chunks_exact(3)
+                .map(|b| Bucket { left: b[0], right: b[1], count: b[2] })
+                .collect();
+            Value::Histogram(Histogram {
+                min: buckets.first().map_or(0.0, |b| b.left),
+                max: buckets.last().map_or(0.0, |b| b.right),
+                num: buckets.iter().map(|b| b.count).sum(),
+                sum: None,
+                sum_squares: None,
+                buckets,
+            })
+        }
+        _ => Value::Tensor(Tensor { dtype: dtype.as_str_name().to_string(), dims, values }),
+    }
+}
+
+fn decode_ms_event(data: &[u8]) -> Result<Vec<Record>, DecodeError> {
+    use irpb::summary::value::Value::*;
+
+    let event = irpb::Event::decode(data)?;
+    let (step, wall_time) = (event.step(), event.wall_time);
+    let Some(irpb::event::What::Summary(summary)) = event.what else {
+        return Ok(vec![]);
+    };
+
+    let records = summary.value.into_iter().filter_map(|v| {
+        let value = match v.value? {
+            ScalarValue(value) => Value::Scalar { value: value as f64 },
+            Histogram(histogram) => Value::Histogram(ms_histogram(histogram)),
+            Tensor(tensor) => Value::Tensor(ms_tensor(tensor)),
+            Image(_) | LossLandscape(_) => return None,
+        };
+        Some(Record { tag: v.tag, step, wall_time, value })
+    });
+    Ok(records.collect())
+}
+
+fn ms_histogram(histogram: irpb::summary::Histogram) -> Histogram {
+    let buckets = histogram
+        .buckets
+        .iter()
+        .map(|b| Bucket { left: b.left, right: b.left + b.width, count: b.count as f64 })
+        .collect();
+    Histogram {
+        min: histogram.min(),
+        max: histogram.max(),
+        num: histogram.count() as f64,
+        sum: histogram.sum,
+        sum_squares: None,
+        buckets,
+    }
+}
+
+fn ms_tensor(tensor: irpb::TensorProto) -> Tensor {
+    use irpb::DataType::*;
+
+    let dtype = tensor.data_type();
+    let elem = match dtype {
+        DtBool => Some(Element::Bool),
+        DtInt8 => Some(Element::I8),
+        DtInt16 => Some(Element::I16),
+        DtInt32 => Some(Element::I32),
+        DtInt64 => Some(Element::I64),
+        DtUint8 => Some(Element::U8),
+        DtUint16 => Some(Element::U16),
+        DtUint32 => Some(Element::U32),
+        DtUint64 => Some(Element::U64),
+        DtFloat16 => Some(Element::F16),
+        DtBfloat16 => Some(Element::Bf16),
+        DtFloat32 => Some(Element::F32),
+        DtFloat64 => Some(Element::F64),
+        _ => None,
+    };
+
+    let values = match (elem, &tensor.raw_data) {
+        (Some(elem), Some(raw)) if !raw.is_empty() => elem.read_all(raw),
+        (Some(Element::F32), _) => tensor.float_data.iter().map(|&v| v as f64).collect(),
+        (Some(Element::F64), _) => tensor.double_data,
+        (Some(Element::I64), _) => tensor.int64_data.iter().map(|&v| v as f64).collect(),
+        (Some(Element::U64), _) => tensor.uint64_data.iter().map(|&v| v as f64).collect(),
+        (Some(_), _) => tensor.int32_data.iter().map(|&v| v as f64).collect(),
+        (None, _) => vec![],
+    };
+    Tensor { dtype: dtype.as_str_name().to_string(), dims: tensor.dims, values }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn tf_scalar_event(step: i64, value: f32) -> Vec<u8> {
+        let value = tensorboard::summary::Value {
+            tag: "loss".to_string(),
+            value: Some(tensorboard::summary::value::Value::SimpleValue(value)),
+            ..Default::default()
+        };
+        tf_event(step, value)
+    }
+
+    fn tf_event(step: i64, value: tensorboard::summary::Value) -> Vec<u8> {
+        let event = tensorboard::Event {
+            wall_time: 1.5,
+            step,
+            what: Some(tensorboard::event::What::Summary(tensorboard::Summary {
+                value: vec![value],
+            })),
+            ..Default::default()
+        };
+        event.encode_to_vec()
+    }
+
+    #[test]
+    fn test_tf_values() {
+        use tensorboard::summary::value::Value::*;
+
+        let scalar = tensorboard::summary::Value {
+            tag: "loss".to_string(),
+            value: Some(SimpleValue(0.5)),
+            ..Default::default()
+        };
+        let records = decode_event(EventFormat::TensorFlow, &tf_event(3, scalar)).unwrap();
+        assert_eq!(
+            vec![Record {
+                tag: "loss".to_string(),
+                step: 3,
+                wall_time: 1.5,
+                value: Value::Scalar { value: 0.5 }
+            }],
+            records
+        );
+
+        let histo = tensorboard::HistogramProto {
+            min: -1.0,
+            max: 2.0,
+            num: 3.0,
+            sum: 1.0,
+            sum_squares: 6.0,
+            bucket_limit: vec![0.0, 2.0],
+            bucket: vec![1.0, 2.0],
+        };
+        let histo = tensorboard::summary::Value {
+            tag: "weight".to_string(),
+            value: Some(Histo(histo)),
+            ..Default::default()
+        };
+        let records = decode_event(EventFormat::TensorFlow, &tf_event(4, histo)).unwrap();
+        let Value::Histogram(histogram) = &records[0].value else { panic!() };
+        assert_eq!(
+            vec![
+                Bucket { left: -1.0, right: 0.0, count: 1.0 },
+                Bucket { left: 0.0, right: 2.0, count: 2.0 }
+            ],
+            histogram.buckets
+        );
+
+        // TF2 scalars are rank 0 tensors
+        let metadata = tensorboard::SummaryMetadata {
+            plugin_data: Some(tensorboard::summary_metadata::PluginData {
+                plugin_name: "scalars".to_string(),
+                content: vec![],
+            }),
+            ..Default::default()
+        };
+        let tensor = tensorboard::TensorProto {
+            dtype: tensorboard::DataType::DtFloat as i32,
+            tensor_shape: Some(Default::default()),
+            tensor_content: 0.25f32.to_le_bytes().to_vec(),
+            ..Default::default()
+        };
+        let scalar = tensorboard::summary::Value {
+            tag: "accuracy".to_string(),
+            metadata: Some(metadata),
+            value: Some(Tensor(tensor.clone())),
+            ..Default::default()
+        };
+        let records = decode_event(EventFormat::TensorFlow, &tf_event(5, scalar)).unwrap();
+        assert_eq!(Value::Scalar { value: 0.25 }, records[0].value);
+
+        // a half tensor without a plugin stays a tensor
+        let tensor = tensorboard::TensorProto {
+            dtype: tensorboard::DataType::DtHalf as i32,
+            tensor_content: vec![],
+            half_val: vec![0x3c00, 0xc000],
+            ..tensor
+        };
+        let value =
+            tensorboard::summary::Value { value: Some(Tensor(tensor)), ..Default::default() };
+        let records = decode_event(EventFormat::TensorFlow, &tf_event(6, value)).unwrap();
+        let expected =
+            super::Tensor { dtype: "DT_HALF".to_string(), dims: vec![], values: vec![1.0, -2.0] };
+        assert_eq!(Value::Tensor(expected), records[0].value);
+
+        let tensor = tensorboard::TensorProto {
+            dtype: tensorboard::DataType::DtUint64 as i32,
+            uint64_val: vec![7, 1 << 40],
+            ..Default::default()
+        };
+        let value =
+            tensorboard::summary::Value { value: Some(Tensor(tensor)), ..Default::default() };
+        let records = decode_event(EventFormat::TensorFlow, &tf_event(7, value)).unwrap();
+        let Value::Tensor(tensor) = &records[0].value else { panic!() };
+        assert_eq!(vec![7.0, (1u64 << 40) as f64], tensor.values);
+
+        let version = tensorboard::Event {
+            what: Some(tensorboard::event::What::FileVersion("brain.Event:2".to_string())),
+            ..Default::default()
+        };
+        assert!(
+            decode_event(EventFormat::TensorFlow, &version.encode_to_vec()).unwrap().is_empty()
+        );
+    }
+
+    #[test]
+    fn test_ms_values() {
+        use irpb::summary::value::Value::*;
+
+        let histogram = irpb::summary::Histogram {
+            buckets: vec![irpb::summary::histogram::Bucket { left: -1.0, width: 0.5, count: 4 }],
+            min: Some(-1.0),
+            max: Some(-0.5),
+            count: Some(4),
+            ..Default::default()
+        };
+        let tensor = irpb::TensorProto {
+            dims: vec![2],
+            data_type: Some(irpb::DataType::DtInt32 as i32),
+            raw_data: Some([7i32.to_le_bytes(), (-7i32).to_le_bytes()].concat()),
+            ..Default::default()
+        };
+        let values = vec![
+            irpb::summary::Value { tag: "loss".to_string(), value: Some(ScalarValue(2.0)) },
+            irpb::summary::Value { tag: "weight".to_string(), value: Some(Histogram(histogram)) },
+            irpb::summary::Value { tag: "label".to_string(), value: Some(Tensor(tensor)) },
+        ];
+        let event = irpb::Event {
+            wall_time: 1.0,
+            step: Some(8),
+            what: Some(irpb::event::What::Summary(irpb::Summary { value: values })),
+        };
+
+        let records = decode_event(EventFormat::MindSpore, &event.encode_to_vec()).unwrap();
+        assert_eq!(3, records.len());
+        assert!(records.iter().all(|r| r.step == 8 && r.wall_time == 1.0));
+        assert_eq!(Value::Scalar { value: 2.0 }, records[0].value);
+        let Value::Histogram(histogram) = &records[1].value else { panic!() };
+        assert_eq!((-1.0, 4.0, None), (histogram.min, histogram.num, histogram.sum));
+        assert_eq!(vec![Bucket { left: -1.0, right: -0.5, count: 4.0 }], histogram.buckets);
+        let expected =
+            super::Tensor { dtype: "DT_INT32".to_string(), dims: vec![2], values: vec![7.0, -7.0] };
+        assert_eq!(Value::Tensor(expected), records[2].value);
+
+        let json = serde_json::to_value(&records[0]).unwrap();
+        assert_eq!(
+            serde_json::json!({"tag": "loss", "step": 8, "wall_time": 1.0, "type": "scalar", "value": 2.0}),
+            json
+        );
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            EventFormat::MindSpore,
+            EventFormat::from_path("events.out.events.summary.1700000000.host_MS")
+        );
+        assert_eq!(
+            EventFormat::TensorFlow,
+            EventFormat::from_path("events.out.tfevents.1700000000.host")
+        );
+    }
+}
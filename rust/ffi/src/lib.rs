@@ -1,11 +1,25 @@
 mod geometry;
 mod parse_layout;
 
-use std::ffi::{c_char, CStr, CString};
+use std::{
+    ffi::{c_char, CStr, CString},
+    io,
+};
 
 use parse_layout::*;
-use serde::Deserialize;
-use subgraph::{subgraphs_geir, subgraphs_mindir, subgraphs_onnx};
+use parser::{
+    detect_pb_format, is_text_format, parse_anf_ir_model, parse_geir_model, parse_mindir_model,
+    parse_onnx_model, parse_tf_model, preview_onnx_tensor, with_attr_format, AttrFormat, Model,
+    TensorRef,
+};
+use serde::{Deserialize, Serialize};
+use subgraph::{
+    export_pattern, fusion_report::ReportFormat, fusion_report_anf_ir, fusion_report_geir,
+    fusion_report_mindir, fusion_report_onnx, fusion_report_tf, pattern::PatternFormat,
+    query::QueryPattern, query_anf_ir, query_geir, query_mindir, query_onnx, query_tf,
+    subgraphs_anf_ir, subgraphs_geir, subgraphs_mindir, subgraphs_models, subgraphs_onnx,
+    subgraphs_tf, QueryOptions, SubgraphOptions,
+};
 
 use self::FileType::*;
 
@@ -13,22 +27,62 @@ enum FileType {
     ONNX,
     MindIR,
     GeIR,
+    AnfIr,
+    TensorFlow,
     Unsupported,
 }
 
 impl From<&str> for FileType {
     fn from(value: &str) -> Self {
+        if is_text_format(value) {
+            // `model.onnx.pbtxt` or `graph.pb.pbtxt` names its format,
+            // GE dumps such as `ge_onnx_xxx_Build.pbtxt` don't
+            let stem = &value[..value.rfind('.').unwrap()];
+            return match FileType::from_name(stem) {
+                Unsupported => GeIR,
+                file_type => file_type,
+            };
+        }
+        if value.ends_with(".pb") {
+            // ONNX models and ANF IR graph files are saved as `.pb` too
+            return match detect_pb_format(value) {
+                Ok("onnx") => ONNX,
+                Ok("anf_ir") => AnfIr,
+                Ok("tensorflow") => TensorFlow,
+                _ => Unsupported,
+            };
+        }
+        FileType::from_name(value)
+    }
+}
+
+impl FileType {
+    fn from_name(value: &str) -> Self {
         if value.ends_with(".onnx") {
             return ONNX;
         } else if value.ends_with(".mindir") {
             return MindIR;
-        } else if value.ends_with(".geir") {
+        } else if value.ends_with(".geir") || value.ends_with(".om") {
+            // offline models wrap the same `ModelDef`, see `parser::parse_om_model`
             return GeIR;
+        } else if value.ends_with(".ms_graph") || value.contains(".summary.") {
+            // MindSpore summary files are named `events.out.events.summary.<time>.<host>_MS`
+            return AnfIr;
+        } else if value.ends_with(".pb") || value.contains("tfevents") {
+            return TensorFlow;
         }
         Unsupported
     }
 }
 
+/// Every result is serialized through here, attributes anywhere in it in the pre-formatted
+/// shapes the frontend renders as they are, see [`AttrFormat::Compact`].
+fn to_json<T: Serialize>(ret: &T) -> String {
+    with_attr_format(AttrFormat::Compact, || serde_json::to_string(ret)).unwrap()
+}
+
+/// Lays out a model, or returns `null` if it can't be read,
+/// [`layout_with_options_rs`] returns `{"error": "..."}` saying why.
 #[unsafe(no_mangle)]
 pub extern "C" fn layout_rs(path: *const c_char) -> *mut c_char {
     let path = unsafe {
@@ -40,10 +94,50 @@ pub extern "C" fn layout_rs(path: *const c_char) -> *mut c_char {
         ONNX => layout_onnx(path),
         MindIR => layout_mindir(path),
         GeIR => layout_geir(path),
-        Unsupported => unimplemented!(),
+        AnfIr => layout_anf_ir(path),
+        TensorFlow => layout_tf(path),
+        Unsupported => Err(unsupported()),
+    };
+
+    let ret = ret.ok();
+
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+#[derive(Deserialize)]
+struct LayoutParams {
+    path: String,
+    /// `"typed"` (default) or `"compact"`, the latter is what [`layout_rs`] returns
+    #[serde(default)]
+    attr_format: AttrFormat,
+}
+
+/// Same as [`layout_rs`], but attributes are serialized in the requested [`AttrFormat`],
+/// and a model that can't be read gives `{"error": "..."}` rather than `null`.
+#[unsafe(no_mangle)]
+pub extern "C" fn layout_with_options_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
     };
 
-    let json_string = serde_json::to_string(&ret).unwrap();
+    let LayoutParams { path, attr_format } = serde_json::from_str::<LayoutParams>(data).unwrap();
+    let path = path.as_str();
+
+    let ret = Response::from(match FileType::from(path) {
+        ONNX => layout_onnx(path),
+        MindIR => layout_mindir(path),
+        GeIR => layout_geir(path),
+        AnfIr => layout_anf_ir(path),
+        TensorFlow => layout_tf(path),
+        Unsupported => Err(unsupported()),
+    });
+
+    let json_string = with_attr_format(attr_format, || serde_json::to_string(&ret)).unwrap();
 
     let c_string = CString::new(json_string).unwrap();
 
@@ -55,6 +149,8 @@ struct SubgraphParams {
     path: String,
     min: usize,
     max: usize,
+    #[serde(flatten)]
+    options: SubgraphOptions,
 }
 
 #[unsafe(no_mangle)]
@@ -64,17 +160,243 @@ pub extern "C" fn subgraph_rs(data: *const c_char) -> *mut c_char {
         CStr::from_ptr(data).to_str().unwrap()
     };
 
-    let SubgraphParams { path, min, max } = serde_json::from_str::<SubgraphParams>(data).unwrap();
+    let SubgraphParams { path, min, max, options } =
+        serde_json::from_str::<SubgraphParams>(data).unwrap();
     let path = path.as_str();
 
     let ret = match FileType::from(path) { 
-        ONNX => subgraphs_onnx(path, min, max),
-        MindIR => subgraphs_mindir(path, min, max),
-        GeIR => subgraphs_geir(path, min, max),
-        Unsupported => unimplemented!()
+        ONNX => subgraphs_onnx(path, min, max, options),
+        MindIR => subgraphs_mindir(path, min, max, options),
+        GeIR => subgraphs_geir(path, min, max, options),
+        AnfIr => subgraphs_anf_ir(path, min, max, options),
+        TensorFlow => subgraphs_tf(path, min, max, options),
+        Unsupported => None
+    };
+
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "unsupported file type")
+}
+
+/// Errors name the file, the models compared by [`diff_rs`] may be in different files.
+fn parse_model(path: &str) -> io::Result<Model> {
+    let model = match FileType::from(path) {
+        ONNX => parse_onnx_model(path),
+        MindIR => parse_mindir_model(path),
+        GeIR => parse_geir_model(path),
+        AnfIr => parse_anf_ir_model(path),
+        TensorFlow => parse_tf_model(path),
+        Unsupported => Err(unsupported()),
+    };
+    model.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+#[derive(Deserialize)]
+struct MultiSubgraphParams {
+    paths: Vec<String>,
+    /// minimum number of models a pattern must appear in
+    min: usize,
+    max: usize,
+    #[serde(flatten)]
+    options: SubgraphOptions,
+}
+
+/// Mine blocks shared by several models, each model may be any of the supported formats.
+/// Returns `null` if any model fails to parse.
+#[unsafe(no_mangle)]
+pub extern "C" fn subgraph_multi_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
+    };
+
+    let MultiSubgraphParams { paths, min, max, options } =
+        serde_json::from_str::<MultiSubgraphParams>(data).unwrap();
+
+    let models = paths
+        .into_iter()
+        .map(|path| parse_model(&path).ok().map(|model| (path, model)))
+        .collect::<Option<Vec<_>>>();
+
+    let ret = models.and_then(|models| subgraphs_models(models, min, max, options));
+
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    path: String,
+    /// `{"json": {...}}` or `{"dfs_code": "0 1 Conv <NIL> Relu"}`
+    pattern: QueryPattern,
+    #[serde(flatten)]
+    options: QueryOptions,
+}
+
+/// Find every occurrence of a user-supplied pattern in a model.
+/// Returns `null` if the model or the pattern is invalid.
+#[unsafe(no_mangle)]
+pub extern "C" fn query_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
+    };
+
+    let QueryParams { path, pattern, options } = serde_json::from_str::<QueryParams>(data).unwrap();
+    let path = path.as_str();
+
+    let ret = match FileType::from(path) {
+        ONNX => query_onnx(path, &pattern, options),
+        MindIR => query_mindir(path, &pattern, options),
+        GeIR => query_geir(path, &pattern, options),
+        AnfIr => query_anf_ir(path, &pattern, options),
+        TensorFlow => query_tf(path, &pattern, options),
+        Unsupported => None,
     };
 
-    let json_string = serde_json::to_string(&ret).unwrap();
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    /// same as [`QueryParams::pattern`]
+    pattern: QueryPattern,
+    /// `"json"` (default) or `"dot"`
+    #[serde(default)]
+    format: PatternFormat,
+}
+
+/// Export a pattern as a standalone JSON pattern file or Graphviz DOT, both carrying the
+/// canonical pattern id. Returns `null` if the pattern is invalid.
+#[unsafe(no_mangle)]
+pub extern "C" fn export_pattern_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
+    };
+
+    let ExportParams { pattern, format } = serde_json::from_str::<ExportParams>(data).unwrap();
+
+    let ret = match export_pattern(&pattern, format) {
+        Ok(content) => Some(content),
+        Err(e) => {
+            eprintln!("Failed to export pattern: {}", e);
+            None
+        }
+    };
+
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+#[derive(Deserialize)]
+struct FusionReportParams {
+    path: String,
+    min: usize,
+    max: usize,
+    /// `"json"` (default) or `"csv"`
+    #[serde(default)]
+    format: ReportFormat,
+    #[serde(flatten)]
+    options: SubgraphOptions,
+}
+
+/// Mine a model and rank the patterns as fusion candidates by coverage.
+/// Returns the report rendered in the requested format, or `null` if the model fails to parse.
+#[unsafe(no_mangle)]
+pub extern "C" fn fusion_report_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
+    };
+
+    let FusionReportParams { path, min, max, format, options } =
+        serde_json::from_str::<FusionReportParams>(data).unwrap();
+    let path = path.as_str();
+
+    let ret = match FileType::from(path) {
+        ONNX => fusion_report_onnx(path, min, max, options),
+        MindIR => fusion_report_mindir(path, min, max, options),
+        GeIR => fusion_report_geir(path, min, max, options),
+        AnfIr => fusion_report_anf_ir(path, min, max, options),
+        TensorFlow => fusion_report_tf(path, min, max, options),
+        Unsupported => None,
+    };
+    let ret = ret.map(|report| report.export(format));
+
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+fn default_preview_limit() -> usize {
+    16
+}
+
+#[derive(Deserialize)]
+struct PreviewParams {
+    path: String,
+    /// `{"kind": "initializer", "name": ...}` or `{"kind": "attribute", "node": ..., "attribute": ...}`
+    tensor: TensorRef,
+    /// How many leading elements to return, 16 by default
+    #[serde(default = "default_preview_limit")]
+    limit: usize,
+}
+
+/// Preview the contents of an initializer or a tensor attribute, only ONNX models carry them.
+/// Returns `null` if the tensor isn't found or can't be decoded.
+#[unsafe(no_mangle)]
+pub extern "C" fn tensor_preview_rs(data: *const c_char) -> *mut c_char {
+    let data = unsafe {
+        assert!(!data.is_null());
+        CStr::from_ptr(data).to_str().unwrap()
+    };
+
+    let PreviewParams { path, tensor, limit } = serde_json::from_str::<PreviewParams>(data).unwrap();
+    let path = path.as_str();
+
+    let ret = match FileType::from(path) {
+        ONNX => preview_onnx_tensor(path, &tensor, limit),
+        _ => None,
+    };
+
+    let json_string = to_json(&ret);
+
+    let c_string = CString::new(json_string).unwrap();
+
+    c_string.into_raw()
+}
+
+/// Compare two versions of a model, which may be in different formats.
+/// Returns the [`parser::ModelDiff`] with a combined layout, or an error if either fails to parse.
+#[unsafe(no_mangle)]
+pub extern "C" fn diff_rs(path_a: *const c_char, path_b: *const c_char) -> *mut c_char {
+    let (path_a, path_b) = unsafe {
+        assert!(!path_a.is_null() && !path_b.is_null());
+        (CStr::from_ptr(path_a).to_str().unwrap(), CStr::from_ptr(path_b).to_str().unwrap())
+    };
+
+    let ret = Response::from(layout_diff(path_a, path_b));
+
+    let json_string = to_json(&ret);
 
     let c_string = CString::new(json_string).unwrap();
 
@@ -1,10 +1,19 @@
+use std::io;
+
 use ahash::{HashMap, HashMapExt};
 use layout::{layout, Graph, GraphEdge, GraphNode, Key, KeyCodecExt};
-use parser::{parse_geir_model, parse_mindir_model, parse_onnx_model, Model};
-use serde::Serialize;
+use parser::{
+    diff_models, estimate_cost, infer_shapes, parse_anf_ir_model, parse_geir_model,
+    parse_mindir_model, parse_onnx_model, parse_tf_model, with_attr_source, CostReport, EdgeKind,
+    Model, ModelDiff, ModelInfo, ShapeReport,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 use smartstring::alias::String;
 
-use crate::geometry::{calc_edge_bounding, line_curve, Point};
+use crate::{
+    geometry::{calc_edge_bounding, line_curve, Point},
+    parse_model,
+};
 
 #[allow(non_snake_case)]
 #[derive(Serialize)]
@@ -58,51 +67,55 @@ impl RenderEdge {
 }
 
 fn layout_model(model: &Model) -> (Vec<RenderNode>, Vec<RenderEdge>) {
+    let nodes = model.nodes.iter().map(|(id, node)| (id, &node.opType));
+
+    // inputs and outputs are edges when they name nodes, as in MindIR and GEIR
+    let io_edges = model.nodes.iter().flat_map(|(id, node)| {
+        let inputs = node.input.iter().map(move |source| (source, id));
+        inputs.chain(node.output.iter().map(move |sink| (id, sink)))
+    });
+    // control dependencies, as in TensorFlow, are only kept as typed edges
+    let control_edges = model.typed_edges.iter().filter(|edge| edge.kind == EdgeKind::Control);
+    let edges = model.edges.iter().map(|(source, target)| (source, target)).chain(io_edges);
+    let edges = edges.chain(control_edges.map(|edge| (&edge.source, &edge.target)));
+
+    layout_graph(nodes, edges)
+}
+
+/// Lays out nodes given by id and op type, edges with an end that isn't a node are ignored.
+fn layout_graph<'a>(
+    nodes: impl Iterator<Item = (&'a String, &'a String)>,
+    edges: impl Iterator<Item = (&'a String, &'a String)>,
+) -> (Vec<RenderNode>, Vec<RenderEdge>) {
     let mut g = Graph::default();
 
     let mut counter: Key = 0;
 
     let mut name_key = HashMap::new();
     let mut key_name = HashMap::new();
+    let mut op_types = HashMap::new();
 
-    for key in model.nodes.keys() {
-        name_key.insert(key.clone(), counter);
-        key_name.insert(counter, key.clone());
+    for (id, op_type) in nodes {
+        name_key.insert(id.clone(), counter);
+        key_name.insert(counter, id.clone());
+        op_types.insert(counter, op_type.clone());
+        g.set_node(counter, Some(GraphNode::of(0.0, 0.0, 100.0, 30.0)));
         counter += 1;
     }
 
-    for (source, target) in &model.edges {
+    for (source, target) in edges {
         if let (Some(&s), Some(&t)) = (name_key.get(source), name_key.get(target)) {
             g.set_edge(s, t, Some(GraphEdge::of(s, t)));
         }
     }
 
-    for (id, node) in &model.nodes {
-        let key = name_key[id];
-        g.set_node(key, Some(GraphNode::of(0.0, 0.0, 100.0, 30.0)));
-
-        for source in &node.input {
-            if let Some(&source) = name_key.get(source) {
-                g.set_edge(source, key, Some(GraphEdge::of(source, key)));
-            }
-        }
-
-        for sink in &node.output {
-            if let Some(&sink) = name_key.get(sink) {
-                g.set_edge(key, sink, Some(GraphEdge::of(key, sink)));
-            }
-        }
-    }
-
     layout(&mut g);
 
     let mut nodes = Vec::with_capacity(g.nodes.len());
     let mut edges = Vec::with_capacity(g.edge_values.len());
 
     for (id, node) in &g.nodes {
-        let node_id = &key_name[id];
-        let op_type = model.nodes[node_id].opType.clone();
-        let node = RenderNode::new(node_id.clone(), node, op_type);
+        let node = RenderNode::new(key_name[id].clone(), node, op_types[id].clone());
         nodes.push(node);
     }
 
@@ -118,19 +131,64 @@ fn layout_model(model: &Model) -> (Vec<RenderNode>, Vec<RenderEdge>) {
     (nodes, edges)
 }
 
-#[derive(Serialize)]
 pub struct LayoutRet {
+    info: ModelInfo,
     model: Model,
     nodes: Vec<RenderNode>,
     edges: Vec<RenderEdge>,
+    /// Estimated per node, name scope and for the whole model, to colour nodes by cost
+    cost: CostReport,
+    /// Nodes whose output shapes couldn't be inferred, and why
+    shapes: ShapeReport,
+}
+
+/// Attributes are serialized in the shapes of the model's format, see [`with_attr_source`].
+impl Serialize for LayoutRet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        with_attr_source(&self.info.format, || {
+            let mut state = serializer.serialize_struct("LayoutRet", 6)?;
+            state.serialize_field("info", &self.info)?;
+            state.serialize_field("model", &self.model)?;
+            state.serialize_field("nodes", &self.nodes)?;
+            state.serialize_field("edges", &self.edges)?;
+            state.serialize_field("cost", &self.cost)?;
+            state.serialize_field("shapes", &self.shapes)?;
+            state.end()
+        })
+    }
+}
+
+/// What the frontend receives: the result, or `{"error": "..."}` saying why the model couldn't
+/// be read, e.g. the line and column of a mistake in a text dump.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Response<T> {
+    Ok(T),
+    Error { error: std::string::String },
+}
+
+impl<T> From<io::Result<T>> for Response<T> {
+    fn from(result: io::Result<T>) -> Self {
+        match result {
+            Ok(ret) => Response::Ok(ret),
+            Err(e) => Response::Error { error: e.to_string() },
+        }
+    }
 }
 
 macro_rules! layout_command {
     ($func_name:ident, $parse_func:ident) => {
-        pub fn $func_name(path: &str) -> Option<LayoutRet> {
-            let model = $parse_func(path)?;
+        pub fn $func_name(path: &str) -> io::Result<LayoutRet> {
+            let mut model = $parse_func(path)?;
+            let info = std::mem::take(&mut model.info);
+            // the cost model needs the shapes of the tensors
+            let shapes = infer_shapes(&mut model);
+            let cost = estimate_cost(&model);
             let (nodes, edges) = layout_model(&model);
-            Some(LayoutRet { model, nodes, edges })
+            Ok(LayoutRet { info, model, nodes, edges, cost, shapes })
         }
     };
 }
@@ -138,3 +196,41 @@ macro_rules! layout_command {
 layout_command!(layout_mindir, parse_mindir_model);
 layout_command!(layout_geir, parse_geir_model);
 layout_command!(layout_onnx, parse_onnx_model);
+layout_command!(layout_anf_ir, parse_anf_ir_model);
+layout_command!(layout_tf, parse_tf_model);
+
+#[derive(Serialize)]
+pub struct DiffRet {
+    #[serde(serialize_with = "serialize_info")]
+    a: ModelInfo,
+    #[serde(serialize_with = "serialize_info")]
+    b: ModelInfo,
+    diff: ModelDiff,
+    /// Nodes of both versions, a node matched in both is laid out once, under its id in `b`
+    nodes: Vec<RenderNode>,
+    edges: Vec<RenderEdge>,
+}
+
+/// The two models may be in different formats, each keeps the attribute shapes of its own.
+fn serialize_info<S>(info: &ModelInfo, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    with_attr_source(&info.format, || info.serialize(serializer))
+}
+
+/// Compares two versions of a model, which may be in different formats,
+/// and lays out their union so that matched nodes share coordinates.
+pub fn layout_diff(path_a: &str, path_b: &str) -> io::Result<DiffRet> {
+    let mut a = parse_model(path_a)?;
+    let mut b = parse_model(path_b)?;
+    let diff = diff_models(&a, &b);
+
+    let nodes = diff.nodes.iter().map(|node| (&node.id, &node.op_type));
+    let edges = diff.edges.iter().map(|edge| (&edge.source, &edge.target));
+    let (nodes, edges) = layout_graph(nodes, edges);
+
+    let a = std::mem::take(&mut a.info);
+    let b = std::mem::take(&mut b.info);
+    Ok(DiffRet { a, b, diff, nodes, edges })
+}
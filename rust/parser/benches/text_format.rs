@@ -0,0 +1,116 @@
+//! Reads a synthetic multi-MB ONNX text dump, and the same model in binary for comparison
+//!
+//! ```text
+//! cargo bench -p parser --bench text_format -- [nodes]
+//! ```
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use parser::{parse_onnx_model, text_format};
+
+// every node carries the attributes of a convolution, every tenth one a weight with its data
+fn synthetic_dump(nodes: usize) -> String {
+    let mut text = String::from(
+        "ir_version: 8\nproducer_name: \"bench\"\nopset_import {\n  version: 17\n}\ngraph {\n  name: \"synthetic\"\n",
+    );
+    for i in 0..nodes {
+        let _ = write!(
+            text,
+            r#"  node {{
+    input: "t{i}"
+    input: "w{w}"
+    output: "t{o}"
+    name: "block_{b}/Conv_{i}"
+    op_type: "Conv"
+    attribute {{
+      name: "kernel_shape"
+      ints: 3
+      ints: 3
+      type: INTS
+    }}
+    attribute {{
+      name: "pads"
+      ints: [1, 1, 1, 1]
+      type: INTS
+    }}
+    attribute {{
+      name: "alpha"
+      f: 0.0001
+      type: FLOAT
+    }}
+    doc_string: "layer \"{i}\"\n"
+  }}
+"#,
+            w = i / 10,
+            o = i + 1,
+            b = i / 100,
+        );
+        if i % 10 == 0 {
+            let _ = write!(
+                text,
+                "  initializer {{\n    dims: 8\n    dims: 8\n    data_type: 1\n    name: \"w{}\"\n",
+                i / 10
+            );
+            for j in 0..64 {
+                let _ = writeln!(text, "    float_data: {}", (i + j) as f32 * 0.001);
+            }
+            text.push_str("  }\n");
+        }
+    }
+    text.push_str("}\n");
+    text
+}
+
+fn measure<T>(mut f: impl FnMut() -> T) -> Duration {
+    // best of a few runs, the first one also warms up the page cache and the schema
+    (0..5)
+        .map(|_| {
+            let now = Instant::now();
+            black_box(f());
+            now.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn mb_per_s(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64()
+}
+
+fn main() {
+    let nodes = std::env::args().skip(1).find_map(|a| a.parse().ok()).unwrap_or(20_000);
+
+    let text = synthetic_dump(nodes);
+    let binary = text_format::to_binary("onnx.ModelProto", &text).unwrap();
+    let dir = std::env::temp_dir();
+    let text_path = dir.join(format!("text_format_bench_{}.pbtxt", std::process::id()));
+    let binary_path = text_path.with_extension("onnx");
+    std::fs::write(&text_path, &text).unwrap();
+    std::fs::write(&binary_path, &binary).unwrap();
+
+    let elapsed = measure(|| text_format::to_binary("onnx.ModelProto", &text).unwrap());
+    println!(
+        "encoding: {} nodes, {:.2} MiB of text, {:?}, {:.0} MiB/s",
+        nodes,
+        text.len() as f64 / (1024.0 * 1024.0),
+        elapsed,
+        mb_per_s(text.len(), elapsed)
+    );
+
+    let text_model = measure(|| parse_onnx_model(text_path.to_str().unwrap()).unwrap());
+    let binary_model = measure(|| parse_onnx_model(binary_path.to_str().unwrap()).unwrap());
+    println!(
+        "parse_onnx_model: text {:?} ({:.0} MiB/s), binary {:?} ({:.2} MiB), {:.1}x",
+        text_model,
+        mb_per_s(text.len(), text_model),
+        binary_model,
+        binary.len() as f64 / (1024.0 * 1024.0),
+        text_model.as_secs_f64() / binary_model.as_secs_f64()
+    );
+
+    let _ = std::fs::remove_file(text_path);
+    let _ = std::fs::remove_file(binary_path);
+}
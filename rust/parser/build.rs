@@ -3,7 +3,23 @@ use prost_build::Config;
 fn main() {
     let mut config = Config::new();
     config.type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+    // decoded from a `Bytes` buffer, tensor payloads are slices of the file instead of copies
+    config.bytes([".onnx.TensorProto.raw_data"]);
+    // the text format reader is driven by the descriptors and needs the full name of each message
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    config.file_descriptor_set_path(out_dir.join("file_descriptor_set.bin"));
+    config.enable_type_names();
     config
-        .compile_protos(&["proto/mindir.proto", "proto/geir.proto", "proto/onnx.proto"], &["proto"])
+        .compile_protos(
+            &[
+                "proto/mindir.proto",
+                "proto/geir.proto",
+                "proto/onnx.proto",
+                "proto/mindspore_summary.proto",
+                "proto/event.proto",
+                "proto/graph.proto",
+            ],
+            &["proto"],
+        )
         .unwrap();
 }
@@ -0,0 +1,526 @@
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter, Write},
+    thread::LocalKey,
+};
+
+use serde::{
+    Deserialize, Serialize, Serializer,
+    ser::{SerializeMap, SerializeStruct},
+};
+use smartstring::alias::String;
+
+use crate::{AttrValue, AttrValue::*, TensorType};
+
+/// How [`AttrValue`]s are serialized:
+/// - `Typed`: `{"type": "ints", "value": [1, 1]}`, numbers stay numbers
+/// - `Compact`: every value pre-rendered with [`Display`], e.g.
+///   `{"type": "string-like", "value": "1, 1"}`, which is the shape the frontend was built on.
+///   The shapes differ between formats, see [`with_attr_source`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttrFormat {
+    #[default]
+    Typed,
+    Compact,
+}
+
+/// The formats whose [`AttrFormat::Compact`] shapes predate typed values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Onnx,
+    MindIr,
+    GeIr,
+    /// Formats read since values are typed, and values of an unknown format
+    Other,
+}
+
+impl From<&str> for Source {
+    fn from(format: &str) -> Self {
+        match format {
+            "onnx" => Source::Onnx,
+            "mindir" => Source::MindIr,
+            "geir" | "om" => Source::GeIr,
+            _ => Source::Other,
+        }
+    }
+}
+
+thread_local! {
+    static ATTR_FORMAT: Cell<AttrFormat> = const { Cell::new(AttrFormat::Typed) };
+    static ATTR_SOURCE: Cell<Source> = const { Cell::new(Source::Other) };
+}
+
+/// Sets `key` to `value` while `f` runs, the previous value is restored even if it panics.
+fn scoped<V: Copy + 'static, T>(
+    key: &'static LocalKey<Cell<V>>,
+    value: V,
+    f: impl FnOnce() -> T,
+) -> T {
+    struct Restore<V: Copy + 'static>(&'static LocalKey<Cell<V>>, V);
+
+    impl<V: Copy + 'static> Drop for Restore<V> {
+        fn drop(&mut self) {
+            self.0.set(self.1);
+        }
+    }
+
+    let _restore = Restore(key, key.replace(value));
+    f()
+}
+
+/// Serializes every [`AttrValue`] reached inside `f` in the given format.
+///
+/// ```
+/// use parser::{AttrFormat, AttrValue, with_attr_format};
+///
+/// let value = AttrValue::Ints(vec![1, 1]);
+/// let json = with_attr_format(AttrFormat::Compact, || serde_json::to_string(&value));
+/// assert_eq!(r#"{"type":"string-like","value":"1, 1"}"#, json.unwrap());
+/// ```
+///
+/// ## Note
+/// The format is a thread-local setting, so attributes nested in derived structs such as
+/// [`crate::Node`] follow it without threading an option through every type.
+/// The previous format is restored when `f` returns, even if it panics.
+pub fn with_attr_format<T>(format: AttrFormat, f: impl FnOnce() -> T) -> T {
+    scoped(&ATTR_FORMAT, format, f)
+}
+
+/// Serializes every [`AttrValue`] reached inside `f` as read from a model of the given
+/// [`crate::ModelInfo::format`], which only matters for [`AttrFormat::Compact`].
+///
+/// ```
+/// use parser::{AttrFormat, AttrValue, with_attr_format, with_attr_source};
+///
+/// let value = AttrValue::Strs(vec!["a".into(), "b".into()]);
+/// let json = with_attr_format(AttrFormat::Compact, || {
+///     with_attr_source("onnx", || serde_json::to_string(&value))
+/// });
+/// assert_eq!(r#"{"type":"string-like","value":"a;b"}"#, json.unwrap());
+/// ```
+///
+/// ## Note
+/// ONNX, MindIR and GEIR attributes were sent in shapes of their own before values were typed,
+/// and the frontend still relies on them, e.g. ONNX string lists were a single `;`-separated
+/// string while GEIR ones were arrays, and only GEIR tensors were `tensor-val`s.
+/// Formats read since then, and values of an unknown format, follow the shapes of GEIR,
+/// except that only lists of number lists and of graphs are arrays.
+pub fn with_attr_source<T>(format: &str, f: impl FnOnce() -> T) -> T {
+    scoped(&ATTR_SOURCE, Source::from(format), f)
+}
+
+impl Serialize for AttrValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match ATTR_FORMAT.get() {
+            AttrFormat::Typed => serialize_typed(self, serializer),
+            AttrFormat::Compact => serialize_compact(self, serializer),
+        }
+    }
+}
+
+/// Serializes a value, including everything nested in it, in [`AttrFormat::Compact`]
+/// regardless of the current format.
+pub struct Compact<'a>(pub &'a AttrValue);
+
+impl Serialize for Compact<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        with_attr_format(AttrFormat::Compact, || serialize_compact(self.0, serializer))
+    }
+}
+
+fn serialize_typed<S>(value: &AttrValue, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut state = serializer.serialize_struct("AttrValue", 2)?;
+    match value {
+        Int(v) => {
+            state.serialize_field("type", "int")?;
+            state.serialize_field("value", v)?;
+        }
+        Float(v) => {
+            state.serialize_field("type", "float")?;
+            state.serialize_field("value", v)?;
+        }
+        Float16(v) => {
+            state.serialize_field("type", "float16")?;
+            state.serialize_field("value", v)?;
+        }
+        Bfloat16(v) => {
+            state.serialize_field("type", "bfloat16")?;
+            state.serialize_field("value", v)?;
+        }
+        Bool(v) => {
+            state.serialize_field("type", "bool")?;
+            state.serialize_field("value", v)?;
+        }
+        Str(v) => {
+            state.serialize_field("type", "str")?;
+            state.serialize_field("value", v)?;
+        }
+        Ints(v) => {
+            state.serialize_field("type", "ints")?;
+            state.serialize_field("value", v)?;
+        }
+        Floats(v) => {
+            state.serialize_field("type", "floats")?;
+            state.serialize_field("value", v)?;
+        }
+        Bools(v) => {
+            state.serialize_field("type", "bools")?;
+            state.serialize_field("value", v)?;
+        }
+        Strs(v) => {
+            state.serialize_field("type", "strs")?;
+            state.serialize_field("value", v)?;
+        }
+        Tensor(v) => {
+            state.serialize_field("type", "tensor")?;
+            state.serialize_field("value", v)?;
+        }
+        Tensors(v) => {
+            state.serialize_field("type", "tensors")?;
+            state.serialize_field("value", v)?;
+        }
+        Graph(v) => {
+            state.serialize_field("type", "graph")?;
+            state.serialize_field("value", v)?;
+        }
+        List(v) => {
+            state.serialize_field("type", "list")?;
+            state.serialize_field("value", v)?;
+        }
+        Tuple(v) => {
+            state.serialize_field("type", "tuple")?;
+            state.serialize_field("value", v)?;
+        }
+        Map(v) => {
+            state.serialize_field("type", "map")?;
+            state.serialize_field("value", v)?;
+        }
+        Dict(v) => {
+            state.serialize_field("type", "dict")?;
+            state.serialize_field("value", &Entries(v))?;
+        }
+        Unknown(v) => {
+            state.serialize_field("type", "unknown")?;
+            state.serialize_field("value", v)?;
+        }
+    }
+    state.end()
+}
+
+/// The entries of a [`Dict`] as a JSON object, in their order.
+struct Entries<'a>(&'a [(String, AttrValue)]);
+
+impl Serialize for Entries<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in self.0 {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+/// The shapes of [`AttrFormat::Compact`], named after the `type` each is tagged with.
+enum Shape<'a> {
+    StringLike(std::string::String),
+    StringLikeArray(Vec<std::string::String>),
+    TensorVal(std::string::String),
+    TensorVals(Vec<std::string::String>),
+    TensorsTuple(Vec<Vec<std::string::String>>),
+    Map(&'a BTreeMap<String, AttrValue>),
+    Unknown(usize),
+}
+
+/// ## Note
+/// Maps and unknown values have no pre-rendered form, they keep their own type,
+/// values nested in a map follow the current format.
+fn serialize_compact<S>(value: &AttrValue, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut state = serializer.serialize_struct("AttrValue", 2)?;
+    match compact(value, ATTR_SOURCE.get()) {
+        Shape::StringLike(v) => {
+            state.serialize_field("type", "string-like")?;
+            state.serialize_field("value", &v)?;
+        }
+        Shape::StringLikeArray(v) => {
+            state.serialize_field("type", "string-like-array")?;
+            state.serialize_field("value", &v)?;
+        }
+        Shape::TensorVal(v) => {
+            state.serialize_field("type", "tensor-val")?;
+            state.serialize_field("value", &v)?;
+        }
+        Shape::TensorVals(v) => {
+            state.serialize_field("type", "tensor-vals")?;
+            state.serialize_field("value", &v)?;
+        }
+        Shape::TensorsTuple(v) => {
+            state.serialize_field("type", "tensors-tuple")?;
+            state.serialize_field("value", &v)?;
+        }
+        Shape::Map(m) => {
+            state.serialize_field("type", "map")?;
+            state.serialize_field("value", m)?;
+        }
+        Shape::Unknown(len) => {
+            state.serialize_field("type", "unknown")?;
+            state.serialize_field("value", &len)?;
+        }
+    }
+    state.end()
+}
+
+/// ## Explanation
+/// The shape a value of `source` was sent in, anything not listed is a string-like:
+/// - ONNX: `strings` joined by `;`, `graphs` as `Graphs[a;b]`
+/// - MindIR: an empty tensor list is the string `[]`, lists are strings as well
+/// - GEIR: a tensor is a `tensor-val`, every list (of tensors, graphs or number lists)
+///   is an array of strings
+/// - any format: a single tensor of a list is a string-like, tuples of tensor lists
+///   are `tensors-tuple`s
+fn compact(value: &AttrValue, source: Source) -> Shape<'_> {
+    match value {
+        Map(m) => Shape::Map(m),
+        Unknown(len) => Shape::Unknown(*len),
+        Strs(v) if source == Source::Onnx => Shape::StringLike(v.join(";")),
+        Strs(v) => Shape::StringLikeArray(render(v)),
+        Tensor(t) if matches!(source, Source::GeIr | Source::Other) => {
+            Shape::TensorVal(t.to_string())
+        }
+        Tensors(ts) if ts.len() == 1 => Shape::StringLike(ts[0].to_string()),
+        Tensors(ts) if !ts.is_empty() || source != Source::MindIr => Shape::TensorVals(render(ts)),
+        List(items) if source == Source::Onnx => {
+            let names: Vec<std::string::String> = items
+                .iter()
+                .map(|item| match item {
+                    Graph(name) => name.to_string(),
+                    other => other.to_string(),
+                })
+                .collect();
+            Shape::StringLike(format!("Graphs[{}]", names.join(";")))
+        }
+        List(items)
+            if source == Source::GeIr || (source == Source::Other && is_rendered_list(items)) =>
+        {
+            Shape::StringLikeArray(render(items))
+        }
+        Tuple(items) if is_tensors_tuple(items) => Shape::TensorsTuple(
+            items
+                .iter()
+                .map(|item| match item {
+                    Tensors(ts) => render(ts),
+                    _ => vec![],
+                })
+                .collect(),
+        ),
+        _ => Shape::StringLike(value.to_string()),
+    }
+}
+
+#[inline]
+fn render<T: Display>(items: &[T]) -> Vec<std::string::String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+/// Lists of graphs and of number lists, e.g. GEIR `list_list_int`, are sent one string per item.
+#[inline]
+fn is_rendered_list(items: &[AttrValue]) -> bool {
+    !items.is_empty() && items.iter().all(|item| matches!(item, Ints(_) | Floats(_) | Graph(_)))
+}
+
+#[inline]
+fn is_tensors_tuple(items: &[AttrValue]) -> bool {
+    !items.is_empty() && items.iter().all(|item| matches!(item, Tensors(_)))
+}
+
+/// Writes `items` separated by `sep`, each with [`Display`].
+fn join<T: Display>(f: &mut Formatter<'_>, items: &[T], sep: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(sep)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// ## Note
+/// Numbers go through [`itoa`] and [`ryu`], as the string conversions in
+/// [`crate::SmartStringExt`] do, so `1.0` is rendered as `1.0` rather than `1`.
+impl Display for AttrValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Int(v) => f.write_str(itoa::Buffer::new().format(*v)),
+            Float(v) => f.write_str(ryu::Buffer::new().format(*v)),
+            Float16(v) => write!(f, "f16({})", Float(*v)),
+            Bfloat16(v) => write!(f, "bf16({})", Float(*v)),
+            Bool(v) => write!(f, "{}", v),
+            Str(v) => f.write_str(v),
+            Ints(v) => {
+                let items: Vec<AttrValue> = v.iter().map(|&i| Int(i)).collect();
+                join(f, &items, ", ")
+            }
+            Floats(v) => {
+                let items: Vec<AttrValue> = v.iter().map(|&x| Float(x)).collect();
+                join(f, &items, ", ")
+            }
+            Bools(v) => join(f, v, ","),
+            Strs(v) => join(f, v, ", "),
+            Tensor(t) => write!(f, "{}", t),
+            Tensors(ts) => {
+                f.write_char('[')?;
+                join(f, ts, ", ")?;
+                f.write_char(']')
+            }
+            Graph(name) => write!(f, "Graph({})", name),
+            List(items) => {
+                f.write_char('[')?;
+                join(f, items, ", ")?;
+                f.write_char(']')
+            }
+            Tuple(items) => {
+                f.write_char('(')?;
+                join(f, items, ", ")?;
+                f.write_char(')')
+            }
+            Map(m) => {
+                f.write_char('{')?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", k, v)?;
+                }
+                f.write_char('}')
+            }
+            Dict(entries) => {
+                f.write_char('{')?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", k, v)?;
+                }
+                f.write_char('}')
+            }
+            Unknown(len) => write!(f, "Unknown({} bytes)", len),
+        }
+    }
+}
+
+/// `Float32(2, 3)【NCHW】`, the shape is omitted when unknown and the layout when the format
+/// has none, a tensor without dtype is rendered as its bare dims: `2, 3`.
+impl Display for TensorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let dims = self.dims.as_deref().unwrap_or_default();
+        let dims: Vec<AttrValue> = dims.iter().map(|&d| Int(d)).collect();
+        if self.dtype.is_empty() {
+            return join(f, &dims, ", ");
+        }
+        f.write_str(&self.dtype)?;
+        if self.dims.is_some() {
+            f.write_char('(')?;
+            join(f, &dims, ", ")?;
+            f.write_char(')')?;
+        }
+        if let Some(layout) = &self.layout {
+            write!(f, "【{}】", layout)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn json(value: &AttrValue, format: AttrFormat) -> std::string::String {
+        with_attr_format(format, || serde_json::to_string(value).unwrap())
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("-3", Int(-3).to_string());
+        assert_eq!("0.1", Float(0.1).to_string());
+        assert_eq!("1.0", Float(1.0).to_string());
+        assert_eq!("1, 2", Ints(vec![1, 2]).to_string());
+        assert_eq!("true,false", Bools(vec![true, false]).to_string());
+        assert_eq!("Float(2, 3)", Tensor(TensorType::new("Float", &[2, 3])).to_string());
+        let nchw = TensorType { dtype: "DT_FLOAT".into(), dims: None, layout: Some("NCHW".into()) };
+        assert_eq!("DT_FLOAT【NCHW】", nchw.to_string());
+        let sparse = TensorType { dims: Some(vec![4, 4]), ..Default::default() };
+        assert_eq!("4, 4", sparse.to_string());
+        assert_eq!("(1, [3, 3])", Tuple(vec![Int(1), List(vec![Int(3), Int(3)])]).to_string());
+        let map = Map(BTreeMap::from([("b".into(), Bool(false)), ("a".into(), Graph("g".into()))]));
+        assert_eq!("{a: Graph(g), b: false}", map.to_string());
+    }
+
+    #[test]
+    fn test_typed_json() {
+        assert_eq!(r#"{"type":"int","value":2}"#, json(&Int(2), AttrFormat::Typed));
+        assert_eq!(
+            r#"{"type":"floats","value":[0.5]}"#,
+            json(&Floats(vec![0.5]), AttrFormat::Typed)
+        );
+        assert_eq!(
+            r#"{"type":"tensor","value":{"dtype":"Float","dims":[2]}}"#,
+            json(&Tensor(TensorType::new("Float", &[2])), AttrFormat::Typed)
+        );
+        let map = Map(BTreeMap::from([("stride".into(), Ints(vec![2, 2]))]));
+        assert_eq!(
+            r#"{"type":"map","value":{"stride":{"type":"ints","value":[2,2]}}}"#,
+            json(&map, AttrFormat::Typed)
+        );
+    }
+
+    #[test]
+    fn test_compact_json() {
+        assert_eq!(r#"{"type":"string-like","value":"2"}"#, json(&Int(2), AttrFormat::Compact));
+        assert_eq!(
+            r#"{"type":"string-like-array","value":["a","b"]}"#,
+            json(&Strs(vec!["a".into(), "b".into()]), AttrFormat::Compact)
+        );
+        let ts = Tensors(vec![TensorType::new("Float", &[1]), TensorType::new("Float", &[2])]);
+        assert_eq!(
+            r#"{"type":"tensor-vals","value":["Float(1)","Float(2)"]}"#,
+            json(&ts, AttrFormat::Compact)
+        );
+        assert_eq!(
+            r#"{"type":"string-like","value":"Float(1)"}"#,
+            json(&Tensors(vec![TensorType::new("Float", &[1])]), AttrFormat::Compact)
+        );
+        assert_eq!(
+            r#"{"type":"string-like-array","value":["1, 2","Graph(g)"]}"#,
+            json(&List(vec![Ints(vec![1, 2]), Graph("g".into())]), AttrFormat::Compact)
+        );
+        assert_eq!(
+            r#"{"type":"tensors-tuple","value":[["Float(1)","Float(2)"]]}"#,
+            json(&Tuple(vec![ts]), AttrFormat::Compact)
+        );
+        let map = Map(BTreeMap::from([("group".into(), Int(1))]));
+        assert_eq!(
+            r#"{"type":"map","value":{"group":{"type":"string-like","value":"1"}}}"#,
+            serde_json::to_string(&Compact(&map)).unwrap()
+        );
+        // the format is restored afterwards
+        assert_eq!(r#"{"type":"int","value":2}"#, serde_json::to_string(&Int(2)).unwrap());
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+
+use ahash::{HashMap, HashSet, HashSetExt};
+use serde::Serialize;
+use smartstring::alias::String;
+
+use crate::{AttrValue, Dim, Model, Node, TensorShape};
+
+/// ## Note
+/// `flops` counts a multiply-accumulate as two operations, `macs` only counts those of
+/// convolutions and matrix products.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Cost {
+    pub flops: u64,
+    pub macs: u64,
+    /// Elements of the initializers a node reads
+    pub params: u64,
+    pub param_bytes: u64,
+    /// Bytes of the tensors a node writes
+    pub activation_bytes: u64,
+}
+
+impl Cost {
+    fn add(&mut self, other: &Cost) {
+        self.flops += other.flops;
+        self.macs += other.macs;
+        self.params += other.params;
+        self.param_bytes += other.param_bytes;
+        self.activation_bytes += other.activation_bytes;
+    }
+}
+
+/// ## Explanation
+/// The estimated cost of every node, aggregated per name scope, per op type
+/// and for the whole model.
+///
+/// ## Note
+/// - A scope is a `/` separated prefix of node names, a node counts in every enclosing scope:
+///   `/encoder/layer.0/MatMul` counts in `/encoder` and `/encoder/layer.0`.
+/// - An initializer shared by several nodes counts in each of them, but once in `total`.
+/// - Nodes in `incomplete` have outputs or initializers of unknown or symbolic size,
+///   or outputs without names, their cost only covers what is known.
+#[derive(Debug, Default, Serialize)]
+pub struct CostReport {
+    pub total: Cost,
+    pub nodes: HashMap<String, Cost>,
+    pub scopes: BTreeMap<String, Cost>,
+    pub op_types: BTreeMap<String, Cost>,
+    pub incomplete: Vec<String>,
+}
+
+/// The number of bytes of an element, by the dtype names of [`TensorShape::dtype`].
+pub fn dtype_size(dtype: &str) -> Option<u64> {
+    match dtype {
+        "Bool" | "Int8" | "Uint8" | "Int4" | "Uint4" => Some(1),
+        "Float8e4m3fn" | "Float8e4m3fnuz" | "Float8e5m2" | "Float8e5m2fnuz" => Some(1),
+        "Int16" | "Uint16" | "Float16" | "Bfloat16" => Some(2),
+        "Int32" | "Uint32" | "Float" => Some(4),
+        "Int64" | "Uint64" | "Double" | "Complex64" => Some(8),
+        "Complex128" => Some(16),
+        _ => None,
+    }
+}
+
+/// FLOPs per output element of the elementwise ops costing more than one,
+/// normalizations are counted at inference, with precomputed statistics for `BatchNormalization`.
+const ELEMENT_FLOPS: &[(&str, u64)] = &[
+    ("BatchNormalization", 2),
+    ("GroupNormalization", 5),
+    ("InstanceNormalization", 5),
+    ("LayerNormalization", 5),
+    ("LogSoftmax", 3),
+    ("Softmax", 3),
+];
+
+/// Ops that only move or reinterpret data.
+const ZERO_FLOPS: &[&str] = &[
+    "Cast",
+    "Concat",
+    "Constant",
+    "Dropout",
+    "Flatten",
+    "Gather",
+    "Identity",
+    "Reshape",
+    "Shape",
+    "Split",
+    "Squeeze",
+    "Transpose",
+    "Unsqueeze",
+];
+
+const ELEMENTWISE: &[&str] = &[
+    "Abs",
+    "Add",
+    "And",
+    "BitShift",
+    "Ceil",
+    "Celu",
+    "Clip",
+    "Cos",
+    "Div",
+    "Elu",
+    "Equal",
+    "Erf",
+    "Exp",
+    "Floor",
+    "Gelu",
+    "Greater",
+    "GreaterOrEqual",
+    "HardSigmoid",
+    "HardSwish",
+    "IsInf",
+    "IsNaN",
+    "LeakyRelu",
+    "Less",
+    "LessOrEqual",
+    "Log",
+    "Max",
+    "Mean",
+    "Min",
+    "Mish",
+    "Mod",
+    "Mul",
+    "Neg",
+    "Not",
+    "Or",
+    "PRelu",
+    "Pow",
+    "Reciprocal",
+    "Relu",
+    "Round",
+    "Selu",
+    "Sigmoid",
+    "Sign",
+    "Sin",
+    "Softplus",
+    "Softsign",
+    "Sqrt",
+    "Sub",
+    "Sum",
+    "Tanh",
+    "Where",
+    "Xor",
+];
+
+/// The number of elements, `None` unless every dim is static.
+fn numel(dims: &[Dim]) -> Option<u64> {
+    dims.iter()
+        .map(|dim| match dim {
+            Dim::Static(value) => u64::try_from(*value).ok(),
+            _ => None,
+        })
+        .product()
+}
+
+fn bytes(shape: &TensorShape) -> Option<u64> {
+    Some(numel(&shape.dims)? * dtype_size(&shape.dtype)?)
+}
+
+/// ## Explanation
+/// Estimates the cost of the nodes from their attributes and the shapes of their tensors,
+/// which come from [`Model::value_info`] and [`Node::output_shapes`],
+/// so [`crate::infer_shapes`] should run first.
+pub fn estimate_cost(model: &Model) -> CostReport {
+    let mut report = CostReport::default();
+    let mut read_params = HashSet::new();
+
+    for (id, node) in &model.nodes {
+        let params = node.input.iter().filter(|name| model.parameters.contains_key(*name));
+        let (mut cost, mut complete) = node_cost(model, node);
+        for name in params {
+            match model
+                .value_info
+                .get(name)
+                .and_then(|shape| Some((numel(&shape.dims)?, bytes(shape)?)))
+            {
+                Some((count, size)) => {
+                    cost.params += count;
+                    cost.param_bytes += size;
+                    if read_params.insert(name) {
+                        report.total.params += count;
+                        report.total.param_bytes += size;
+                    }
+                }
+                None => complete = false,
+            }
+        }
+        if !complete {
+            report.incomplete.push(id.clone());
+        }
+
+        report.total.flops += cost.flops;
+        report.total.macs += cost.macs;
+        report.total.activation_bytes += cost.activation_bytes;
+        report.op_types.entry(node.opType.clone()).or_default().add(&cost);
+        for (end, _) in id.match_indices('/').filter(|&(end, _)| end > 0) {
+            report.scopes.entry(String::from(&id[..end])).or_default().add(&cost);
+        }
+        report.nodes.insert(id.clone(), cost);
+    }
+
+    report.incomplete.sort();
+    report
+}
+
+/// The compute and activations of a node, and whether they could be fully computed.
+fn node_cost(model: &Model, node: &Node) -> (Cost, bool) {
+    let mut cost = Cost::default();
+    // without tensor names, as in GEIR, there are no shapes to compute anything from
+    if node.output.is_empty() {
+        return (cost, false);
+    }
+    let outputs: Option<Vec<&TensorShape>> =
+        node.output_shapes.iter().map(Option::as_ref).collect();
+    let Some(outputs) = outputs.filter(|outputs| outputs.len() == node.output.len()) else {
+        return (cost, false);
+    };
+    let activations: Option<u64> = outputs.iter().map(|&shape| bytes(shape)).sum();
+    cost.activation_bytes = activations.unwrap_or_default();
+
+    let input = |i: usize| node.input.get(i).and_then(|name| model.value_info.get(name));
+    let Some(y) = outputs.first().and_then(|&shape| numel(&shape.dims)) else {
+        return (cost, false);
+    };
+    let op = node.opType.as_str();
+    let macs = match op {
+        "Conv" => input(1).and_then(|w| {
+            // every output element reads `C / group x kernel` weights
+            Some(y * numel(w.dims.get(1..)?)?)
+        }),
+        "MatMul" | "Gemm" => input(0).and_then(|a| {
+            let k = match (op, int_attr(node, "transA")) {
+                ("Gemm", Some(1)) => a.dims.first()?,
+                _ => a.dims.last()?,
+            };
+            match k {
+                Dim::Static(k) => Some(y * *k as u64),
+                _ => None,
+            }
+        }),
+        _ => Some(0),
+    };
+    let Some(macs) = macs else {
+        return (cost, false);
+    };
+    cost.macs = macs;
+
+    let flops = match op {
+        "Conv" | "Gemm" => {
+            // the bias is added once per output element
+            let bias = if node.input.get(2).is_some_and(|name| !name.is_empty()) { y } else { 0 };
+            Some(macs * 2 + bias)
+        }
+        "MatMul" => Some(macs * 2),
+        "MaxPool" | "AveragePool" | "LpPool" => match node.attributes.get("kernel_shape") {
+            Some(AttrValue::Ints(kernel)) => Some(y * kernel.iter().product::<i64>() as u64),
+            _ => None,
+        },
+        "GlobalMaxPool" | "GlobalAveragePool" | "GlobalLpPool" => {
+            input(0).and_then(|x| numel(&x.dims))
+        }
+        _ if ZERO_FLOPS.contains(&op) => Some(0),
+        _ if ELEMENTWISE.contains(&op) => Some(y),
+        _ => match ELEMENT_FLOPS.iter().find(|(name, _)| *name == op) {
+            Some((_, per_element)) => Some(y * per_element),
+            // ops without a formula are only counted by their activations
+            None => return (cost, activations.is_some()),
+        },
+    };
+    cost.flops = flops.unwrap_or_default();
+    (cost, flops.is_some() && activations.is_some())
+}
+
+fn int_attr(node: &Node, name: &str) -> Option<i64> {
+    match node.attributes.get(name) {
+        Some(AttrValue::Int(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Dim::Static as S,
+        fixture::{self, node},
+        infer_shapes,
+    };
+
+    fn model(
+        nodes: Vec<Node>,
+        initializers: &[(&str, Vec<Dim>)],
+        inputs: &[(&str, Vec<Dim>)],
+    ) -> Model {
+        let shape = |(name, dims): &(&str, Vec<Dim>)| {
+            (String::from(*name), TensorShape::new("Float", dims.clone()))
+        };
+        let mut model = fixture::model(nodes);
+        model.parameters =
+            initializers.iter().map(|(name, _)| (String::from(*name), String::new())).collect();
+        model.value_info = initializers.iter().chain(inputs).map(shape).collect();
+        infer_shapes(&mut model);
+        model
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let model = model(
+            vec![
+                node("Conv", &["x", "w", "b"], &["/stem/conv"], &[]),
+                node("Relu", &["/stem/conv"], &["/stem/relu"], &[]),
+                node("Flatten", &["/stem/relu"], &["/head/flat"], &[]),
+                node(
+                    "Gemm",
+                    &["/head/flat", "fc", "fc_b"],
+                    &["/head/fc"],
+                    &[("transB", AttrValue::Int(1))],
+                ),
+                node("Gemm", &["/head/fc", "fc2", "fc_b"], &["/head/fc2"], &[]),
+            ],
+            &[
+                ("w", vec![S(4), S(3), S(3), S(3)]),
+                ("b", vec![S(4)]),
+                ("fc", vec![S(10), S(256)]),
+                ("fc2", vec![S(10), S(10)]),
+                ("fc_b", vec![S(10)]),
+            ],
+            &[("x", vec![S(1), S(3), S(10), S(10)])],
+        );
+        let report = estimate_cost(&model);
+
+        // 4 x 8 x 8 outputs, each reading 3 x 3 x 3 weights
+        let conv = report.nodes["/stem/conv"];
+        assert_eq!((6912, 6912 * 2 + 256), (conv.macs, conv.flops));
+        assert_eq!((112, 448), (conv.params, conv.param_bytes));
+        assert_eq!(256 * 4, conv.activation_bytes);
+        assert_eq!((256, 0), (report.nodes["/stem/relu"].flops, report.nodes["/head/flat"].flops));
+        assert_eq!(2560, report.nodes["/head/fc"].macs);
+
+        let stem = report.scopes["/stem"];
+        assert_eq!(conv.flops + 256, stem.flops);
+        assert_eq!(2, report.scopes.len());
+        assert_eq!(2560 + 100 + 10 * 2, report.op_types["Gemm"].params);
+
+        // `fc_b` is read by both Gemm but counts once
+        assert_eq!(112 + 2560 + 100 + 10, report.total.params);
+        assert_eq!(report.total.params * 4, report.total.param_bytes);
+        assert!(report.incomplete.is_empty());
+    }
+
+    #[test]
+    fn test_symbolic_cost() {
+        let batch = Dim::Symbolic("batch".into());
+        let model = model(
+            vec![
+                node("MatMul", &["x", "w"], &["mm"], &[]),
+                node("TopK", &["mm"], &["custom"], &[]),
+                Node { output: vec![], ..node("Relu", &["mm"], &["unnamed"], &[]) },
+            ],
+            &[("w", vec![S(8), S(2)])],
+            &[("x", vec![batch, S(8)])],
+        );
+        let report = estimate_cost(&model);
+
+        let mm = report.nodes["mm"];
+        assert_eq!((0, 16, 64), (mm.flops, mm.params, mm.param_bytes));
+        assert_eq!(vec!["custom", "mm", "unnamed"], report.incomplete);
+        assert!(report.scopes.is_empty());
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::BTreeMap;
+
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use serde::Serialize;
+use smartstring::alias::String;
+
+use crate::{Model, Node};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// Both nodes have the same id
+    Name,
+    /// Same op type, attributes and inputs
+    Signature,
+    /// Same op type and inputs, with different attributes
+    Structure,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeDiff {
+    /// The id in the combined graph: the id in `b`, or in `a` for a removed node
+    pub id: String,
+    pub status: DiffStatus,
+    pub op_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub b: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_by: Option<MatchKind>,
+    /// The op type in `a`, when it differs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_op_type: Option<String>,
+    /// Attributes added, removed or changed, sorted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed_attributes: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inputs_changed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EdgeDiff {
+    pub source: String,
+    pub target: String,
+    pub status: DiffStatus,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub unchanged: usize,
+}
+
+/// ## Explanation
+/// The difference between two versions of a model, as one combined graph:
+/// a node matched in both versions appears once, under its id in `b`.
+/// Nodes are sorted by id, edges by source and target.
+#[derive(Debug, Default, Serialize)]
+pub struct ModelDiff {
+    pub summary: DiffSummary,
+    pub nodes: Vec<NodeDiff>,
+    pub edges: Vec<EdgeDiff>,
+}
+
+/// An input of a node, resolved to the node producing it when there's one.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Input<'a> {
+    Node(&'a str),
+    /// A graph input, an initializer or a parameter, by name
+    Value(&'a str),
+}
+
+struct Side<'a> {
+    model: &'a Model,
+    /// Tensor names and node ids, to the id of the node producing them
+    producers: HashMap<&'a str, &'a str>,
+    /// Node ids by [`Node::index`]
+    order: Vec<&'a str>,
+}
+
+impl<'a> Side<'a> {
+    fn new(model: &'a Model) -> Self {
+        let mut producers = HashMap::new();
+        for (id, node) in &model.nodes {
+            producers.insert(id.as_str(), id.as_str());
+            for output in node.output.iter().filter(|o| !o.is_empty()) {
+                producers.insert(output.as_str(), id.as_str());
+            }
+        }
+        let mut order: Vec<&Node> = model.nodes.values().collect();
+        order.sort_by(|a, b| (a.index, &a.name).cmp(&(b.index, &b.name)));
+        let order = order.into_iter().map(|node| node.name.as_str()).collect();
+        Self { model, producers, order }
+    }
+
+    fn node(&self, id: &str) -> &'a Node {
+        &self.model.nodes[id]
+    }
+
+    fn inputs(&self, id: &str) -> Vec<Input<'a>> {
+        let node = self.node(id);
+        node.input
+            .iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| match self.producers.get(name.as_str()) {
+                Some(&producer) if producer != id => Input::Node(producer),
+                _ => Input::Value(name.as_str()),
+            })
+            .collect()
+    }
+
+    /// The edges between nodes, from both [`Model::edges`] and the inputs of the nodes.
+    fn edges(&self) -> HashSet<(&'a str, &'a str)> {
+        let mut edges = HashSet::new();
+        for (source, target) in &self.model.edges {
+            if self.model.nodes.contains_key(source) && self.model.nodes.contains_key(target) {
+                edges.insert((source.as_str(), target.as_str()));
+            }
+        }
+        for &id in &self.order {
+            for input in self.inputs(id) {
+                if let Input::Node(source) = input {
+                    edges.insert((source, id));
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// The matched pairs, in both directions.
+#[derive(Default)]
+struct Matching<'a> {
+    a_to_b: HashMap<&'a str, (&'a str, MatchKind)>,
+    b_to_a: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Matching<'a> {
+    fn insert(&mut self, a: &'a str, b: &'a str, kind: MatchKind) {
+        self.a_to_b.insert(a, (b, kind));
+        self.b_to_a.insert(b, a);
+    }
+}
+
+/// ## Explanation
+/// A key shared by nodes of either side that look the same: the op type, the attributes
+/// with [`MatchKind::Signature`], and the inputs, where a producer already matched is
+/// named by its id in `b`, and one not matched yet by its op type.
+fn signature(
+    side: &Side,
+    id: &str,
+    to_b: impl Fn(&str) -> Option<String>,
+    kind: MatchKind,
+) -> String {
+    let node = side.node(id);
+    let mut key = node.opType.clone();
+    if kind == MatchKind::Signature {
+        let attributes: BTreeMap<_, _> = node.attributes.iter().collect();
+        for (name, value) in attributes {
+            key.push_str(&format!("|{}={}", name, value));
+        }
+    }
+    for input in side.inputs(id) {
+        match input {
+            Input::Node(producer) => match to_b(producer) {
+                Some(b) => key.push_str(&format!("|={}", b)),
+                None => key.push_str(&format!("|~{}", side.node(producer).opType)),
+            },
+            Input::Value(name) => key.push_str(&format!("|'{}", name)),
+        }
+    }
+    key
+}
+
+/// Pairs up the unmatched nodes of both sides with equal keys, in graph order.
+/// Returns how many pairs were added.
+fn match_by<'a>(a: &Side<'a>, b: &Side<'a>, matching: &mut Matching<'a>, kind: MatchKind) -> usize {
+    let mut candidates: HashMap<String, Vec<&str>> = HashMap::new();
+    for &id in b.order.iter().filter(|id| !matching.b_to_a.contains_key(*id)) {
+        let key = signature(b, id, |p| matching.b_to_a.contains_key(p).then(|| p.into()), kind);
+        candidates.entry(key).or_default().push(id);
+    }
+    for list in candidates.values_mut() {
+        list.reverse();
+    }
+
+    let mut pairs = vec![];
+    for &id in a.order.iter().filter(|id| !matching.a_to_b.contains_key(*id)) {
+        let to_b = |p: &str| matching.a_to_b.get(p).map(|&(b, _)| String::from(b));
+        if let Some(other) = candidates.get_mut(&signature(a, id, to_b, kind)).and_then(Vec::pop) {
+            pairs.push((id, other));
+        }
+    }
+    for &(id, other) in &pairs {
+        matching.insert(id, other, kind);
+    }
+    pairs.len()
+}
+
+/// ## Explanation
+/// Compares two models, which may come from different formats.
+///
+/// Nodes are matched by id first. The others are matched by their signature, then by their
+/// structure, ignoring attributes, in rounds: each match names the inputs of the nodes after it,
+/// which may make them match in the next round.
+///
+/// A matched node is [`DiffStatus::Modified`] when its op type, its attributes or its inputs
+/// differ. Inputs are compared through the matching, so renaming a producer doesn't modify
+/// its consumers, while rewiring does.
+pub fn diff_models(a: &Model, b: &Model) -> ModelDiff {
+    let (side_a, side_b) = (Side::new(a), Side::new(b));
+    let mut matching = Matching::default();
+    for &id in &side_a.order {
+        if let Some((other, _)) = b.nodes.get_key_value(id) {
+            matching.insert(id, other.as_str(), MatchKind::Name);
+        }
+    }
+    loop {
+        let matched = match_by(&side_a, &side_b, &mut matching, MatchKind::Signature)
+            + match_by(&side_a, &side_b, &mut matching, MatchKind::Structure);
+        if matched == 0 {
+            break;
+        }
+    }
+
+    let mut diff = ModelDiff::default();
+    for &id in &side_a.order {
+        let Some(&(other, kind)) = matching.a_to_b.get(id) else {
+            diff.nodes.push(NodeDiff::single(id, &a.nodes[id], DiffStatus::Removed));
+            continue;
+        };
+        diff.nodes.push(compare(&side_a, &side_b, &matching, id, other, kind));
+    }
+    for &id in side_b.order.iter().filter(|id| !matching.b_to_a.contains_key(*id)) {
+        diff.nodes.push(NodeDiff::single(id, &b.nodes[id], DiffStatus::Added));
+    }
+    diff.nodes.sort_by(|x, y| x.id.cmp(&y.id));
+
+    let summary = &mut diff.summary;
+    for node in &diff.nodes {
+        match node.status {
+            DiffStatus::Added => summary.added += 1,
+            DiffStatus::Removed => summary.removed += 1,
+            DiffStatus::Modified => summary.modified += 1,
+            DiffStatus::Unchanged => summary.unchanged += 1,
+        }
+    }
+
+    // the edges of `a` in terms of the combined ids
+    let combined =
+        |id: &'_ str| matching.a_to_b.get(id).map_or(String::from(id), |&(b, _)| b.into());
+    let edges_a: HashSet<_> =
+        side_a.edges().into_iter().map(|(s, t)| (combined(s), combined(t))).collect();
+    let edges_b: HashSet<_> =
+        side_b.edges().into_iter().map(|(s, t)| (String::from(s), String::from(t))).collect();
+    for edge in edges_a.union(&edges_b) {
+        let status = match (edges_a.contains(edge), edges_b.contains(edge)) {
+            (true, true) => DiffStatus::Unchanged,
+            (true, false) => DiffStatus::Removed,
+            _ => DiffStatus::Added,
+        };
+        let (source, target) = edge.clone();
+        diff.edges.push(EdgeDiff { source, target, status });
+    }
+    diff.edges.sort_by(|x, y| (&x.source, &x.target).cmp(&(&y.source, &y.target)));
+
+    diff
+}
+
+impl NodeDiff {
+    fn single(id: &str, node: &Node, status: DiffStatus) -> Self {
+        let (a, b) = match status {
+            DiffStatus::Removed => (Some(id.into()), None),
+            _ => (None, Some(id.into())),
+        };
+        NodeDiff {
+            id: id.into(),
+            status,
+            op_type: node.opType.clone(),
+            a,
+            b,
+            matched_by: None,
+            previous_op_type: None,
+            changed_attributes: vec![],
+            inputs_changed: false,
+        }
+    }
+}
+
+fn compare(
+    side_a: &Side,
+    side_b: &Side,
+    matching: &Matching,
+    id: &str,
+    other: &str,
+    kind: MatchKind,
+) -> NodeDiff {
+    let (x, y) = (side_a.node(id), side_b.node(other));
+    let mut changed_attributes: Vec<String> = x
+        .attributes
+        .iter()
+        .filter(|(name, value)| y.attributes.get(*name) != Some(*value))
+        .map(|(name, _)| name.clone())
+        .chain(y.attributes.keys().filter(|name| !x.attributes.contains_key(*name)).cloned())
+        .collect();
+    changed_attributes.sort();
+
+    // the inputs of `a`, renamed to `b` through the matching
+    let inputs_a: Vec<Input> = side_a
+        .inputs(id)
+        .into_iter()
+        .map(|input| match input {
+            Input::Node(producer) => match matching.a_to_b.get(producer) {
+                Some(&(b, _)) => Input::Node(b),
+                // no node of `b` has this id, the producer is removed
+                None => Input::Value(producer),
+            },
+            value => value,
+        })
+        .collect();
+    let inputs_changed = inputs_a != side_b.inputs(other);
+
+    let previous_op_type = (x.opType != y.opType).then(|| x.opType.clone());
+    let modified = previous_op_type.is_some() || !changed_attributes.is_empty() || inputs_changed;
+    NodeDiff {
+        id: other.into(),
+        status: if modified { DiffStatus::Modified } else { DiffStatus::Unchanged },
+        op_type: y.opType.clone(),
+        a: Some(id.into()),
+        b: Some(other.into()),
+        matched_by: Some(kind),
+        previous_op_type,
+        changed_attributes,
+        inputs_changed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AttrValue,
+        fixture::{model, node},
+    };
+
+    fn status(diff: &ModelDiff, id: &str) -> (DiffStatus, Option<MatchKind>) {
+        let node = diff.nodes.iter().find(|n| n.id == id).unwrap();
+        (node.status, node.matched_by)
+    }
+
+    #[test]
+    fn test_diff_models() {
+        let stride = |s: i64| [("strides", AttrValue::Ints(vec![s, s]))];
+        let a = model(vec![
+            node("Conv", &["x", "w"], &["conv"], &stride(1)),
+            node("Relu", &["conv"], &["relu"], &[]),
+            node("MaxPool", &["relu"], &["pool"], &[]),
+            node("Gemm", &["pool", "fc_w"], &["fc"], &[]),
+            node("Dropout", &["fc"], &["drop"], &[]),
+        ]);
+        // an exporter renamed every node, changed the stride, removed the dropout
+        // and added a softmax
+        let b = model(vec![
+            node("Conv", &["x", "w"], &["/conv/Conv"], &stride(2)),
+            node("Relu", &["/conv/Conv"], &["/relu/Relu"], &[]),
+            node("AveragePool", &["/relu/Relu"], &["pool"], &[]),
+            node("Gemm", &["pool", "fc_w"], &["/fc/Gemm"], &[]),
+            node("Softmax", &["/fc/Gemm"], &["/softmax"], &[]),
+        ]);
+        let diff = diff_models(&a, &b);
+
+        assert_eq!((DiffStatus::Modified, Some(MatchKind::Structure)), status(&diff, "/conv/Conv"));
+        assert_eq!(
+            (DiffStatus::Unchanged, Some(MatchKind::Signature)),
+            status(&diff, "/relu/Relu")
+        );
+        assert_eq!((DiffStatus::Modified, Some(MatchKind::Name)), status(&diff, "pool"));
+        assert_eq!((DiffStatus::Unchanged, Some(MatchKind::Signature)), status(&diff, "/fc/Gemm"));
+        assert_eq!((DiffStatus::Removed, None), status(&diff, "drop"));
+        assert_eq!((DiffStatus::Added, None), status(&diff, "/softmax"));
+        assert_eq!(DiffSummary { added: 1, removed: 1, modified: 2, unchanged: 2 }, diff.summary);
+
+        let conv = diff.nodes.iter().find(|n| n.id == "/conv/Conv").unwrap();
+        assert_eq!(
+            (vec!["strides".into()], false),
+            (conv.changed_attributes.clone(), conv.inputs_changed)
+        );
+        let pool = diff.nodes.iter().find(|n| n.id == "pool").unwrap();
+        assert_eq!(Some("MaxPool".into()), pool.previous_op_type);
+
+        let edge = |source: &str, target: &str, status| EdgeDiff {
+            source: source.into(),
+            target: target.into(),
+            status,
+        };
+        assert_eq!(
+            vec![
+                edge("/conv/Conv", "/relu/Relu", DiffStatus::Unchanged),
+                edge("/fc/Gemm", "/softmax", DiffStatus::Added),
+                edge("/fc/Gemm", "drop", DiffStatus::Removed),
+                edge("/relu/Relu", "pool", DiffStatus::Unchanged),
+                edge("pool", "/fc/Gemm", DiffStatus::Unchanged),
+            ],
+            diff.edges
+        );
+    }
+
+    #[test]
+    fn test_rewired_inputs() {
+        let a = model(vec![
+            node("Relu", &["in"], &["x"], &[]),
+            node("Sigmoid", &["in"], &["y"], &[]),
+            node("Add", &["x", "y"], &["add"], &[]),
+        ]);
+        let b = model(vec![
+            node("Relu", &["in"], &["x"], &[]),
+            node("Sigmoid", &["in"], &["y"], &[]),
+            node("Add", &["y", "x"], &["add"], &[]),
+        ]);
+        let diff = diff_models(&a, &b);
+        let add = diff.nodes.iter().find(|n| n.id == "add").unwrap();
+        assert_eq!((DiffStatus::Modified, true), (add.status, add.inputs_changed));
+        assert_eq!(DiffSummary { added: 0, removed: 0, modified: 1, unchanged: 2 }, diff.summary);
+
+        let diff = diff_models(&a, &a);
+        assert_eq!(DiffSummary { added: 0, removed: 0, modified: 0, unchanged: 3 }, diff.summary);
+        assert!(diff.edges.iter().all(|e| e.status == DiffStatus::Unchanged));
+    }
+}
@@ -1,15 +1,34 @@
 #![allow(unused_doc_comments)]
 
+pub mod attr_format;
+pub use attr_format::*;
+
+pub mod cost;
+pub use cost::*;
+
+pub mod diff;
+pub use diff::*;
+
 pub mod model;
 pub use model::*;
 
+pub mod preview;
+pub use preview::*;
+
 pub mod processors;
 pub use processors::*;
 
+pub mod shape_inference;
+pub use shape_inference::*;
+
 pub mod str_ext;
 pub use str_ext::StrExt;
 
 pub mod string_ext;
 pub use string_ext::SmartStringExt;
 
+pub mod text_format;
+
+pub mod tfrecord;
+
 pub type StdString = String;
@@ -1,15 +1,54 @@
+use std::collections::BTreeMap;
+
 use ahash::HashMap;
-use serde::{Serialize, Serializer, ser::SerializeStruct};
+use serde::Serialize;
 use smartstring::alias::String;
 
-use self::AttrValue::*;
-
 #[derive(Debug, Serialize)]
 pub struct Model {
     pub name: String,
     pub nodes: HashMap<String, Node>,
     pub edges: Vec<(String, String)>,
+    /// Edges with their kind and source output, for formats that carry them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub typed_edges: Vec<Edge>,
     pub parameters: HashMap<String, String>,
+    /// Known shapes of tensors by name: graph inputs and outputs, initializers, constants
+    /// and ONNX `value_info`, completed by [`crate::infer_shapes`]
+    #[serde(skip)]
+    pub value_info: HashMap<String, TensorShape>,
+    /// Model-level metadata, serialized separately from the graph.
+    #[serde(skip)]
+    pub info: ModelInfo,
+}
+
+/// ## Note
+/// The fields are shared by all formats, a field a format doesn't carry stays empty:
+/// - `ir_version` is kept as a string, since MindIR stores it as one
+/// - `opset_import` is only present in ONNX
+/// - `metadata` holds free-form key-value pairs: ONNX `metadata_props`,
+///   MindIR `user_info` (plus `mind_ir_version`), OM partition sizes
+/// - `attributes` holds GEIR model attributes
+#[derive(Debug, Default, Serialize)]
+pub struct ModelInfo {
+    pub format: String,
+    pub ir_version: String,
+    pub producer_name: String,
+    pub producer_version: String,
+    pub domain: String,
+    pub model_version: String,
+    pub doc_string: String,
+    pub opset_import: Vec<OpsetId>,
+    pub metadata: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, AttrValue>,
+}
+
+/// An operator set the model depends on, an empty domain means the default ONNX domain.
+#[derive(Debug, Serialize)]
+pub struct OpsetId {
+    pub domain: String,
+    pub version: i64,
 }
 
 #[allow(non_snake_case)]
@@ -20,58 +59,162 @@ pub struct Node {
     pub input: Vec<String>,
     pub output: Vec<String>,
     pub attributes: HashMap<String, AttrValue>,
-    pub dynamic: bool
+    pub dynamic: bool,
+    /// Position of the node in the source graph.
+    pub index: usize,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub domain: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub doc_string: String,
+    /// Shapes of the `output` tensors, filled by [`crate::infer_shapes`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output_shapes: Vec<Option<TensorShape>>,
 }
 
-#[derive(Debug)]
+/// ## Note
+/// Values keep their type so they can be sorted, filtered and compared numerically,
+/// rendering lives in [`crate::attr_format`].
+/// Floats read as `f32` are widened with [`widen`](crate::processors::widen).
+#[derive(Debug, Clone, PartialEq)]
 pub enum AttrValue {
-    StringLike(String),
-    StringLikeArray(Vec<String>),
-    TensorVal(String),
-    TensorVals(Vec<String>),
-    TensorsTuple(Vec<Vec<String>>),
+    Int(i64),
+    Float(f64),
+    /// MindIR half precision floats, widened like [`AttrValue::Float`]
+    Float16(f64),
+    Bfloat16(f64),
+    Bool(bool),
+    Str(String),
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+    Bools(Vec<bool>),
+    Strs(Vec<String>),
+    Tensor(TensorType),
+    Tensors(Vec<TensorType>),
+    /// Reference to a subgraph by name
+    Graph(String),
+    List(Vec<AttrValue>),
+    Tuple(Vec<AttrValue>),
+    /// Nested attributes keyed by name, e.g. GEIR `NamedAttrs`
+    Map(BTreeMap<String, AttrValue>),
+    /// Key-value pairs in their source order, e.g. MindIR `Dict`
+    Dict(Vec<(String, AttrValue)>),
+    /// A value that can't be decoded, carrying the length of its raw bytes
+    Unknown(usize),
 }
 
-impl Serialize for AttrValue {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut state = serializer.serialize_struct("AttrValue", 2)?;
-        match self {
-            StringLike(s) => {
-                state.serialize_field("type", "string-like")?;
-                state.serialize_field("value", s)?;
-            }
-            StringLikeArray(s) => {
-                state.serialize_field("type", "string-like-array")?;
-                state.serialize_field("value", s)?;
-            }
-            TensorVal(s) => {
-                state.serialize_field("type", "tensor-val")?;
-                state.serialize_field("value", s)?;
-            }
-            TensorVals(v) => {
-                state.serialize_field("type", "tensor-vals")?;
-                state.serialize_field("value", v)?;
-            }
-            TensorsTuple(vv) => {
-                state.serialize_field("type", "tensors-tuple")?;
-                state.serialize_field("value", vv)?;
-            }
-        }
-        state.end()
+/// Type of a tensor without its data.
+/// - `dims` is `None` when the shape is unknown, which differs from a scalar's empty `dims`
+/// - `dtype` is empty when the format doesn't carry one, e.g. ONNX sparse tensors
+/// - `layout` is `None` when the format doesn't carry one, only GEIR does
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TensorType {
+    pub dtype: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dims: Option<Vec<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+}
+
+impl TensorType {
+    pub fn new(dtype: impl Into<String>, dims: &[i64]) -> Self {
+        Self { dtype: dtype.into(), dims: Some(dims.to_vec()), layout: None }
+    }
+}
+
+/// ## Note
+/// Unlike [`TensorType`], the rank is always known, while a dim may be symbolic, e.g. `batch`.
+/// `value` holds the contents of small integer tensors (shapes, axes, split sizes),
+/// so that ops like `Reshape` can be resolved.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TensorShape {
+    pub dtype: String,
+    pub dims: Vec<Dim>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Vec<i64>>,
+}
+
+impl TensorShape {
+    pub fn new(dtype: impl Into<String>, dims: Vec<Dim>) -> Self {
+        Self { dtype: dtype.into(), dims, value: None }
     }
 }
 
+/// Serialized as a number, a string, or `null` when unknown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Dim {
+    Static(i64),
+    Symbolic(String),
+    Unknown,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Edge {
     pub source: String,
     pub target: String,
+    /// Index of the output of `source` the edge carries, always 0 for control edges
+    pub output: usize,
+    pub kind: EdgeKind,
+    /// Shape of the tensor carried, filled by [`crate::infer_shapes`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<TensorShape>,
 }
 
 impl Edge {
     pub fn new(s: String, t: String) -> Self {
-        Self { source: s, target: t }
+        Self { source: s, target: t, output: 0, kind: EdgeKind::Data, shape: None }
+    }
+}
+
+/// A control edge only orders execution, no tensor flows along it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    #[default]
+    Data,
+    Control,
+}
+
+/// Small graphs for the unit tests of the passes over a [`Model`].
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::*;
+
+    /// A node named after its first output.
+    pub(crate) fn node(
+        op_type: &str,
+        input: &[&str],
+        output: &[&str],
+        attrs: &[(&str, AttrValue)],
+    ) -> Node {
+        Node {
+            name: String::from(output[0]),
+            opType: op_type.into(),
+            input: input.iter().map(|&s| s.into()).collect(),
+            output: output.iter().map(|&s| s.into()).collect(),
+            attributes: attrs.iter().map(|(k, v)| (String::from(*k), v.clone())).collect(),
+            dynamic: false,
+            index: 0,
+            domain: String::new(),
+            doc_string: String::new(),
+            output_shapes: vec![],
+        }
+    }
+
+    /// The nodes are indexed in the order given.
+    pub(crate) fn model(nodes: Vec<Node>) -> Model {
+        Model {
+            name: "test".into(),
+            nodes: nodes
+                .into_iter()
+                .enumerate()
+                .map(|(index, node)| (node.name.clone(), Node { index, ..node }))
+                .collect(),
+            edges: vec![],
+            typed_edges: vec![],
+            parameters: Default::default(),
+            value_info: Default::default(),
+            info: Default::default(),
+        }
     }
 }
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::SmartStringExt;
+
+/// Which tensor of a model to preview.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TensorRef {
+    /// A graph initializer (weight), by name
+    Initializer { name: String },
+    /// A tensor attribute of a node, e.g. the `value` of a `Constant`, by node id
+    Attribute { node: String, attribute: String },
+}
+
+/// ## Explanation
+/// A bounded look at the contents of a tensor: its first elements,
+/// and statistics over all of them.
+///
+/// ## Note
+/// `min`, `max` and `mean` only take finite elements, they are `None` when there are none.
+/// Integers are widened to `f64`, which is exact up to `2^53`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TensorPreview {
+    pub dtype: String,
+    pub dims: Vec<i64>,
+    /// The number of elements, all of them count in the statistics
+    pub count: u64,
+    /// The first elements, at most the requested limit
+    pub values: PreviewValues,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub nan_count: u64,
+    pub inf_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PreviewValues {
+    Numbers(Vec<f64>),
+    Strings(Vec<String>),
+}
+
+impl TensorPreview {
+    pub(crate) fn strings(
+        dtype: String,
+        dims: Vec<i64>,
+        strings: &[Vec<u8>],
+        limit: usize,
+    ) -> Self {
+        let values = strings.iter().take(limit).map(|s| String::from_vecu8(s)).collect();
+        TensorPreview {
+            dtype,
+            dims,
+            count: strings.len() as u64,
+            values: PreviewValues::Strings(values),
+            min: None,
+            max: None,
+            mean: None,
+            nan_count: 0,
+            inf_count: 0,
+        }
+    }
+}
+
+/// Collects a [`TensorPreview`] one element at a time, so tensors are never held whole.
+pub(crate) struct PreviewBuilder {
+    limit: usize,
+    values: Vec<f64>,
+    count: u64,
+    finite: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    nan_count: u64,
+    inf_count: u64,
+}
+
+impl PreviewBuilder {
+    pub(crate) fn new(limit: usize) -> Self {
+        PreviewBuilder {
+            limit,
+            values: vec![],
+            count: 0,
+            finite: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            nan_count: 0,
+            inf_count: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: f64) {
+        if self.values.len() < self.limit {
+            self.values.push(value);
+        }
+        self.count += 1;
+        if value.is_nan() {
+            self.nan_count += 1;
+        } else if value.is_infinite() {
+            self.inf_count += 1;
+        } else {
+            self.finite += 1;
+            self.sum += value;
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+    }
+
+    pub(crate) fn finish(self, dtype: String, dims: Vec<i64>) -> TensorPreview {
+        let finite = self.finite > 0;
+        TensorPreview {
+            dtype,
+            dims,
+            count: self.count,
+            values: PreviewValues::Numbers(self.values),
+            min: finite.then_some(self.min),
+            max: finite.then_some(self.max),
+            mean: finite.then(|| self.sum / self.finite as f64),
+            nan_count: self.nan_count,
+            inf_count: self.inf_count,
+        }
+    }
+}
+
+/// The element types stored as fixed-width little endian values,
+/// shared with the `events` crate for the tensors of summaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element {
+    Bool,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F16,
+    Bf16,
+    F32,
+    F64,
+}
+
+impl Element {
+    pub fn width(self) -> usize {
+        match self {
+            Element::Bool | Element::I8 | Element::U8 => 1,
+            Element::I16 | Element::U16 | Element::F16 | Element::Bf16 => 2,
+            Element::I32 | Element::U32 | Element::F32 => 4,
+            Element::I64 | Element::U64 | Element::F64 => 8,
+        }
+    }
+
+    /// Reads the element at the start of `bytes`, which holds at least [`Element::width`] bytes.
+    pub fn read(self, bytes: &[u8]) -> f64 {
+        match self {
+            Element::Bool => (bytes[0] != 0) as u8 as f64,
+            Element::I8 => bytes[0] as i8 as f64,
+            Element::U8 => bytes[0] as f64,
+            Element::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Element::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Element::F16 => f16_to_f64(u16::from_le_bytes([bytes[0], bytes[1]])),
+            Element::Bf16 => bf16_to_f64(u16::from_le_bytes([bytes[0], bytes[1]])),
+            Element::I32 => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Element::U32 => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Element::F32 => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Element::I64 => i64::from_le_bytes(bytes[..8].try_into().unwrap()) as f64,
+            Element::U64 => u64::from_le_bytes(bytes[..8].try_into().unwrap()) as f64,
+            Element::F64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+
+    /// Reads every element of `bytes`, a trailing partial element is ignored.
+    pub fn read_all(self, bytes: &[u8]) -> Vec<f64> {
+        bytes.chunks_exact(self.width()).map(|chunk| self.read(chunk)).collect()
+    }
+
+    /// Pushes every element of `bytes`, a trailing partial element is ignored.
+    pub(crate) fn decode(self, bytes: &[u8], builder: &mut PreviewBuilder) {
+        for chunk in bytes.chunks_exact(self.width()) {
+            builder.push(self.read(chunk));
+        }
+    }
+}
+
+/// IEEE 754 half precision
+pub(crate) fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exp = ((bits >> 10) & 0x1f) as i32;
+    let frac = (bits & 0x3ff) as f64;
+    sign * match exp {
+        0 => frac * 2f64.powi(-24),
+        0x1f if frac == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + frac / 1024.0) * 2f64.powi(exp - 15),
+    }
+}
+
+/// bfloat16 is the upper half of a `f32`
+pub(crate) fn bf16_to_f64(bits: u16) -> f64 {
+    f32::from_bits((bits as u32) << 16) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_floats() {
+        assert_eq!(1.0, f16_to_f64(0x3c00));
+        assert_eq!(-2.0, f16_to_f64(0xc000));
+        assert_eq!(65504.0, f16_to_f64(0x7bff));
+        assert_eq!(2f64.powi(-24), f16_to_f64(0x0001));
+        assert_eq!(f64::NEG_INFINITY, f16_to_f64(0xfc00));
+        assert!(f16_to_f64(0x7e00).is_nan());
+
+        assert_eq!(1.0, bf16_to_f64(0x3f80));
+        assert_eq!(-0.5, bf16_to_f64(0xbf00));
+        assert!(bf16_to_f64(0x7fc0).is_nan());
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut builder = PreviewBuilder::new(2);
+        for value in [1.0, f64::NAN, 3.0, f64::INFINITY, -4.0] {
+            builder.push(value);
+        }
+        let preview = builder.finish("Float".into(), vec![5]);
+        let PreviewValues::Numbers(values) = &preview.values else { panic!() };
+        assert_eq!(2, values.len());
+        assert!(values[1].is_nan());
+        assert_eq!(5, preview.count);
+        assert_eq!((Some(-4.0), Some(3.0), Some(0.0)), (preview.min, preview.max, preview.mean));
+        assert_eq!((1, 1), (preview.nan_count, preview.inf_count));
+
+        let preview = PreviewBuilder::new(2).finish("Float".into(), vec![0]);
+        assert_eq!((0, None, None), (preview.count, preview.min, preview.mean));
+    }
+}
@@ -0,0 +1,457 @@
+/// Also read by the `events` crate, which decodes the summaries of MindSpore summary files.
+pub mod irpb {
+    include!(concat!(env!("OUT_DIR"), "/mindspore.irpb.rs"));
+}
+
+use std::{fs, io};
+
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use irpb::{
+    DataType, Event, GraphProto, ModelProto, NamedValueProto, NodeProto, TensorProto, TypeProto,
+    ValueProto, event::What, type_proto,
+};
+use prost::Message;
+use smartstring::alias::String;
+
+use super::{TensorFormatter, format_tensors, invalid_data, is_text_format, parse_pb, widen};
+use crate::{
+    AttrValue, AttrValue::*, Model, ModelInfo, Node, SmartStringExt, TensorType, tfrecord,
+};
+
+/// ## Explanation
+/// MindSpore writes its computation graph as an `anf_ir` [`GraphProto`] in two places:
+/// - a summary file, as the `graph_def` of an [`Event`] in the TFRecord-framed event stream
+/// - a standalone graph file (`.ms_graph`, `.pb`, see [`super::detect_pb_format`]),
+///   which holds a [`ModelProto`],
+///   possibly as a text dump (see [`super::is_text_format`])
+///
+/// The two are told apart by content rather than by file name: a file whose first
+/// record passes both CRC checks is read as a summary file.
+pub fn parse_anf_ir_model(path: &str) -> io::Result<Model> {
+    if is_text_format(path) {
+        let model = parse_pb::<ModelProto>(path)?;
+        return model_graph(model).ok_or_else(|| invalid_data("no graph in the model"));
+    }
+
+    let buffer = fs::read(path)?;
+    parse_anf_ir_bytes(&buffer).ok_or_else(|| invalid_data("no graph in the file"))
+}
+
+/// Whether a buffer decodes as a bare [`GraphProto`] whose nodes all name their op,
+/// which a TensorFlow `GraphDef`, also made of repeated nodes, doesn't.
+pub(super) fn is_anf_ir_graph(buffer: &[u8]) -> bool {
+    GraphProto::decode(buffer).is_ok_and(|graph| {
+        !graph.node.is_empty() && graph.node.iter().all(|node| node.op_type.is_some())
+    })
+}
+
+fn parse_anf_ir_bytes(buffer: &[u8]) -> Option<Model> {
+    match tfrecord::records(buffer).next() {
+        Some(Ok(_)) => parse_summary(buffer),
+        _ => parse_graph_file(buffer),
+    }
+}
+
+/// ## Note
+/// A summary file may hold a graph event per training phase, the first one is used,
+/// records after a truncated tail (e.g. training still running) are ignored.
+fn parse_summary(buffer: &[u8]) -> Option<Model> {
+    let mut version = String::new();
+    for record in tfrecord::records(buffer) {
+        let Ok(event) = Event::decode(record.ok()?) else {
+            continue;
+        };
+        match event.what {
+            Some(What::Version(v)) => version = v.into(),
+            Some(What::GraphDef(graph)) => {
+                let mut info = ModelInfo { format: String::from("anf_ir"), ..Default::default() };
+                if !version.is_empty() {
+                    info.metadata.insert("event_version".into(), version);
+                }
+                if let Some(step) = event.step {
+                    info.metadata.insert("step".into(), String::from_i64(step));
+                }
+                return Some(process_graph(&graph, info));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// A graph file is a [`ModelProto`], a bare [`GraphProto`] is accepted as well.
+fn parse_graph_file(buffer: &[u8]) -> Option<Model> {
+    if let Ok(model @ ModelProto { graph: Some(_), .. }) = ModelProto::decode(buffer) {
+        return model_graph(model);
+    }
+
+    let graph = GraphProto::decode(buffer).ok()?;
+    let info = ModelInfo { format: String::from("anf_ir"), ..Default::default() };
+    Some(process_graph(&graph, info))
+}
+
+fn model_graph(model: ModelProto) -> Option<Model> {
+    let info = ModelInfo {
+        format: String::from("anf_ir"),
+        ir_version: String::from_i64(model.ir_version()),
+        domain: String::from(model.domain()),
+        model_version: String::from_i64(model.model_version()),
+        ..Default::default()
+    };
+    model.graph.map(|graph| process_graph(&graph, info))
+}
+
+/// ## Explanation
+/// Nodes in `anf_ir` are named by their index (`"1"`, `"2"`, ...), the readable
+/// name is `full_name`, e.g. `Default/network/conv1/Conv2D-op1`.
+/// `full_name` is used as the node id when it's unique, inputs are renamed accordingly.
+///
+/// Inputs that are not nodes refer to [`GraphProto::parameters`] or
+/// [`GraphProto::const_vals`], both are kept in [`Model::parameters`].
+/// Control edges are kept as inputs as well, since [`Node`] doesn't type its inputs.
+///
+/// The scope of a node (e.g. `Default/network`) is kept as its `doc_string`,
+/// `domain` stays empty since [`crate::infer_shapes`] takes any other domain for a custom op set.
+fn process_graph(graph: &GraphProto, info: ModelInfo) -> Model {
+    let ids = node_ids(&graph.node);
+    let id_of: HashMap<&str, &String> =
+        graph.node.iter().map(|n| n.name()).zip(ids.iter()).collect();
+
+    let mut parameters = HashMap::new();
+    for parameter in &graph.parameters {
+        let ty = parameter.r#type.as_ref().map_or_else(String::null, format_type);
+        parameters.insert(String::from(parameter.name()), ty);
+    }
+    for constant in &graph.const_vals {
+        let value = constant
+            .value
+            .as_ref()
+            .map_or_else(String::null, |v| parse_value(v).to_string().into());
+        parameters.insert(String::from(constant.key()), value);
+    }
+
+    let mut nodes = HashMap::with_capacity(graph.node.len());
+    for (index, (node, id)) in graph.node.iter().zip(&ids).enumerate() {
+        let input = node
+            .input
+            .iter()
+            .map(|i| match id_of.get(i.name()) {
+                Some(&id) => id.clone(),
+                _ => String::from(i.name()),
+            })
+            .collect();
+        let attributes = node
+            .attribute
+            .iter()
+            .filter_map(|attr| Some((String::from(attr.name()), parse_value(attr.value.as_ref()?))))
+            .collect();
+
+        nodes.insert(id.clone(), Node {
+            name: id.clone(),
+            opType: String::from(node.op_type()),
+            input,
+            output: vec![],
+            attributes,
+            dynamic: false,
+            index,
+            domain: String::new(),
+            doc_string: String::from(node.scope()),
+            output_shapes: vec![],
+        });
+    }
+
+    Model {
+        name: String::from(graph.name()),
+        nodes,
+        edges: vec![],
+        typed_edges: vec![],
+        parameters,
+        value_info: HashMap::new(),
+        info,
+    }
+}
+
+fn node_ids(nodes: &[NodeProto]) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for node in nodes {
+        *counts.entry(node.full_name()).or_default() += 1;
+    }
+
+    let mut used = HashSet::with_capacity(nodes.len());
+    nodes
+        .iter()
+        .map(|node| {
+            let full_name = node.full_name();
+            let mut id = match counts[full_name] {
+                1 if !full_name.is_empty() => String::from(full_name),
+                _ => String::from(node.name()),
+            };
+            while !used.insert(id.clone()) {
+                id.push('_');
+            }
+            id
+        })
+        .collect()
+}
+
+/// ## Explanation
+/// [`ValueProto::dtype`] is a discriminator, like the `type` of an ONNX attribute,
+/// integer kinds share `int_val`/`uint_val` and float kinds share `float_val`.
+/// Kinds that only describe a type (e.g. `DT_BASE_INT`, `DT_ANY`) keep their name.
+fn parse_value(value: &ValueProto) -> AttrValue {
+    use DataType::*;
+
+    let dtype = value.dtype();
+    match dtype {
+        DtBool => Bool(value.bool_val()),
+        DtInt8 | DtInt16 | DtInt32 | DtInt64 => Int(value.int_val()),
+        DtUint8 | DtUint16 | DtUint32 | DtUint64 => uint(value.uint_val()),
+        DtFloat16 | DtFloat32 | DtBfloat16 => Float(widen(value.float_val())),
+        DtFloat64 => Float(value.double_val()),
+        DtString => Str(String::from(value.str_val())),
+        DtTensor => match &value.tensor_val {
+            Some(t) => Tensor(t.tensor_type()),
+            _ => Str(String::null()),
+        },
+        DtGraph => Graph(String::from(value.graph.as_ref().map_or("", |g| g.name()))),
+        DtBools => Bools(value.bool_vals.clone()),
+        DtInts8 | DtInts16 | DtInts32 | DtInts64 => Ints(value.int_vals.clone()),
+        DtUints8 | DtUints16 | DtUints32 | DtUints64 => {
+            match value.uint_vals.iter().map(|&u| i64::try_from(u)).collect() {
+                Ok(ints) => Ints(ints),
+                _ => List(value.uint_vals.iter().map(|&u| uint(u)).collect()),
+            }
+        }
+        DtFloats16 | DtFloats32 | DtBfloats16 => {
+            Floats(value.float_vals.iter().map(|&f| widen(f)).collect())
+        }
+        DtFloats64 => Floats(value.double_vals.clone()),
+        DtStrings => Strs(value.str_vals.iter().map(String::from).collect()),
+        DtTensors => format_tensors(&value.tensor_vals),
+        DtGraphs => List(value.graphs.iter().map(|g| Graph(String::from(g.name()))).collect()),
+        DtTuple => Tuple(value.values.iter().map(parse_value).collect()),
+        DtList => List(value.values.iter().map(parse_value).collect()),
+        DtDict => Map(value.dict_val.iter().filter_map(parse_named_value).collect()),
+        DtNone => Str(String::from("None")),
+        DtType => Str(value.type_val.as_ref().map_or_else(String::null, format_type)),
+        _ => match &value.str_val {
+            Some(s) => Str(String::from(s)),
+            _ => Str(String::from(dtype.as_str_name())),
+        },
+    }
+}
+
+/// Entries without a value are dropped, as attributes are.
+#[inline]
+fn parse_named_value(kv: &NamedValueProto) -> Option<(String, AttrValue)> {
+    Some((String::from(kv.key()), parse_value(kv.value.as_ref()?)))
+}
+
+#[inline]
+fn uint(value: u64) -> AttrValue {
+    match i64::try_from(value) {
+        Ok(i) => Int(i),
+        _ => Str(format!("{}", value).into()),
+    }
+}
+
+/// A tensor type is rendered as a [`TensorType`], a sequence as the tuple of its elements.
+fn format_type(ty: &TypeProto) -> String {
+    match &ty.value {
+        Some(type_proto::Value::TensorType(tensor)) => {
+            let dims =
+                tensor.shape.as_ref().map(|shape| shape.dim.iter().map(|d| d.size()).collect());
+            let dtype = format!("{:?}", tensor.elem_type());
+            TensorType { dtype: dtype.into(), dims, layout: None }.to_string().into()
+        }
+        Some(type_proto::Value::SequenceType(seq)) => {
+            let elems: Vec<String> = seq.elem_types.iter().map(format_type).collect();
+            format!("({})", elems.join(", ")).into()
+        }
+        _ => format!("{:?}", ty.data_type()).into(),
+    }
+}
+
+impl TensorFormatter for TensorProto {
+    fn tensor_type(&self) -> TensorType {
+        TensorType::new(format!("{:?}", self.data_type()), &self.dims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use irpb::{
+        AttributeProto, InputProto, ParameterProto, TensorShapeProto, input_proto::EdgeType,
+        tensor_shape_proto::Dimension,
+    };
+
+    use super::*;
+
+    fn node(name: &str, op_type: &str, full_name: &str, inputs: &[&str]) -> NodeProto {
+        NodeProto {
+            name: Some(name.into()),
+            op_type: Some(op_type.into()),
+            full_name: Some(full_name.into()),
+            scope: Some("Default/network".into()),
+            input: inputs
+                .iter()
+                .map(|i| InputProto {
+                    name: Some(i.to_string()),
+                    r#type: Some(EdgeType::DataEdge as i32),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn value(dtype: DataType) -> ValueProto {
+        ValueProto { dtype: Some(dtype as i32), ..Default::default() }
+    }
+
+    fn graph() -> GraphProto {
+        let mut conv = node("1", "Conv2D", "Default/network/Conv2D-op1", &["x", "conv.weight"]);
+        let stride = ValueProto { int_vals: vec![1, 1], ..value(DataType::DtInts64) };
+        conv.attribute.push(AttributeProto { name: Some("stride".into()), value: Some(stride) });
+        let relu = node("2", "ReLU", "Default/network/ReLU-op2", &["1"]);
+        // the same full name twice falls back to the node names
+        let add = node("3", "Add", "Default/network/Add", &["2", "const1"]);
+        let add2 = node("4", "Add", "Default/network/Add", &["3", "1"]);
+
+        let shape = TensorShapeProto {
+            dim: [1, 3, 224, 224].map(|size| Dimension { size: Some(size), name: None }).into(),
+        };
+        let x_type = TypeProto {
+            data_type: Some(DataType::DtTensor as i32),
+            value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                elem_type: Some(DataType::DtFloat32 as i32),
+                shape: Some(shape),
+            })),
+        };
+        GraphProto {
+            name: Some("lenet".into()),
+            node: vec![conv, relu, add, add2],
+            parameters: vec![ParameterProto {
+                name: Some("x".into()),
+                r#type: Some(x_type),
+                default_val: None,
+            }],
+            const_vals: vec![NamedValueProto {
+                key: Some("const1".into()),
+                value: Some(ValueProto { float_val: Some(0.5), ..value(DataType::DtFloat32) }),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_graph_file() {
+        let model = ModelProto { ir_version: Some(1), graph: Some(graph()), ..Default::default() };
+        let model = parse_anf_ir_bytes(&model.encode_to_vec()).unwrap();
+        assert_eq!("lenet", model.name);
+        assert_eq!("anf_ir", model.info.format);
+        assert_eq!("1", model.info.ir_version);
+        assert_eq!(4, model.nodes.len());
+
+        let conv = &model.nodes["Default/network/Conv2D-op1"];
+        assert_eq!("Conv2D", conv.opType);
+        assert_eq!("", conv.domain);
+        assert_eq!("Default/network", conv.doc_string);
+        assert_eq!(vec!["x", "conv.weight"], conv.input);
+        assert_eq!(Some(&Ints(vec![1, 1])), conv.attributes.get("stride"));
+
+        let relu = &model.nodes["Default/network/ReLU-op2"];
+        assert_eq!(vec!["Default/network/Conv2D-op1"], relu.input);
+        assert_eq!(vec!["Default/network/ReLU-op2", "const1"], model.nodes["3"].input);
+        assert_eq!(vec!["3", "Default/network/Conv2D-op1"], model.nodes["4"].input);
+
+        assert_eq!("DtFloat32(1, 3, 224, 224)", model.parameters["x"]);
+        assert_eq!("0.5", model.parameters["const1"]);
+    }
+
+    #[test]
+    fn test_detect_pb_format() {
+        let model = ModelProto { ir_version: Some(1), graph: Some(graph()), ..Default::default() };
+        assert_eq!(Some("anf_ir"), super::super::detect_pb_bytes(&model.encode_to_vec()));
+        assert_eq!(Some("anf_ir"), super::super::detect_pb_bytes(&graph().encode_to_vec()));
+    }
+
+    #[test]
+    fn test_text_graph_file() {
+        let text = r#"
+ir_version: 1
+graph {
+  name: "lenet"
+  node { name: "1" op_type: "ReLU" full_name: "Default/network/ReLU-op1" }
+}
+"#;
+        let path = std::env::temp_dir().join(format!("anf_ir_{}.pbtxt", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let model = parse_anf_ir_model(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let model = model.unwrap();
+        assert_eq!("lenet", model.name);
+        assert_eq!("1", model.info.ir_version);
+        assert!(model.nodes.contains_key("Default/network/ReLU-op1"));
+    }
+
+    #[test]
+    fn test_summary_file() {
+        let event = |what| Event { wall_time: 0.0, step: Some(3), what: Some(what) };
+        let mut file = vec![];
+        tfrecord::write_record(
+            &mut file,
+            &event(What::Version("Mindspore.Event:1".into())).encode_to_vec(),
+        );
+        tfrecord::write_record(&mut file, &event(What::GraphDef(graph())).encode_to_vec());
+        // a truncated trailing record, as when training is still writing
+        file.extend_from_slice(&[1, 0, 0]);
+
+        let model = parse_anf_ir_bytes(&file).unwrap();
+        assert_eq!("lenet", model.name);
+        assert_eq!(4, model.nodes.len());
+        assert_eq!("Mindspore.Event:1", model.info.metadata["event_version"]);
+        assert_eq!("3", model.info.metadata["step"]);
+
+        let mut no_graph = vec![];
+        tfrecord::write_record(&mut no_graph, &event(What::Version("v".into())).encode_to_vec());
+        assert!(parse_anf_ir_bytes(&no_graph).is_none());
+    }
+
+    #[test]
+    fn test_values() {
+        use DataType::*;
+
+        assert_eq!(Bool(true), parse_value(&ValueProto { bool_val: Some(true), ..value(DtBool) }));
+        assert_eq!(
+            Str("18446744073709551615".into()),
+            parse_value(&ValueProto { uint_val: Some(u64::MAX), ..value(DtUint64) })
+        );
+        assert_eq!(
+            Float(0.1),
+            parse_value(&ValueProto { float_val: Some(0.1), ..value(DtFloat32) })
+        );
+        let tuple = ValueProto {
+            values: vec![ValueProto { int_val: Some(2), ..value(DtInt32) }, value(DtNone)],
+            ..value(DtTuple)
+        };
+        assert_eq!("(2, None)", parse_value(&tuple).to_string());
+        let dict = ValueProto {
+            dict_val: vec![NamedValueProto {
+                key: Some("axis".into()),
+                value: Some(ValueProto { int_vals: vec![0], ..value(DtInts32) }),
+            }],
+            ..value(DtDict)
+        };
+        assert_eq!("{axis: 0}", parse_value(&dict).to_string());
+        let tensor = TensorProto {
+            dims: vec![2, 2],
+            data_type: Some(DtFloat16 as i32),
+            ..Default::default()
+        };
+        let tensor = ValueProto { tensor_val: Some(tensor), ..value(DtTensor) };
+        assert_eq!("DtFloat16(2, 2)", parse_value(&tensor).to_string());
+        assert_eq!(Str("DT_ANY".into()), parse_value(&value(DtAny)));
+    }
+}
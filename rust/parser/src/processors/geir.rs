@@ -1,13 +1,18 @@
-mod geir {
+pub(super) mod geir {
     include!(concat!(env!("OUT_DIR"), "/geir.rs"));
 }
 
+use std::io;
+
 use ahash::{HashMap, HashMapExt};
-use geir::{AttrDef, DataType, ModelDef, TensorDef, TensorDescriptor};
+use geir::{AttrDef, DataType, GraphDef, ModelDef, NamedAttrs, TensorDef, TensorDescriptor};
+use prost::Message;
 use smartstring::alias::String;
 
-use super::{TensorFormatter, format_tensors, parse_pb};
-use crate::{AttrValue, AttrValue::*, Model, Node, SmartStringExt};
+use super::{
+    TensorFormatter, format_tensors, invalid_data, is_om_file, parse_om_model, parse_pb, widen,
+};
+use crate::{AttrValue, AttrValue::*, Model, ModelInfo, Node, SmartStringExt, TensorType};
 
 impl From<ModelDef> for Option<Model> {
     fn from(value: ModelDef) -> Self {
@@ -16,7 +21,7 @@ impl From<ModelDef> for Option<Model> {
             let mut nodes = HashMap::new();
             let parameters = HashMap::new();
             let ops = &graph.op;
-            for op in ops {
+            for (index, op) in ops.iter().enumerate() {
                 let name = String::from(&op.name);
                 let op_type = String::from(&op.r#type);
                 let mut attributes = HashMap::new();
@@ -26,106 +31,514 @@ impl From<ModelDef> for Option<Model> {
                         attributes.insert(String::from(name), val);
                     }
                 }
-                let node =
-                    Node { name: name.clone(), opType: op_type, input, output: vec![], attributes, dynamic:false };
+                let node = Node {
+                    name: name.clone(),
+                    opType: op_type,
+                    input,
+                    output: vec![],
+                    attributes,
+                    dynamic: false,
+                    index,
+                    domain: String::new(),
+                    doc_string: String::new(),
+                    output_shapes: vec![],
+                };
 
                 nodes.insert(name, node);
             }
 
-            return Some(Model { name, nodes, edges: vec![], parameters });
+            let mut attributes = HashMap::new();
+            for (name, attr) in &value.attr {
+                if let Some(val) = parse_attr(attr) {
+                    attributes.insert(String::from(name), val);
+                }
+            }
+            let info = ModelInfo {
+                format: String::from("geir"),
+                ir_version: String::from_i64(value.version as i64),
+                model_version: String::from(&value.custom_version),
+                attributes,
+                ..Default::default()
+            };
+
+            return Some(Model {
+                name,
+                nodes,
+                edges: vec![],
+                typed_edges: vec![],
+                parameters,
+                value_info: HashMap::new(),
+                info,
+            });
         }
 
         None
     }
 }
 
-pub fn parse_geir_model(path: &str) -> Option<Model> {
-    match parse_pb::<ModelDef>(path) {
-        Ok(model) => model.into(),
-        Err(_) => None,
+/// Reads a `ModelDef` dump, or the model definition of an offline model (`.om`).
+pub fn parse_geir_model(path: &str) -> io::Result<Model> {
+    if is_om_file(path) {
+        return parse_om_model(path);
     }
+
+    let model: Option<Model> = parse_pb::<ModelDef>(path)?.into();
+    model.ok_or_else(|| invalid_data("no graph in the model"))
 }
 
 impl TensorFormatter for TensorDescriptor {
-    fn fmt(&self) -> String {
-        let dtype = self.dtype();
-        let layout = self.layout.as_str();
-        if let Some(shape) = &self.shape {
-            let dims = String::from_i64s(&shape.dim);
-            return format!("{:?}({})【{}】", dtype, dims, layout).into();
+    fn tensor_type(&self) -> TensorType {
+        TensorType {
+            dtype: format!("{:?}", self.dtype()).into(),
+            dims: self.shape.as_ref().map(|shape| shape.dim.clone()),
+            layout: Some(String::from(&self.layout)),
         }
-
-        format!("{:?}【{}】", dtype, layout).into()
     }
 }
 
+/// ## Note
+/// A GE dump may contain values this parser has never seen, none of them may panic:
+/// - an undecodable value becomes [`Unknown`] with the length of its raw bytes
+/// - [`NamedAttrs`] become nested [`Map`]s, keyed by their names, a list of them is a
+///   [`AttrValue::List`] of such single entry maps, so repeated names are all kept in order
+/// - graphs are referenced by name, the subgraphs themselves are in [`ModelDef::graph`]
 fn parse_attr(attr: &AttrDef) -> Option<AttrValue> {
     use geir::attr_def::Value::*;
 
-    let Some(value) = attr.value.as_ref() else {
-        return None;
-    };
+    let value = attr.value.as_ref()?;
     let val = match value {
-        S(bytes) => StringLike(String::from_vecu8(bytes)),
-        I(i) => StringLike(String::from_i64(*i)),
-        F(f) => StringLike(String::from_f32(*f)),
-        B(b) => StringLike(String::from_bool(*b)),
+        S(bytes) => Str(String::from_vecu8(bytes)),
+        I(i) => Int(*i),
+        F(f) => Float(widen(*f)),
+        B(b) => Bool(*b),
         /// this branch is likely unreachable
-        Bt(bytes) => StringLike(String::from_vecu8(bytes)),
+        Bt(bytes) => Str(String::from_vecu8(bytes)),
         List(list) => {
             use geir::attr_def::list_value::ListValueType::*;
 
             match list.val_type() {
-                /// refer to **geir.proto**, this variant is unreachable
-                VtListNone => unreachable!(),
-                VtListString => StringLikeArray(String::vecu8s2strings(&list.s)),
-                VtListInt => StringLike(String::from_i64s(&list.i)),
-                VtListFloat => StringLike(String::from_f32s(&list.f)),
-                VtListBool => StringLike(String::from_bools(&list.b)),
-                VtListBytes => StringLikeArray(String::vecu8s2strings(&list.s)),
+                /// refer to **geir.proto**, this type only makes sense for an empty list,
+                /// an out-of-range type is also decoded as this variant
+                VtListNone => match list.encoded_len() {
+                    0 => Strs(vec![]),
+                    len => Unknown(len),
+                },
+                VtListString => Strs(String::vecu8s2strings(&list.s)),
+                VtListInt => Ints(list.i.clone()),
+                VtListFloat => Floats(list.f.iter().map(|&f| widen(f)).collect()),
+                VtListBool => Bools(list.b.clone()),
+                VtListBytes => Strs(String::vecu8s2strings(&list.bt)),
                 VtListTensorDesc => format_tensors(&list.td),
-                VtListTensor => format_tensor_defs(&list.t),
-                /// unimplemented
-                VtListGraph => todo!(),
-                /// unimplemented
-                VtListNamedAttrs => todo!(),
+                VtListTensor => AttrValue::List(list.t.iter().map(format_tensor_def).collect()),
+                VtListGraph => AttrValue::List(list.g.iter().map(format_graph).collect()),
+                VtListNamedAttrs => AttrValue::List(
+                    list.na
+                        .iter()
+                        .map(|na| Map([format_named_attrs(na)].into_iter().collect()))
+                        .collect(),
+                ),
                 VtListDataType => {
                     let vals = list.dt().map(|s| String::from(s.as_str_name())).collect();
-                    StringLikeArray(vals)
+                    Strs(vals)
                 }
             }
         }
-        /// unimplemented
-        Func(_) => return None,
-        Td(td) => TensorVal(td.fmt()),
-        T(t) => match &t.desc {
-            Some(td) => TensorVal(td.fmt()),
-            _ => StringLike(String::null()),
-        },
-        /// unimplemented
-        G(_) => return None,
+        Func(func) => Map([format_named_attrs(func)].into_iter().collect()),
+        Td(td) => Tensor(td.tensor_type()),
+        T(t) => format_tensor_def(t),
+        G(g) => format_graph(g),
         ListListInt(lli) => {
-            StringLikeArray(lli.list_list_i.iter().map(|l| String::from_i64s(&l.list_i)).collect())
-        }
-        ListListFloat(llf) => {
-            StringLikeArray(llf.list_list_f.iter().map(|l| String::from_f32s(&l.list_f)).collect())
+            AttrValue::List(lli.list_list_i.iter().map(|l| Ints(l.list_i.clone())).collect())
         }
-        /// used of deprecated api
-        Dt(dt) => StringLike(DataType::try_from(*dt).unwrap().as_str_name().into()),
+        ListListFloat(llf) => AttrValue::List(
+            llf.list_list_f
+                .iter()
+                .map(|l| Floats(l.list_f.iter().map(|&f| widen(f)).collect()))
+                .collect(),
+        ),
+        Dt(dt) => match DataType::try_from(*dt) {
+            Ok(dt) => Str(dt.as_str_name().into()),
+            Err(_) => Unknown(attr.encoded_len()),
+        },
     };
 
     Some(val)
 }
 
+#[inline]
+fn format_graph(graph: &GraphDef) -> AttrValue {
+    Graph(String::from(&graph.name))
+}
+
+/// Attributes without a value are dropped, as they are for nodes.
+fn format_named_attrs(named: &NamedAttrs) -> (String, AttrValue) {
+    let attrs = named
+        .attr
+        .iter()
+        .filter_map(|(name, attr)| Some((String::from(name), parse_attr(attr)?)))
+        .collect();
+    (String::from(&named.name), Map(attrs))
+}
+
 /// maybe t.desc is always Some
-fn format_tensor_defs(values: &[TensorDef]) -> AttrValue {
-    StringLikeArray(
-        values
+fn format_tensor_def(value: &TensorDef) -> AttrValue {
+    match &value.desc {
+        Some(td) => Tensor(td.tensor_type()),
+        _ => Str(String::null()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geir::{
+        OpDef, ShapeDef,
+        attr_def::{
+            ListListFloat, ListListInt, ListValue, Value, list_list_float::ListFloat,
+            list_list_int::ListInt, list_value::ListValueType,
+        },
+    };
+
+    use super::*;
+    use crate::{AttrFormat, with_attr_format, with_attr_source};
+
+    // xorshift64*, enough to drive the generator deterministically without extra dependencies
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: u64) -> usize {
+            (self.next() % n) as usize
+        }
+
+        // mostly valid enum values, sometimes out of range
+        fn enum_value(&mut self, max: i32) -> i32 {
+            match self.below(4) {
+                0 => self.next() as i32,
+                _ => self.below(max as u64 + 1) as i32,
+            }
+        }
+
+        fn bytes(&mut self) -> Vec<u8> {
+            (0..self.below(8)).map(|_| self.next() as u8).collect()
+        }
+
+        fn vec<T>(&mut self, mut f: impl FnMut(&mut Rng) -> T) -> Vec<T> {
+            (0..self.below(4)).map(|_| f(self)).collect()
+        }
+    }
+
+    fn descriptor(rng: &mut Rng) -> TensorDescriptor {
+        TensorDescriptor {
+            dtype: rng.enum_value(40),
+            shape: (rng.below(2) == 0).then(|| ShapeDef { dim: rng.vec(|r| r.next() as i64) }),
+            layout: String::from_vecu8(&rng.bytes()).into(),
+            ..Default::default()
+        }
+    }
+
+    fn tensor(rng: &mut Rng) -> TensorDef {
+        TensorDef { desc: (rng.below(2) == 0).then(|| descriptor(rng)), ..Default::default() }
+    }
+
+    fn graph(rng: &mut Rng) -> GraphDef {
+        GraphDef { name: String::from_vecu8(&rng.bytes()).into(), ..Default::default() }
+    }
+
+    fn named_attrs(rng: &mut Rng, depth: usize) -> NamedAttrs {
+        let attr =
+            rng.vec(|r| (format!("a{}", r.below(3)), attr(r, depth + 1))).into_iter().collect();
+        NamedAttrs { name: format!("n{}", rng.below(3)), attr }
+    }
+
+    fn attr(rng: &mut Rng, depth: usize) -> AttrDef {
+        let value = match rng.below(if depth > 3 { 9 } else { 14 }) {
+            0 => None,
+            1 => Some(Value::S(rng.bytes())),
+            2 => Some(Value::I(rng.next() as i64)),
+            3 => Some(Value::F(f32::from_bits(rng.next() as u32))),
+            4 => Some(Value::B(rng.below(2) == 0)),
+            5 => Some(Value::Bt(rng.bytes())),
+            6 => Some(Value::Dt(rng.enum_value(40))),
+            7 => Some(Value::Td(descriptor(rng))),
+            8 => Some(Value::T(tensor(rng))),
+            9 => Some(Value::G(graph(rng))),
+            10 => Some(Value::Func(named_attrs(rng, depth))),
+            11 => Some(Value::ListListInt(ListListInt {
+                list_list_i: rng.vec(|r| ListInt { list_i: r.vec(|r| r.next() as i64) }),
+            })),
+            12 => Some(Value::ListListFloat(ListListFloat {
+                list_list_f: rng.vec(|r| ListFloat { list_f: r.vec(|r| r.next() as f32) }),
+            })),
+            _ => Some(Value::List(list(rng, depth))),
+        };
+        AttrDef { value }
+    }
+
+    // any combination of fields, regardless of val_type
+    fn list(rng: &mut Rng, depth: usize) -> ListValue {
+        let mut list = ListValue { val_type: rng.enum_value(10), ..Default::default() };
+        for _ in 0..rng.below(3) {
+            match rng.below(10) {
+                0 => list.s = rng.vec(Rng::bytes),
+                1 => list.i = rng.vec(|r| r.next() as i64),
+                2 => list.f = rng.vec(|r| r.next() as f32),
+                3 => list.b = rng.vec(|r| r.below(2) == 0),
+                4 => list.bt = rng.vec(Rng::bytes),
+                5 => list.td = rng.vec(descriptor),
+                6 => list.t = rng.vec(tensor),
+                7 => list.g = rng.vec(graph),
+                8 => list.na = rng.vec(|r| named_attrs(r, depth)),
+                _ => list.dt = rng.vec(|r| r.enum_value(40)),
+            }
+        }
+        list
+    }
+
+    fn list_attr(list: ListValue) -> AttrDef {
+        AttrDef { value: Some(Value::List(list)) }
+    }
+
+    #[test]
+    fn test_previously_panicking_values() {
+        use ListValueType::*;
+
+        let graphs = ListValue {
+            val_type: VtListGraph as i32,
+            g: vec![GraphDef { name: "then_branch".into(), ..Default::default() }],
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(AttrValue::List(vec![Graph("then_branch".into())])),
+            parse_attr(&list_attr(graphs))
+        );
+
+        let named = |group: i64| {
+            let inner = AttrDef { value: Some(Value::I(group)) };
+            NamedAttrs { name: "conv".into(), attr: [("group".into(), inner)].into() }
+        };
+        let named = ListValue {
+            val_type: VtListNamedAttrs as i32,
+            na: vec![named(3), named(4)],
+            ..Default::default()
+        };
+        let Some(AttrValue::List(list)) = parse_attr(&list_attr(named)) else { panic!() };
+        let groups: Vec<_> = list
             .iter()
-            .map(|t| match &t.desc {
-                Some(t) => t.fmt(),
-                _ => String::null(),
+            .map(|value| {
+                let Map(map) = value else { panic!() };
+                let Some(Map(conv)) = map.get("conv") else { panic!() };
+                conv.get("group").cloned()
             })
-            .collect(),
-    )
+            .collect();
+        assert_eq!(vec![Some(Int(3)), Some(Int(4))], groups);
+
+        let bytes = ListValue {
+            val_type: VtListBytes as i32,
+            bt: vec![b"weight".to_vec()],
+            ..Default::default()
+        };
+        assert_eq!(Some(Strs(vec!["weight".into()])), parse_attr(&list_attr(bytes)));
+
+        assert_eq!(Some(Strs(vec![])), parse_attr(&list_attr(ListValue::default())));
+        let stray = ListValue { i: vec![1, 2], ..Default::default() };
+        assert!(matches!(parse_attr(&list_attr(stray)), Some(Unknown(len)) if len > 0));
+
+        let dt = AttrDef { value: Some(Value::Dt(9999)) };
+        assert!(matches!(parse_attr(&dt), Some(Unknown(len)) if len == dt.encoded_len()));
+        let dt = AttrDef { value: Some(Value::Dt(DataType::DtFloat as i32)) };
+        assert_eq!(Some(Str("DT_FLOAT".into())), parse_attr(&dt));
+
+        let tenth = AttrDef { value: Some(Value::F(0.1)) };
+        assert_eq!(Some(Float(0.1)), parse_attr(&tenth));
+
+        let empty_td = ListValue { val_type: VtListTensorDesc as i32, ..Default::default() };
+        assert_eq!(Some(Tensors(vec![])), parse_attr(&list_attr(empty_td)));
+    }
+
+    // the compact JSON the frontend reads for GEIR attributes
+    fn compact(value: Value) -> serde_json::Value {
+        let value = parse_attr(&AttrDef { value: Some(value) }).unwrap();
+        with_attr_format(AttrFormat::Compact, || {
+            with_attr_source("geir", || serde_json::to_value(&value).unwrap())
+        })
+    }
+
+    #[test]
+    fn test_compact_json() {
+        use ListValueType::*;
+        use serde_json::json;
+
+        let list = |val_type: ListValueType, list: ListValue| {
+            Value::List(ListValue { val_type: val_type as i32, ..list })
+        };
+        let desc = |dims: Option<Vec<i64>>| TensorDescriptor {
+            dtype: DataType::DtFloat as i32,
+            shape: dims.map(|dim| ShapeDef { dim }),
+            layout: "NCHW".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(json!({"type": "string-like", "value": "3"}), compact(Value::I(3)));
+        assert_eq!(json!({"type": "string-like", "value": "0.1"}), compact(Value::F(0.1)));
+        assert_eq!(json!({"type": "string-like", "value": "true"}), compact(Value::B(true)));
+        let ints = ListValue { i: vec![1, 2], ..Default::default() };
+        assert_eq!(json!({"type": "string-like", "value": "1, 2"}), compact(list(VtListInt, ints)));
+        let bools = ListValue { b: vec![true, false], ..Default::default() };
+        assert_eq!(
+            json!({"type": "string-like", "value": "true,false"}),
+            compact(list(VtListBool, bools))
+        );
+        let strs = ListValue { s: vec![b"a".to_vec(), b"b".to_vec()], ..Default::default() };
+        assert_eq!(
+            json!({"type": "string-like-array", "value": ["a", "b"]}),
+            compact(list(VtListString, strs))
+        );
+        assert_eq!(
+            json!({"type": "string-like-array", "value": []}),
+            compact(list(VtListNone, ListValue::default()))
+        );
+
+        assert_eq!(
+            json!({"type": "tensor-val", "value": "DtFloat(1, 2)【NCHW】"}),
+            compact(Value::Td(desc(Some(vec![1, 2]))))
+        );
+        assert_eq!(
+            json!({"type": "tensor-val", "value": "DtFloat【NCHW】"}),
+            compact(Value::Td(desc(None)))
+        );
+        let bare = TensorDescriptor { dtype: DataType::DtFloat as i32, ..Default::default() };
+        assert_eq!(json!({"type": "tensor-val", "value": "DtFloat【】"}), compact(Value::Td(bare)));
+        assert_eq!(
+            json!({"type": "string-like", "value": "NULL"}),
+            compact(Value::T(TensorDef::default()))
+        );
+        let one = ListValue { td: vec![desc(Some(vec![1]))], ..Default::default() };
+        assert_eq!(
+            json!({"type": "string-like", "value": "DtFloat(1)【NCHW】"}),
+            compact(list(VtListTensorDesc, one))
+        );
+        let tensors = ListValue {
+            t: vec![
+                TensorDef { desc: Some(desc(Some(vec![1]))), ..Default::default() },
+                TensorDef::default(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            json!({"type": "string-like-array", "value": ["DtFloat(1)【NCHW】", "NULL"]}),
+            compact(list(VtListTensor, tensors))
+        );
+
+        let graph = |name: &str| GraphDef { name: name.into(), ..Default::default() };
+        assert_eq!(
+            json!({"type": "string-like", "value": "Graph(g)"}),
+            compact(Value::G(graph("g")))
+        );
+        let graphs = ListValue { g: vec![graph("a"), graph("b")], ..Default::default() };
+        assert_eq!(
+            json!({"type": "string-like-array", "value": ["Graph(a)", "Graph(b)"]}),
+            compact(list(VtListGraph, graphs))
+        );
+        let lli = ListListInt {
+            list_list_i: vec![ListInt { list_i: vec![1, 2] }, ListInt { list_i: vec![3] }],
+        };
+        assert_eq!(
+            json!({"type": "string-like-array", "value": ["1, 2", "3"]}),
+            compact(Value::ListListInt(lli))
+        );
+        let dts = ListValue { dt: vec![DataType::DtFloat as i32], ..Default::default() };
+        assert_eq!(
+            json!({"type": "string-like-array", "value": ["DT_FLOAT"]}),
+            compact(list(VtListDataType, dts))
+        );
+
+        let inner = AttrDef { value: Some(Value::I(3)) };
+        let named = NamedAttrs { name: "conv".into(), attr: [("group".into(), inner)].into() };
+        assert_eq!(
+            json!({
+                "type": "map",
+                "value": {"conv": {"type": "map", "value": {"group": {"type": "string-like", "value": "3"}}}},
+            }),
+            compact(Value::Func(named))
+        );
+    }
+
+    #[test]
+    fn test_text_dump() {
+        // as GE writes its `ge_onnx_*.pbtxt` dumps
+        let text = r#"
+name: "ge_model"
+graph {
+  name: "main"
+  op {
+    name: "conv1"
+    type: "Conv2D"
+    input: "data:0"
+    attr {
+      key: "strides"
+      value {
+        list {
+          i: 1
+          i: 1
+          val_type: VT_LIST_INT
+        }
+      }
+    }
+    attr {
+      key: "dtype"
+      value {
+        dt: DT_FLOAT
+      }
+    }
+  }
+}
+"#;
+        let model = crate::text_format::parse::<ModelDef>(text).unwrap();
+
+        let strides = ListValue {
+            i: vec![1, 1],
+            val_type: ListValueType::VtListInt as i32,
+            ..Default::default()
+        };
+        let dtype = AttrDef { value: Some(Value::Dt(DataType::DtFloat as i32)) };
+        let op = OpDef {
+            name: "conv1".into(),
+            r#type: "Conv2D".into(),
+            input: vec!["data:0".into()],
+            attr: [("strides".into(), list_attr(strides)), ("dtype".into(), dtype)].into(),
+            ..Default::default()
+        };
+        let graph = GraphDef { name: "main".into(), op: vec![op], ..Default::default() };
+        assert_eq!(
+            ModelDef { name: "ge_model".into(), graph: vec![graph], ..Default::default() },
+            model
+        );
+    }
+
+    #[test]
+    fn test_random_attrs_never_panic() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..20_000 {
+            let attr = attr(&mut rng, 0);
+            parse_attr(&attr);
+
+            // corrupt the encoded message, whatever still decodes must parse as well
+            let mut bytes = attr.encode_to_vec();
+            for _ in 0..rng.below(4) {
+                if !bytes.is_empty() {
+                    let at = rng.below(bytes.len() as u64);
+                    bytes[at] = rng.next() as u8;
+                }
+            }
+            if let Ok(attr) = AttrDef::decode(&*bytes) {
+                parse_attr(&attr);
+            }
+        }
+    }
 }
@@ -2,6 +2,8 @@ mod mindir {
     include!(concat!(env!("OUT_DIR"), "/mindir.rs"));
 }
 
+use std::{collections::BTreeMap, io};
+
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use mindir::{
     AttributeProto, GraphProto, ModelProto, NodeProto, PrimitiveProto, TensorProto,
@@ -9,18 +11,17 @@ use mindir::{
 };
 use smartstring::alias::String;
 
-use super::{TensorFormatter, format_tensors, parse_pb};
-use crate::{AttrValue, AttrValue::*, Model, Node, SmartStringExt, StrExt};
+use super::{TensorFormatter, format_tensors, invalid_data, parse_pb, widen};
+use crate::{AttrValue, AttrValue::*, Model, ModelInfo, Node, SmartStringExt, StrExt, TensorType};
 
-pub fn parse_mindir_model(path: &str) -> Option<Model> {
-    match parse_pb::<ModelProto>(path) {
-        Ok(model_proto) => model_proto.into(),
-        _ => None,
-    }
+pub fn parse_mindir_model(path: &str) -> io::Result<Model> {
+    let model: Option<Model> = parse_pb::<ModelProto>(path)?.into();
+    model.ok_or_else(|| invalid_data("no graph in the model"))
 }
 
 impl From<ModelProto> for Option<Model> {
     fn from(model: ModelProto) -> Self {
+        let info = model_info(&model);
         if let Some(graph) = model.graph {
             let name = String::from(graph.name());
 
@@ -33,7 +34,10 @@ impl From<ModelProto> for Option<Model> {
                 name,
                 nodes: ctx.nodes,
                 edges: vec![],
+                typed_edges: vec![],
                 parameters: ctx.parameters,
+                value_info: HashMap::new(),
+                info,
             });
         }
 
@@ -41,6 +45,26 @@ impl From<ModelProto> for Option<Model> {
     }
 }
 
+fn model_info(model: &ModelProto) -> ModelInfo {
+    let mut metadata: BTreeMap<String, String> =
+        model.user_info.iter().map(|(k, v)| (String::from(k), String::from(v))).collect();
+    if let Some(version) = model.mind_ir_version {
+        metadata.insert(String::from("mind_ir_version"), String::from_i64(version));
+    }
+
+    ModelInfo {
+        format: String::from("mindir"),
+        ir_version: String::from(model.ir_version()),
+        producer_name: String::from(model.producer_name()),
+        producer_version: String::from(model.producer_version()),
+        domain: String::from(model.domain()),
+        model_version: String::from(model.model_version()),
+        doc_string: String::from(model.doc_string()),
+        metadata,
+        ..Default::default()
+    }
+}
+
 struct Context {
     prefix: String,
     nodes: HashMap<String, Node>,
@@ -91,12 +115,12 @@ impl Context {
             self.node_names.insert(output.name.strip_prefix1(self.prefix()));
         }
 
-        for node in &graph.node {
-            self.process_node(node);
+        for (index, node) in graph.node.iter().enumerate() {
+            self.process_node(index, node);
         }
     }
 
-    fn process_node(&mut self, node: &NodeProto) -> Option<()> {
+    fn process_node(&mut self, index: usize, node: &NodeProto) -> Option<()> {
         if let Some(op_type) = node.op_type1(self) {
             let node_name = node.stripped_name(self.prefix());
 
@@ -109,6 +133,10 @@ impl Context {
                 output,
                 attributes,
                 dynamic: false,
+                index,
+                domain: String::from(node.domain()),
+                doc_string: String::from(node.doc_string()),
+                output_shapes: vec![],
             });
         }
 
@@ -185,52 +213,118 @@ impl NodeProto {
 }
 
 fn parse_attr(attr: &AttributeProto) -> Option<AttrValue> {
-    use AttributeType::{
-        Bfloat16, Complex64, Complex128, Double, Float, Float16, Int8, Int16, Int32, Int64, Tensor,
-        Tensors, Tuple, TypeNull, Uint8, Uint16, Uint32, Uint64, Undefined,
+    /// Disrupted the order and put the most common cases in front
+    let value = match attr.r#type() {
+        AttributeType::Tensor => Tensor(attr.t.as_ref()?.tensor_type()),
+        _ => parse_value(attr),
     };
 
-    let discriminator = attr.r#type();
+    Some(value)
+}
 
-    /// Disrupted the order and put the most common cases in front
-    let value = match discriminator {
-        Tensor => StringLike(attr.t.as_ref()?.fmt()),
-        Tensors => format_tensors(&attr.tensors),
-        Tuple => format_tuple(&attr.values),
-        AttributeType::String => StringLike(String::from_vecu8(attr.s())),
-        Undefined | AttributeType::None | TypeNull => StringLike(String::from("Undefined")),
-        Double => StringLike(String::from_f64(attr.d())),
-        Float => StringLike(String::from_f32(attr.f())),
-        Float16 => StringLike(format!("f16({})", String::from_f32(attr.f())).into()),
-        Bfloat16 => StringLike(format!("bf16({})", String::from_f32(attr.f())).into()),
-        Uint8 | Int8 | Uint16 | Int16 | Uint32 | Int32 | Uint64 | Int64 => StringLike("".into()),
-        Complex64 | Complex128 => StringLike("".into()),
-        /// ```ts
-        /// Bool => "",
-        /// List => "",
-        /// Dict => "",
-        /// Umonad => "",
-        /// Iomonad => "",
-        /// Primitiveclosure => "",
-        /// Funcgraphclosure => "",
-        /// Partialclosure => "",
-        /// Unionfuncclosure => "",
-        /// CsrTensor => "",
-        /// CooTensor => "",
-        /// RowTensor => "",
-        /// ClassType => "",
-        /// NameSpace => "",
-        /// Symbol => "",
-        /// MapTensor => "",
-        /// Functor => "",
-        /// Scalar => "",
-        /// ScalarGraphHolder => "",
-        /// Graph => "",
-        /// ```
-        _ => StringLike("".into()),
+/// ## Explanation
+/// Converts any attribute into a typed value, [`List`], [`Tuple`] and [`Dict`] recursively.
+///
+/// MindIR keeps every integer type (including [`Bool`]) in `i`, so [`Uint64`] is
+/// reinterpreted from `int64`. As an [`Int`] can't hold values beyond `i64::MAX`,
+/// every [`Uint64`] is kept as its decimal string, so its type doesn't depend on its value.
+/// Complex numbers are stored as `[real, imag]` in `floats` ([`Complex64`]) or
+/// `doubles` ([`Complex128`]).
+///
+/// Values without a typed counterpart, e.g. closures, monads and sparse tensors,
+/// are kept as their rendered string, for closures, classes and symbols only the name
+/// in `s` is meaningful for display.
+fn parse_value(attr: &AttributeProto) -> AttrValue {
+    use AttributeType::{
+        Bfloat16, Bool, ClassType, Complex64, Complex128, CooTensor, CsrTensor, Dict, Double,
+        Float, Float16, Funcgraphclosure, Functor, Graph, Int8, Int16, Int32, Int64, Iomonad, List,
+        MapTensor, NameSpace, Partialclosure, Primitiveclosure, RowTensor, Scalar,
+        ScalarGraphHolder, Symbol, Tensor, Tensors, Tuple, TypeNull, Uint8, Uint16, Uint32, Uint64,
+        Umonad, Undefined, Unionfuncclosure,
     };
 
-    Some(value)
+    let kind = attr.r#type();
+    match kind {
+        Undefined | TypeNull => Str(String::from("Undefined")),
+        AttributeType::None => Str(String::from("None")),
+        Float => AttrValue::Float(widen(attr.f())),
+        Float16 => AttrValue::Float16(widen(attr.f())),
+        Bfloat16 => AttrValue::Bfloat16(widen(attr.f())),
+        Double => AttrValue::Float(attr.d()),
+        Uint8 | Int8 | Uint16 | Int16 | Uint32 | Int32 | Int64 => Int(attr.i()),
+        Uint64 => Str(format!("{}", attr.i() as u64).into()),
+        Bool => AttrValue::Bool(attr.i() != 0),
+        Complex64 => match attr.floats[..] {
+            [re, im, ..] => format_complex(String::from_f32(re), String::from_f32(im)),
+            _ => Str(String::new()),
+        },
+        Complex128 => match attr.doubles[..] {
+            [re, im, ..] => format_complex(String::from_f64(re), String::from_f64(im)),
+            _ => Str(String::new()),
+        },
+        AttributeType::String => Str(String::from_vecu8(attr.s())),
+        Tensor => match &attr.t {
+            Some(t) => AttrValue::Tensor(t.tensor_type()),
+            _ => Str(String::null()),
+        },
+        Tensors => format_tensors(&attr.tensors),
+        Graph => AttrValue::Graph(attr.g.as_ref().map_or("", |g| g.name()).into()),
+        Tuple => AttrValue::Tuple(attr.values.iter().map(parse_value).collect()),
+        List => AttrValue::List(attr.values.iter().map(parse_value).collect()),
+        Dict => AttrValue::Dict(
+            attr.values.iter().map(|v| (String::from(v.name()), parse_value(v))).collect(),
+        ),
+        Umonad => Str(String::from("UMonad")),
+        Iomonad => Str(String::from("IOMonad")),
+        Primitiveclosure | Funcgraphclosure | Partialclosure | Unionfuncclosure | ClassType
+        | NameSpace | Symbol => Str(format!("{:?}({})", kind, String::from_vecu8(attr.s())).into()),
+        CsrTensor | CooTensor | RowTensor | MapTensor => {
+            let parts = attr
+                .tensors
+                .iter()
+                .map(|t| AttrValue::Tensor(t.tensor_type()))
+                .chain(attr.values.iter().map(parse_value));
+            Str(format!("{:?}[{}]", kind, join(parts)).into())
+        }
+        Functor => match &attr.functor {
+            Some(functor) => {
+                let values = join(functor.values.iter().map(parse_value));
+                Str(format!("Functor({})[{}]", functor.name(), values).into())
+            }
+            _ => Str(String::from("Functor")),
+        },
+        /// A scalar keeps its value in whichever field matches its own dtype.
+        Scalar => {
+            if let Some(i) = attr.i {
+                Int(i)
+            } else if let Some(f) = attr.f {
+                AttrValue::Float(widen(f))
+            } else if let Some(d) = attr.d {
+                AttrValue::Float(d)
+            } else {
+                Str(String::from_vecu8(attr.s()))
+            }
+        }
+        ScalarGraphHolder => match &attr.graph_holder {
+            Some(holder) => {
+                Str(format!("ScalarGraphHolder({} nodes)", holder.scalar_node.len()).into())
+            }
+            _ => Str(String::from("ScalarGraphHolder")),
+        },
+    }
+}
+
+#[inline]
+fn format_complex(re: String, im: String) -> AttrValue {
+    match im.strip_prefix('-') {
+        Some(abs) => Str(format!("({}-{}j)", re, abs).into()),
+        _ => Str(format!("({}+{}j)", re, im).into()),
+    }
+}
+
+#[inline]
+fn join(parts: impl Iterator<Item = AttrValue>) -> std::string::String {
+    parts.map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
 }
 
 /// ## Safety
@@ -248,13 +342,181 @@ fn strip_op(raw: String) -> String {
 
 impl TensorFormatter for TensorProto {
     #[inline]
-    fn fmt(&self) -> String {
-        let dims = String::from_i64s(&self.dims);
-        format!("{:?}({})", self.dtype(), dims).into()
+    fn tensor_type(&self) -> TensorType {
+        TensorType::new(format!("{:?}", self.dtype()), &self.dims)
     }
 }
 
-#[inline]
-fn format_tuple(values: &[AttributeProto]) -> AttrValue {
-    TensorsTuple(values.iter().map(|ts| ts.tensors.iter().map(|t| t.fmt()).collect()).collect())
+#[cfg(test)]
+mod tests {
+    use mindir::{FunctorProto, ScalarGraphHolderProto, ScalarNodeProto, tensor_proto::DataType};
+
+    use super::*;
+    use crate::{AttrFormat, with_attr_format, with_attr_source};
+
+    fn attr(kind: AttributeType) -> AttributeProto {
+        AttributeProto { r#type: Some(kind as i32), ..Default::default() }
+    }
+
+    fn int(kind: AttributeType, i: i64) -> AttributeProto {
+        AttributeProto { i: Some(i), ..attr(kind) }
+    }
+
+    fn tensor(dims: &[i64]) -> TensorProto {
+        TensorProto {
+            dims: dims.to_vec(),
+            dtype: Some(DataType::Float as i32),
+            ..Default::default()
+        }
+    }
+
+    // the compact JSON the frontend reads for MindIR attributes
+    fn compact(attr: &AttributeProto) -> serde_json::Value {
+        let value = parse_attr(attr).unwrap();
+        with_attr_format(AttrFormat::Compact, || {
+            with_attr_source("mindir", || serde_json::to_value(&value).unwrap())
+        })
+    }
+
+    fn render(attr: &AttributeProto) -> std::string::String {
+        match compact(attr) {
+            serde_json::Value::Object(mut v) if v["type"] == "string-like" => {
+                let s = v.remove("value").unwrap();
+                s.as_str().unwrap().to_owned()
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_scalars() {
+        use AttributeType::*;
+
+        for kind in [Uint8, Int8, Uint16, Int16, Uint32, Int32, Int64] {
+            assert_eq!("-3", render(&int(kind, -3)), "{:?}", kind);
+        }
+        assert_eq!("18446744073709551615", render(&int(Uint64, -1)));
+        assert_eq!(Some(Int(-3)), parse_attr(&int(Int8, -3)));
+        assert_eq!(Some(Str("7".into())), parse_attr(&int(Uint64, 7)));
+        assert_eq!("true", render(&int(Bool, 1)));
+        assert_eq!("false", render(&attr(Bool)));
+        assert_eq!("0.5", render(&AttributeProto { f: Some(0.5), ..attr(Float) }));
+        assert_eq!("f16(0.5)", render(&AttributeProto { f: Some(0.5), ..attr(Float16) }));
+        assert_eq!("bf16(0.5)", render(&AttributeProto { f: Some(0.5), ..attr(Bfloat16) }));
+        let tenth = AttributeProto { f: Some(0.1), ..attr(Float) };
+        assert_eq!(Some(AttrValue::Float(0.1)), parse_attr(&tenth));
+        assert_eq!("0.25", render(&AttributeProto { d: Some(0.25), ..attr(Double) }));
+        let s = AttributeProto { s: Some(b"NCHW".to_vec()), ..attr(AttributeType::String) };
+        assert_eq!("NCHW", render(&s));
+        assert_eq!("Undefined", render(&attr(Undefined)));
+        assert_eq!("Undefined", render(&attr(TypeNull)));
+        assert_eq!("None", render(&attr(AttributeType::None)));
+    }
+
+    #[test]
+    fn test_complex() {
+        let c64 = AttributeProto { floats: vec![1.0, -2.0], ..attr(AttributeType::Complex64) };
+        assert_eq!("(1.0-2.0j)", render(&c64));
+        let c128 = AttributeProto { doubles: vec![0.5, 3.0], ..attr(AttributeType::Complex128) };
+        assert_eq!("(0.5+3.0j)", render(&c128));
+        assert_eq!("", render(&attr(AttributeType::Complex64)));
+    }
+
+    #[test]
+    fn test_nested_containers() {
+        use AttributeType::*;
+
+        let list = AttributeProto { values: vec![int(Int64, 3), int(Int64, 3)], ..attr(List) };
+        assert_eq!("[3, 3]", render(&list));
+
+        let tuple = AttributeProto { values: vec![int(Int32, 1), list.clone()], ..attr(Tuple) };
+        assert_eq!("(1, [3, 3])", render(&tuple));
+
+        let keep_dims = AttributeProto { name: Some("keep_dims".into()), ..int(Bool, 0) };
+        let axis = AttributeProto { name: Some("axis".into()), ..list };
+        let dict = AttributeProto { values: vec![keep_dims, axis], ..attr(Dict) };
+        assert_eq!("{keep_dims: false, axis: [3, 3]}", render(&dict));
+
+        let empty = attr(List);
+        assert_eq!("[]", render(&empty));
+    }
+
+    #[test]
+    fn test_tensors() {
+        use AttributeType::*;
+
+        let t = AttributeProto { t: Some(tensor(&[2, 3])), ..attr(Tensor) };
+        assert_eq!("Float(2, 3)", render(&t));
+        assert!(parse_attr(&attr(Tensor)).is_none());
+
+        let ts = AttributeProto { tensors: vec![tensor(&[1]), tensor(&[2])], ..attr(Tensors) };
+        assert_eq!(
+            serde_json::json!({"type": "tensor-vals", "value": ["Float(1)", "Float(2)"]}),
+            compact(&ts)
+        );
+        let one = AttributeProto { tensors: vec![tensor(&[1])], ..attr(Tensors) };
+        assert_eq!("Float(1)", render(&one));
+        assert_eq!("[]", render(&attr(Tensors)));
+
+        // a tuple made of tensors keeps the tensors-tuple form
+        let tuple = AttributeProto { values: vec![ts.clone(), ts], ..attr(Tuple) };
+        assert_eq!(
+            serde_json::json!({
+                "type": "tensors-tuple",
+                "value": [["Float(1)", "Float(2)"], ["Float(1)", "Float(2)"]],
+            }),
+            compact(&tuple)
+        );
+        assert_eq!(
+            "([Float(1), Float(2)], [Float(1), Float(2)])",
+            parse_attr(&tuple).unwrap().to_string()
+        );
+
+        let coo =
+            AttributeProto { tensors: vec![tensor(&[4]), tensor(&[4, 2])], ..attr(CooTensor) };
+        assert_eq!("CooTensor[Float(4), Float(4, 2)]", render(&coo));
+        assert_eq!("CsrTensor[]", render(&attr(CsrTensor)));
+        assert_eq!("RowTensor[]", render(&attr(RowTensor)));
+        assert_eq!("MapTensor[]", render(&attr(MapTensor)));
+    }
+
+    #[test]
+    fn test_graph_like() {
+        use AttributeType::*;
+
+        let graph = GraphProto { name: Some("body".into()), ..Default::default() };
+        assert_eq!("Graph(body)", render(&AttributeProto { g: Some(graph), ..attr(Graph) }));
+        assert_eq!("UMonad", render(&attr(Umonad)));
+        assert_eq!("IOMonad", render(&attr(Iomonad)));
+        for kind in [
+            Primitiveclosure,
+            Funcgraphclosure,
+            Partialclosure,
+            Unionfuncclosure,
+            ClassType,
+            NameSpace,
+            Symbol,
+        ] {
+            let named = AttributeProto { s: Some(b"f".to_vec()), ..attr(kind) };
+            assert_eq!(format!("{:?}(f)", kind), render(&named).as_str());
+        }
+
+        let functor = FunctorProto {
+            name: Some("ShapeCalc".into()),
+            values: vec![int(Int64, 2)],
+            ..Default::default()
+        };
+        let functor = AttributeProto { functor: Some(functor), ..attr(Functor) };
+        assert_eq!("Functor(ShapeCalc)[2]", render(&functor));
+        assert_eq!("Functor", render(&attr(Functor)));
+
+        assert_eq!("7", render(&int(Scalar, 7)));
+        assert_eq!("1.5", render(&AttributeProto { d: Some(1.5), ..attr(Scalar) }));
+        let holder = ScalarGraphHolderProto {
+            scalar_node: vec![ScalarNodeProto::default()],
+            ..Default::default()
+        };
+        let holder = AttributeProto { graph_holder: Some(holder), ..attr(ScalarGraphHolder) };
+        assert_eq!("ScalarGraphHolder(1 nodes)", render(&holder));
+    }
 }
@@ -1,74 +1,165 @@
 use std::{
     fs::File,
-    io::{BufReader, Read, Result},
+    io::{BufReader, Error, ErrorKind, Read, Result},
 };
 
-use prost::Message;
+use bytes::Bytes;
+use memmap2::Mmap;
+use prost::{
+    Message, Name,
+    encoding::{WireType, decode_key, decode_varint},
+};
 use smartstring::alias::String;
 
+pub mod anf_ir;
+pub use anf_ir::*;
+
 pub mod geir;
 pub use geir::*;
 
 pub mod mindir;
 pub use mindir::*;
 
+pub mod om;
+pub use om::*;
+
 pub mod onnx;
 pub use onnx::*;
 
-use crate::{AttrValue, AttrValue::*};
+pub mod tf;
+pub use tf::*;
+
+use crate::{AttrValue, AttrValue::*, TensorType, text_format, tfrecord};
 
 /// ## Note
 /// This generic function is designed for parsing proto buffer files,
 /// using bytes parsing, which is very high performance.
 ///
-/// We may never not support proto buffer text files.
-/// For comparison:
-/// - `30-line` [`pbtxt`] file (using [`protobuf`]) takes `1.4ms`,
-/// - `1800-line` [`pb`] file (using [`prost`]) takes `480μs`.
-///
-/// Instead, we recommend using the provided [`python`] script or writing one yourself,
-/// which only takes several lines to convert the pbtxt format to pb format
-///
-/// ```py
-/// message = ModelDef()
-/// with open(pbtxt_file_path, 'r') as f:
-///     text_format.Merge(f.read(), message)
-/// with open(binary_file_path, 'wb') as f:
-///     f.write(message.SerializeToString())
-/// ```
+/// Text dumps (see [`is_text_format`]) are read by [`text_format::parse`], no conversion
+/// to binary is needed, a mistake is reported with its line and column. For comparison (`cargo bench -p parser --bench text_format`):
+/// - an `11MB` [`pbtxt`] ONNX dump is encoded in `45ms` (about `250MB/s`),
+/// - parsing it takes `125ms`, `1.5x` the `83ms` of the same model as [`pb`].
 ///
 /// ## Performance Improvement
 /// If you parse binary directly without using prost,
 /// which can reduce memory allocation and loop process
+///
+/// Files from [`MMAP_THRESHOLD`] on are memory mapped rather than read, see [`read_bytes`].
 fn parse_pb<T>(path: &str) -> Result<T>
 where
-    T: Default + Message,
+    T: Default + Message + Name,
 {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
-    let mut buffer = vec![];
-    reader.read_to_end(&mut buffer)?;
+    if is_text_format(path) {
+        let text = std::fs::read_to_string(path)?;
+        return text_format::parse(&text).map_err(|e| Error::new(ErrorKind::InvalidData, e));
+    }
 
-    let model: T = Message::decode(&*buffer)?;
+    let model: T = Message::decode(read_bytes(path, MMAP_THRESHOLD)?)?;
 
     Ok(model)
 }
 
+/// The error for a file that was read but doesn't hold a model.
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Whether a file holds a protobuf text dump, by its extension.
+pub fn is_text_format(path: &str) -> bool {
+    [".pbtxt", ".prototxt", ".textproto", ".txtpb"].iter().any(|ext| path.ends_with(ext))
+}
+
+const MMAP_THRESHOLD: u64 = 64 << 20;
+
+/// ## Explanation
+/// ONNX models, ANF IR graph files and TensorFlow `GraphDef`s are all saved as `.pb`,
+/// the format (`"onnx"`, `"anf_ir"` or `"tensorflow"`) is told by the top level fields:
+/// - a `ModelProto` has a varint `ir_version` in field 1, ONNX keeps its `graph` in field 7
+///   and `opset_import` in field 8, ANF IR its `graph` in field 4
+/// - a `GraphDef` and a bare ANF IR `GraphProto` both start with repeated nodes,
+///   their nodes are decoded to tell them apart, see [`anf_ir::is_anf_ir_graph`]
+///
+/// A TFRecord-framed file is taken as a TensorFlow events file.
+pub fn detect_pb_format(path: &str) -> Result<&'static str> {
+    let buffer = read_bytes(path, MMAP_THRESHOLD)?;
+    detect_pb_bytes(&buffer).ok_or_else(|| invalid_data("not an ONNX, ANF IR or TensorFlow model"))
+}
+
+fn detect_pb_bytes(buffer: &[u8]) -> Option<&'static str> {
+    if let Some(Ok(_)) = tfrecord::records(buffer).next() {
+        return Some("tensorflow");
+    }
+
+    let fields = top_level_fields(buffer)?;
+    let has = |tag: u32, wire_type: WireType| fields.contains(&(tag, wire_type));
+    if has(7, WireType::LengthDelimited) || has(8, WireType::LengthDelimited) {
+        Some("onnx")
+    } else if has(1, WireType::LengthDelimited) {
+        Some(if anf_ir::is_anf_ir_graph(buffer) { "anf_ir" } else { "tensorflow" })
+    } else if has(4, WireType::LengthDelimited) {
+        Some("anf_ir")
+    } else {
+        None
+    }
+}
+
+/// The tag and wire type of each top level field, skipping over the values.
+fn top_level_fields(mut buffer: &[u8]) -> Option<Vec<(u32, WireType)>> {
+    let mut fields = vec![];
+    while !buffer.is_empty() {
+        let (tag, wire_type) = decode_key(&mut buffer).ok()?;
+        match wire_type {
+            WireType::Varint => {
+                decode_varint(&mut buffer).ok()?;
+            }
+            WireType::SixtyFourBit => buffer = buffer.get(8..)?,
+            WireType::ThirtyTwoBit => buffer = buffer.get(4..)?,
+            WireType::LengthDelimited => {
+                let len = usize::try_from(decode_varint(&mut buffer).ok()?).ok()?;
+                buffer = buffer.get(len..)?;
+            }
+            WireType::StartGroup | WireType::EndGroup => return None,
+        }
+        fields.push((tag, wire_type));
+    }
+    Some(fields)
+}
+
+/// ## Explanation
+/// Reads a file into a [`Bytes`] buffer, mapping it when it has at least `mmap_from` bytes.
+/// `bytes` fields generated as [`Bytes`] (ONNX `raw_data`) are then decoded as slices
+/// of the buffer: the payload of a mapped file is never copied, nor even paged in.
+///
+/// ## Note
+/// A mapped file truncated by another process while it's being decoded crashes the reader,
+/// the same risk every mmap based model loader takes.
+fn read_bytes(path: &str, mmap_from: u64) -> Result<Bytes> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() >= mmap_from {
+        let mmap = unsafe { Mmap::map(&file)? };
+        return Ok(Bytes::from_owner(mmap));
+    }
+
+    let mut buffer = vec![];
+    BufReader::new(file).read_to_end(&mut buffer)?;
+    Ok(buffer.into())
+}
+
 /// ## Explanation
 /// `Tensor` usually has two attributes: [`dtype`] and [`dims`],
 /// and sometimes also has attributes such as [`device`] and [`layout`].
-/// If this structure is sent directly to the front-end for processing,
-/// most of the time, the front-end only do a string concatenation,
-/// which brings the following disadvantages:
-/// - Increased communication data volume
-/// - Deeper object hierarchy (which may cause a sharp increase in JS GC time)
-/// - Slower string concatenation that must be run [`every-time`] rendering
+/// Only its type is kept, not the whole structure, since the front-end never needs the data.
 ///
 /// ## Note
-/// However, it cannot be ignored that if the edge width needs to be adjusted
-/// according to the data dimension, the design needs to be reconsidered.
+/// Rendering, e.g. `Float(2, 3)`, is done by [`crate::attr_format`],
+/// [`TensorFormatter::fmt`] is only a shortcut for places that store strings, such as parameters.
 trait TensorFormatter {
-    fn fmt(&self) -> String;
+    fn tensor_type(&self) -> TensorType;
+
+    #[inline]
+    fn fmt(&self) -> String {
+        self.tensor_type().to_string().into()
+    }
 }
 
 #[inline]
@@ -76,8 +167,43 @@ fn format_tensors<T>(tensors: &[T]) -> AttrValue
 where
     T: TensorFormatter,
 {
-    if tensors.len() < 2 {
-        return StringLike(tensors[0].fmt());
+    Tensors(tensors.iter().map(|t| t.tensor_type()).collect())
+}
+
+/// Widens a `f32` through its shortest representation, so `0.1f32` becomes `0.1f64`
+/// rather than `0.10000000149011612`.
+#[inline]
+fn widen(value: f32) -> f64 {
+    ryu::Buffer::new().format(value).parse().unwrap_or(value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_bytes() {
+        let path = std::env::temp_dir().join(format!("read_bytes_{}.pb", std::process::id()));
+        std::fs::write(&path, b"payload").unwrap();
+        let path_str = path.to_str().unwrap();
+        // mapped and read files give the same bytes
+        assert_eq!(&b"payload"[..], read_bytes(path_str, 0).unwrap());
+        assert_eq!(&b"payload"[..], read_bytes(path_str, u64::MAX).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_detect_onnx() {
+        use prost::encoding::{bytes, int64};
+
+        // `ir_version`, `graph` and `opset_import` of an ONNX `ModelProto`
+        let mut model = vec![];
+        int64::encode(1, &8, &mut model);
+        bytes::encode(7, &b"\x0a\x00".to_vec(), &mut model);
+        bytes::encode(8, &b"\x10\x11".to_vec(), &mut model);
+        assert_eq!(Some("onnx"), detect_pb_bytes(&model));
+
+        assert_eq!(None, detect_pb_bytes(b"\x08"));
+        assert_eq!(None, detect_pb_bytes(b"payload"));
     }
-    TensorVals(tensors.iter().map(|t| t.fmt()).collect())
 }
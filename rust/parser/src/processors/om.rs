@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+};
+
+use prost::Message;
+use smartstring::alias::String;
+
+use super::{geir::geir::ModelDef, invalid_data};
+use crate::{Model, SmartStringExt};
+
+/// `"IMOD"` read as a little endian `u32`
+const OM_MAGIC: u32 = 0x444F_4D49;
+const HEADER_LEN: u64 = 256;
+const ENTRY_LEN: u64 = 12;
+const MODEL_DEF: u32 = 0;
+
+/// The partition types, indexed by `ModelPartitionType`
+const PARTITION_NAMES: [&str; 9] = [
+    "model_def",
+    "weights_data",
+    "task_info",
+    "tbe_kernels",
+    "cust_aicpu_kernels",
+    "so_bins",
+    "flow_model",
+    "flow_submodel",
+    "model_inout_info",
+];
+
+/// ## Explanation
+/// An Ascend offline model (`.om`) is a binary header followed by a partition table,
+/// every field little endian:
+/// ```text
+/// | header | num    | type, offset, size | ... | partitions ...
+/// | 256 B  | uint32 | uint32 x 3         |
+/// ```
+/// Offsets are relative to the end of the table.
+/// The [`MODEL_DEF`] partition is a GE `ModelDef`, the same message as a `.geir` dump.
+///
+/// ## Note
+/// Only the model definition is read, the other partitions (weights, task info, kernels)
+/// are reported by size in the metadata as `<partition>_size`.
+/// Encrypted models and the 64-bit tables of models over 4 GB aren't supported.
+struct OmHeader {
+    version: u32,
+    encrypted: bool,
+    platform_version: String,
+    partitions: Vec<Partition>,
+}
+
+struct Partition {
+    kind: u32,
+    offset: u64,
+    size: u64,
+}
+
+fn read_header(reader: &mut impl Read) -> io::Result<OmHeader> {
+    let mut header = [0u8; HEADER_LEN as usize];
+    reader.read_exact(&mut header)?;
+    let u32_at =
+        |bytes: &[u8], at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    if u32_at(&header, 0) != OM_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an offline model"));
+    }
+
+    let mut num = [0u8; 4];
+    reader.read_exact(&mut num)?;
+    let len = u32::from_le_bytes(num) as u64 * ENTRY_LEN;
+    let mut table = vec![];
+    reader.take(len).read_to_end(&mut table)?;
+    if table.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let base = HEADER_LEN + 4 + table.len() as u64;
+    let partitions = table
+        .chunks_exact(ENTRY_LEN as usize)
+        .map(|entry| Partition {
+            kind: u32_at(entry, 0),
+            offset: base + u32_at(entry, 4) as u64,
+            size: u32_at(entry, 8) as u64,
+        })
+        .collect();
+    // `platform_version` is a NUL padded string at 160
+    let platform_version = header[160..180].split(|&b| b == 0).next().unwrap_or_default();
+
+    Ok(OmHeader {
+        version: u32_at(&header, 8),
+        encrypted: header[80] != 0,
+        platform_version: String::from_vecu8(platform_version),
+        partitions,
+    })
+}
+
+pub fn parse_om_model(path: &str) -> io::Result<Model> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = read_header(&mut reader)?;
+    if header.encrypted {
+        return Err(invalid_data("encrypted offline model"));
+    }
+
+    let model_def = header.partitions.iter().find(|p| p.kind == MODEL_DEF);
+    let model_def = model_def.ok_or_else(|| invalid_data("no model definition partition"))?;
+    reader.seek(SeekFrom::Start(model_def.offset))?;
+    let mut buffer = vec![];
+    reader.take(model_def.size).read_to_end(&mut buffer)?;
+    let model: Option<Model> = ModelDef::decode(&*buffer)?.into();
+    let mut model = model.ok_or_else(|| invalid_data("no graph in the model"))?;
+
+    let info = &mut model.info;
+    info.format = String::from("om");
+    info.metadata.insert("om_version".into(), String::from_i64(header.version as i64));
+    if !header.platform_version.is_empty() {
+        info.metadata.insert("platform_version".into(), header.platform_version);
+    }
+    // a type may be split over several partitions, e.g. the weights of each subgraph
+    let mut sizes = BTreeMap::<_, u64>::new();
+    for partition in header.partitions.iter().filter(|p| p.kind != MODEL_DEF) {
+        *sizes.entry(partition.kind).or_default() += partition.size;
+    }
+    for (kind, size) in sizes {
+        let key = match PARTITION_NAMES.get(kind as usize) {
+            Some(name) => format!("{}_size", name),
+            None => format!("partition_{}_size", kind),
+        };
+        info.metadata.insert(key.into(), String::from_i64(size as i64));
+    }
+
+    Ok(model)
+}
+
+/// Whether the file starts with the magic number of an offline model.
+pub fn is_om_file(path: &str) -> bool {
+    let mut magic = [0u8; 4];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut magic)) {
+        Ok(_) => u32::from_le_bytes(magic) == OM_MAGIC,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::geir::geir::{GraphDef, OpDef};
+
+    fn om_file(partitions: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut file = vec![0u8; HEADER_LEN as usize];
+        file[..4].copy_from_slice(&OM_MAGIC.to_le_bytes());
+        file[4..8].copy_from_slice(&(HEADER_LEN as u32).to_le_bytes());
+        file[8..12].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        file[160..165].copy_from_slice(b"1.0.0");
+
+        file.extend_from_slice(&(partitions.len() as u32).to_le_bytes());
+        let mut offset = 0;
+        for (kind, size, _) in partitions {
+            for field in [*kind, offset, *size] {
+                file.extend_from_slice(&field.to_le_bytes());
+            }
+            offset += size;
+        }
+        for (_, _, data) in partitions {
+            file.extend_from_slice(data);
+        }
+        file
+    }
+
+    #[test]
+    fn test_parse_om_model() {
+        let op = |name: &str, input: &[&str]| OpDef {
+            name: name.to_string(),
+            r#type: "Relu".to_string(),
+            input_name: input.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let graph = GraphDef {
+            name: "net".to_string(),
+            op: vec![op("a", &[]), op("b", &["a"])],
+            ..Default::default()
+        };
+        let model_def = ModelDef { graph: vec![graph], ..Default::default() }.encode_to_vec();
+
+        // the weights claim more bytes than the file has, they must not be read
+        let file = om_file(&[
+            (MODEL_DEF, model_def.len() as u32, &model_def),
+            (1, 1 << 30, &[]),
+            (2, 64, &[]),
+            (1, 16, &[]),
+        ]);
+        let path = std::env::temp_dir().join(format!("parse_om_model_{}.om", std::process::id()));
+        std::fs::write(&path, file).unwrap();
+        let path_str = path.to_str().unwrap();
+        assert!(is_om_file(path_str));
+        let model = parse_om_model(path_str).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!("net", model.name);
+        assert_eq!(2, model.nodes.len());
+        assert_eq!("om", model.info.format);
+        assert_eq!("1.0.0", model.info.metadata["platform_version"]);
+        assert_eq!("1073741840", model.info.metadata["weights_data_size"]);
+        assert_eq!("64", model.info.metadata["task_info_size"]);
+        assert!(!model.info.metadata.contains_key("model_def_size"));
+    }
+
+    #[test]
+    fn test_not_om_file() {
+        let path = std::env::temp_dir().join(format!("not_om_{}.om", std::process::id()));
+        std::fs::write(&path, om_file(&[])[4..].to_vec()).unwrap();
+        let path_str = path.to_str().unwrap();
+        assert!(!is_om_file(path_str));
+        assert!(parse_om_model(path_str).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
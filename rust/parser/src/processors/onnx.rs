@@ -2,21 +2,40 @@ mod onnx {
     include!(concat!(env!("OUT_DIR"), "/onnx.rs"));
 }
 
-use ahash::{HashMap, HashMapExt};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use onnx::{
-    AttributeProto, GraphProto, ModelProto, SparseTensorProto, TensorProto,
+    AttributeProto, GraphProto, ModelProto, NodeProto, SparseTensorProto, TensorProto,
+    ValueInfoProto,
     attribute_proto::AttributeType::*,
+    tensor_proto::{DataLocation, DataType},
+    tensor_shape_proto::dimension::Value::{DimParam, DimValue},
+    type_proto,
 };
 use smartstring::alias::String;
 
-use super::{TensorFormatter, format_tensors, parse_pb};
-use crate::{AttrValue, AttrValue::*, Model, Node, SmartStringExt};
+use super::{TensorFormatter, format_tensors, invalid_data, parse_pb, widen};
+use crate::{
+    AttrValue, Dim, Model, ModelInfo, Node, OpsetId, SmartStringExt, TensorPreview, TensorRef,
+    TensorShape, TensorType,
+    preview::{Element, PreviewBuilder},
+};
 
-pub fn parse_onnx_model(path: &str) -> Option<Model> {
-    match parse_pb::<ModelProto>(path) {
-        Ok(model) => model.into(),
-        Err(_) => None,
-    }
+/// ## Note
+/// Only the graph is kept: initializers become [`Model::parameters`] entries holding their type,
+/// their payloads are never copied (see [`super::read_bytes`]).
+/// Weights stored next to the model (`data_location = EXTERNAL`) are referenced by location,
+/// the files are listed in the `external_data` metadata but not opened.
+pub fn parse_onnx_model(path: &str) -> io::Result<Model> {
+    let model: Option<Model> = parse_pb::<ModelProto>(path)?.into();
+    model.ok_or_else(|| invalid_data("no graph in the model"))
 }
 
 impl From<ModelProto> for Option<Model> {
@@ -24,15 +43,36 @@ impl From<ModelProto> for Option<Model> {
         if let Some(graph) = value.graph {
             let name = String::from(&graph.name);
             let mut nodes = HashMap::new();
-            let parameters = HashMap::new();
+            let mut parameters = HashMap::new();
+            let mut external_files = BTreeSet::new();
+            for tensor in &graph.initializer {
+                let mut value = tensor.fmt();
+                if let Some(external) = ExternalData::from_tensor(tensor) {
+                    value.push_str(&format!(" @ {}", external));
+                    external_files.insert(external.location);
+                }
+                parameters.insert(String::from(&tensor.name), value);
+            }
+            let value_info = value_info(&graph);
             let ops = &graph.node;
 
-            let mut node_ids = Vec::new();
+            let node_ids = unique_node_ids(ops);
             let mut edges = Vec::new();
 
+            // tensor name -> indices of the nodes consuming it, each node listed once
+            let mut consumers: HashMap<&str, Vec<usize>> = HashMap::new();
+            for (idx, op) in ops.iter().enumerate() {
+                for input in &op.input {
+                    let list = consumers.entry(input.as_str()).or_default();
+                    if list.last() != Some(&idx) {
+                        list.push(idx);
+                    }
+                }
+            }
+
             for (idx, op) in ops.iter().enumerate() {
                 let op_type = String::from(&op.op_type);
-                let node_id: String = format!("{}_{}", op_type, idx).into();
+                let node_id = node_ids[idx].clone();
                 let input = String::from_slice(&op.input);
                 let output = String::from_slice(&op.output);
 
@@ -67,28 +107,329 @@ impl From<ModelProto> for Option<Model> {
                     output,
                     attributes,
                     dynamic: dyn_shape,
+                    index: idx,
+                    domain: String::from(&op.domain),
+                    doc_string: String::from(&op.doc_string),
+                    output_shapes: vec![],
                 };
 
                 nodes.insert(node_id.clone(), node);
-                node_ids.push(node_id.clone());
 
                 for out_tensor in &op.output {
-                    for (next_idx, next_op) in ops.iter().enumerate() {
-                        if next_op.input.contains(out_tensor) && idx != next_idx {
-                            let target_node_id = format!("{}_{}", next_op.op_type, next_idx).into();
-                            edges.push((node_id.clone(), target_node_id));
+                    for &next_idx in consumers.get(out_tensor.as_str()).into_iter().flatten() {
+                        if idx != next_idx {
+                            edges.push((node_id.clone(), node_ids[next_idx].clone()));
                         }
                     }
                 }
             }
 
-            return Some(Model { name, nodes, edges, parameters });
+            let mut info = ModelInfo {
+                format: String::from("onnx"),
+                ir_version: String::from_i64(value.ir_version),
+                producer_name: String::from(&value.producer_name),
+                producer_version: String::from(&value.producer_version),
+                domain: String::from(&value.domain),
+                model_version: String::from_i64(value.model_version),
+                doc_string: String::from(&value.doc_string),
+                opset_import: value
+                    .opset_import
+                    .iter()
+                    .map(|o| OpsetId { domain: String::from(&o.domain), version: o.version })
+                    .collect(),
+                metadata: value
+                    .metadata_props
+                    .iter()
+                    .map(|p| (String::from(&p.key), String::from(&p.value)))
+                    .collect(),
+                ..Default::default()
+            };
+            if !external_files.is_empty() {
+                let files: Vec<&str> = external_files.iter().map(|f| f.as_str()).collect();
+                info.metadata.insert("external_data".into(), files.join(", ").into());
+            }
+
+            return Some(Model {
+                name,
+                nodes,
+                edges,
+                typed_edges: vec![],
+                parameters,
+                value_info,
+                info,
+            });
         }
 
         None
     }
 }
 
+/// Shapes of the graph inputs and outputs, `value_info`, initializers and `Constant` outputs,
+/// the starting point of [`crate::infer_shapes`].
+fn value_info(graph: &GraphProto) -> HashMap<String, TensorShape> {
+    let mut value_info = HashMap::new();
+    for info in graph.input.iter().chain(&graph.value_info).chain(&graph.output) {
+        if let Some(shape) = value_info_shape(info) {
+            value_info.insert(String::from(&info.name), shape);
+        }
+    }
+    for tensor in &graph.initializer {
+        value_info.insert(String::from(&tensor.name), tensor_shape(tensor));
+    }
+    for op in graph.node.iter().filter(|op| op.op_type == "Constant" && op.domain.is_empty()) {
+        if let (Some(output), Some(shape)) = (op.output.first(), constant_shape(op)) {
+            value_info.insert(String::from(output), shape);
+        }
+    }
+    value_info
+}
+
+/// ## Note
+/// A dim with neither a value nor a name is unknown, a type without a shape has an unknown rank
+/// and gives nothing.
+fn value_info_shape(info: &ValueInfoProto) -> Option<TensorShape> {
+    let Some(type_proto::Value::TensorType(tensor)) = &info.r#type.as_ref()?.value else {
+        return None;
+    };
+    let dims = tensor.shape.as_ref()?.dim.iter().map(|dim| match &dim.value {
+        Some(DimValue(value)) => Dim::Static(*value),
+        Some(DimParam(param)) if !param.is_empty() => Dim::Symbolic(String::from(param)),
+        _ => Dim::Unknown,
+    });
+    Some(TensorShape::new(dtype_name(tensor.elem_type), dims.collect()))
+}
+
+/// The name of an ONNX `DataType`, as [`TensorType::dtype`] spells it.
+pub(crate) fn dtype_name(elem_type: i32) -> String {
+    format!("{:?}", DataType::try_from(elem_type).unwrap_or_default()).into()
+}
+
+/// Integer tensors up to this length keep their contents, they are shapes, axes or sizes.
+const MAX_VALUE_LEN: usize = 64;
+
+fn tensor_shape(tensor: &TensorProto) -> TensorShape {
+    let dims = tensor.dims.iter().map(|&d| Dim::Static(d)).collect();
+    TensorShape {
+        value: int_values(tensor),
+        ..TensorShape::new(dtype_name(tensor.data_type), dims)
+    }
+}
+
+fn int_values(tensor: &TensorProto) -> Option<Vec<i64>> {
+    let len = tensor.dims.iter().product::<i64>();
+    if len as usize > MAX_VALUE_LEN || tensor.data_location() == DataLocation::External {
+        return None;
+    }
+
+    let raw = &tensor.raw_data;
+    let values: Vec<i64> = match tensor.data_type() {
+        DataType::Int64 if raw.is_empty() => tensor.int64_data.clone(),
+        DataType::Int64 => {
+            raw.chunks_exact(8).map(|c| i64::from_le_bytes(c.try_into().unwrap())).collect()
+        }
+        DataType::Int32 if raw.is_empty() => tensor.int32_data.iter().map(|&v| v as i64).collect(),
+        DataType::Int32 => {
+            raw.chunks_exact(4).map(|c| i32::from_le_bytes(c.try_into().unwrap()) as i64).collect()
+        }
+        _ => return None,
+    };
+    (values.len() as i64 == len).then_some(values)
+}
+
+fn constant_shape(op: &NodeProto) -> Option<TensorShape> {
+    // a `Constant` has exactly one attribute, the value
+    let attr = op.attribute.first()?;
+    let ints = |dims, value| TensorShape { value: Some(value), ..TensorShape::new("Int64", dims) };
+    match attr.name.as_str() {
+        "value" => Some(tensor_shape(attr.t.as_ref()?)),
+        "value_int" => Some(ints(vec![], vec![attr.i])),
+        "value_ints" => Some(ints(vec![Dim::Static(attr.ints.len() as i64)], attr.ints.clone())),
+        "value_float" => Some(TensorShape::new("Float", vec![])),
+        "value_floats" => {
+            Some(TensorShape::new("Float", vec![Dim::Static(attr.floats.len() as i64)]))
+        }
+        _ => None,
+    }
+}
+
+/// ## Explanation
+/// Previews an initializer, or a tensor attribute of a node of the main graph, see [`TensorPreview`].
+/// Returns [`None`] if the tensor isn't found or its dtype isn't supported (complex, float8).
+///
+/// ## Note
+/// The payload is decoded from `raw_data`, the typed field matching the dtype,
+/// or the external file, streamed a chunk at a time.
+pub fn preview_onnx_tensor(path: &str, tensor: &TensorRef, limit: usize) -> Option<TensorPreview> {
+    let graph = parse_pb::<ModelProto>(path).ok()?.graph?;
+    let proto = match tensor {
+        TensorRef::Initializer { name } => graph.initializer.iter().find(|t| t.name == *name)?,
+        TensorRef::Attribute { node, attribute } => {
+            let idx = unique_node_ids(&graph.node).iter().position(|id| id == node)?;
+            let attr = graph.node[idx].attribute.iter().find(|a| a.name == *attribute)?;
+            attr.t.as_ref()?
+        }
+    };
+    preview_tensor(proto, path, limit)
+}
+
+fn preview_tensor(tensor: &TensorProto, path: &str, limit: usize) -> Option<TensorPreview> {
+    let dtype = tensor.data_type();
+    let name = String::from(format!("{:?}", dtype));
+    let element = match dtype {
+        DataType::String => {
+            return Some(TensorPreview::strings(
+                name,
+                tensor.dims.clone(),
+                &tensor.string_data,
+                limit,
+            ));
+        }
+        DataType::Bool => Element::Bool,
+        DataType::Int8 => Element::I8,
+        DataType::Uint8 => Element::U8,
+        DataType::Int16 => Element::I16,
+        DataType::Uint16 => Element::U16,
+        DataType::Int32 => Element::I32,
+        DataType::Uint32 => Element::U32,
+        DataType::Int64 => Element::I64,
+        DataType::Uint64 => Element::U64,
+        DataType::Float16 => Element::F16,
+        DataType::Bfloat16 => Element::Bf16,
+        DataType::Float => Element::F32,
+        DataType::Double => Element::F64,
+        _ => return None,
+    };
+
+    let mut builder = PreviewBuilder::new(limit);
+    if let Some(external) = ExternalData::from_tensor(tensor) {
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        external.decode(&dir.join(external.location.as_str()), element, &mut builder).ok()?;
+    } else if !tensor.raw_data.is_empty() {
+        element.decode(&tensor.raw_data, &mut builder);
+    } else {
+        // the typed fields, 16-bit floats are stored as their bits in `int32_data`
+        match element {
+            Element::F32 => tensor.float_data.iter().for_each(|&v| builder.push(v as f64)),
+            Element::F64 => tensor.double_data.iter().for_each(|&v| builder.push(v)),
+            Element::I64 => tensor.int64_data.iter().for_each(|&v| builder.push(v as f64)),
+            Element::U32 | Element::U64 => {
+                tensor.uint64_data.iter().for_each(|&v| builder.push(v as f64))
+            }
+            Element::F16 | Element::Bf16 => tensor
+                .int32_data
+                .iter()
+                .for_each(|&v| builder.push(element.read(&(v as u16).to_le_bytes()))),
+            _ => tensor.int32_data.iter().for_each(|&v| builder.push(v as f64)),
+        }
+    }
+
+    Some(builder.finish(name, tensor.dims.clone()))
+}
+
+/// Where the payload of a tensor stored outside the model is, from its `external_data` entries.
+///
+/// ## Note
+/// `location` is relative to the directory of the model. Without a `length`,
+/// the payload runs to the end of the file.
+pub(crate) struct ExternalData {
+    pub location: String,
+    pub offset: u64,
+    pub length: Option<u64>,
+}
+
+impl ExternalData {
+    fn from_tensor(tensor: &TensorProto) -> Option<Self> {
+        if tensor.data_location() != DataLocation::External {
+            return None;
+        }
+
+        let mut external = ExternalData { location: String::new(), offset: 0, length: None };
+        for entry in &tensor.external_data {
+            match entry.key.as_str() {
+                "location" => external.location = String::from(&entry.value),
+                "offset" => external.offset = entry.value.parse().ok()?,
+                "length" => external.length = Some(entry.value.parse().ok()?),
+                _ => {}
+            }
+        }
+        (!external.location.is_empty()).then_some(external)
+    }
+
+    fn decode(
+        &self,
+        path: &Path,
+        element: Element,
+        builder: &mut PreviewBuilder,
+    ) -> io::Result<()> {
+        // a multiple of every element width
+        const CHUNK: u64 = 1 << 16;
+
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file).take(self.length.unwrap_or(u64::MAX));
+        let mut chunk = Vec::with_capacity(CHUNK as usize);
+        loop {
+            chunk.clear();
+            (&mut reader).take(CHUNK).read_to_end(&mut chunk)?;
+            if chunk.is_empty() {
+                return Ok(());
+            }
+            element.decode(&chunk, builder);
+        }
+    }
+}
+
+impl fmt::Display for ExternalData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.length {
+            Some(length) => {
+                write!(f, "{}[{}..{}]", self.location, self.offset, self.offset + length)
+            }
+            None => write!(f, "{}[{}..]", self.location, self.offset),
+        }
+    }
+}
+
+/// Node ids follow `NodeProto.name`, so they match what ONNX Runtime, profilers
+/// and Netron show.
+///
+/// ## Note
+/// `name` is optional and not guaranteed to be unique:
+/// - an empty name falls back to `{op_type}_{index}`
+/// - a name shared by several nodes becomes `{name}_{index}` for every one of them
+///
+/// If a generated id still collides with another node's name, `_` is appended until
+/// it's unique. The result only depends on the graph, so ids are stable across runs.
+fn unique_node_ids(ops: &[NodeProto]) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for op in ops {
+        *counts.entry(op.name.as_str()).or_default() += 1;
+    }
+
+    let mut taken: HashSet<String> = HashSet::new();
+    for op in ops {
+        if !op.name.is_empty() && counts[op.name.as_str()] == 1 {
+            taken.insert(String::from(&op.name));
+        }
+    }
+
+    ops.iter()
+        .enumerate()
+        .map(|(idx, op)| {
+            if !op.name.is_empty() && counts[op.name.as_str()] == 1 {
+                return String::from(&op.name);
+            }
+            let base = if op.name.is_empty() { &op.op_type } else { &op.name };
+            let mut id: String = format!("{}_{}", base, idx).into();
+            while taken.contains(&id) {
+                id.push('_');
+            }
+            taken.insert(id.clone());
+            id
+        })
+        .collect()
+}
+
 /// ## Note
 /// One possible optimization isn't to use libraries such as [`prost`] and
 /// [`protobuf`] to generate type definitions and parsing files.
@@ -119,28 +460,26 @@ fn parse_attr(attr: &AttributeProto) -> Option<AttrValue> {
     let discriminator = attr.r#type();
 
     let value = match discriminator {
-        Undefined => StringLike(String::from("")),
-        Float => StringLike(String::from_f32(attr.f)),
-        Int => StringLike(String::from_i64(attr.i)),
-        String => StringLike(String::from_vecu8(&attr.s)),
+        Undefined => AttrValue::Str(String::new()),
+        Float => AttrValue::Float(widen(attr.f)),
+        Int => AttrValue::Int(attr.i),
+        String => AttrValue::Str(String::from_vecu8(&attr.s)),
         /// ## Safety
         /// These fields are marked as [`optional`], otherwise, during parsing
         /// phase, they were filled with [`Default::default`] even if they
         /// actually have no value, causing unnecessary memory allocation.
         /// When the [`discriminator`] corresponds, the [`Option`] is guaranteed
-        /// to have a value, so the [`unwrap`] here is safe
-        ///
-        /// ## Panics
-        /// If the file is corrupted,
-        /// [`unwrap`] here panics, causing the program to exit.
-        Tensor => StringLike(TensorFormatter::fmt(attr.t.as_ref()?)),
-        Graph => StringLike(format_graph(attr.g.as_ref()?)),
-        SparseTensor => StringLike(String::from_i64s(&attr.sparse_tensor.as_ref()?.dims)),
-        Floats => StringLike(String::from_f32s(&attr.floats)),
-        Ints => StringLike(String::from_i64s(&attr.ints)),
-        Strings => StringLike(String::from_2dvecu8(&attr.strings)),
+        /// to have a value, a corrupted file yields [`None`] instead.
+        Tensor => AttrValue::Tensor(attr.t.as_ref()?.tensor_type()),
+        Graph => AttrValue::Graph(attr.g.as_ref()?.name.as_str().into()),
+        SparseTensor => AttrValue::Tensor(attr.sparse_tensor.as_ref()?.tensor_type()),
+        Floats => AttrValue::Floats(attr.floats.iter().map(|&f| widen(f)).collect()),
+        Ints => AttrValue::Ints(attr.ints.clone()),
+        Strings => AttrValue::Strs(attr.strings.iter().map(|s| String::from_vecu8(s)).collect()),
         Tensors => format_tensors(&attr.tensors),
-        Graphs => StringLike(format_graphs(&attr.graphs)),
+        Graphs => AttrValue::List(
+            attr.graphs.iter().map(|g| AttrValue::Graph(g.name.as_str().into())).collect(),
+        ),
         SparseTensors => format_tensors(&attr.sparse_tensors),
     };
 
@@ -152,37 +491,15 @@ fn is_dyn_shape(attr: &AttributeProto) -> bool {
 }
 
 impl TensorFormatter for TensorProto {
-    fn fmt(&self) -> String {
-        let dtype = self.dtype();
-        let dims = String::from_i64s(&self.dims);
-
-        format!("{:?}({})", dtype, dims).into()
-    }
-}
-
-impl TensorFormatter for SparseTensorProto {
-    fn fmt(&self) -> String {
-        String::from_i64s(&self.dims)
+    fn tensor_type(&self) -> TensorType {
+        TensorType::new(format!("{:?}", self.data_type()), &self.dims)
     }
 }
 
 /// ## Note
-/// Temporarily displayed as a string
-fn format_graph(graph: &GraphProto) -> String {
-    format!("Graph({})", graph.name).into()
-}
-
-fn format_graphs(graphs: &[GraphProto]) -> String {
-    let mut s = String::from("Graphs[");
-    let last_idx = graphs.len() - 1;
-    for (i, g) in graphs.iter().enumerate() {
-        s.push_str(&g.name);
-        if i < last_idx {
-            s.push(';');
-        }
+/// The values of a sparse tensor carry its dtype, the tensor itself only has dims.
+impl TensorFormatter for SparseTensorProto {
+    fn tensor_type(&self) -> TensorType {
+        TensorType { dims: Some(self.dims.clone()), ..Default::default() }
     }
-
-    s.push(']');
-
-    s
 }
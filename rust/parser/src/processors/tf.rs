@@ -0,0 +1,395 @@
+/// Also read by the `events` crate, which decodes the summaries of events files.
+pub mod tensorboard {
+    include!(concat!(env!("OUT_DIR"), "/tensorboard.rs"));
+}
+
+use std::{fs, io, path::Path};
+
+use ahash::{HashMap, HashMapExt};
+use prost::Message;
+use smartstring::alias::String;
+use tensorboard::{
+    AttrValue as TfAttrValue, DataType, Event, GraphDef, NameAttrList, TensorProto,
+    TensorShapeProto, attr_value::ListValue, event::What,
+};
+
+use super::{TensorFormatter, format_tensors, invalid_data, is_text_format, parse_pb, widen};
+use crate::{
+    AttrValue, AttrValue::*, Edge, EdgeKind, Model, ModelInfo, Node, SmartStringExt, TensorType,
+    tfrecord,
+};
+
+/// ## Explanation
+/// Reads a TensorFlow `GraphDef` from either:
+/// - a binary `GraphDef` (`.pb`), e.g. written by `tf.io.write_graph(..., as_text=False)`
+///   or a frozen graph, which is a bare `GraphDef` with its variables turned into constants
+/// - a text `GraphDef` (`.pbtxt`), written by `tf.io.write_graph(..., as_text=True)`
+/// - an events file (`events.out.tfevents.*`), where TensorBoard keeps the serialized
+///   `GraphDef` in the `graph_def` field of an [`Event`]
+///
+/// As for `anf_ir`, a file whose first record passes both CRC checks is read as an events file.
+///
+/// ## Note
+/// A `saved_model.pb` or a checkpoint's `.meta` wraps the `GraphDef` in a
+/// `SavedModel`/`MetaGraphDef` and is not supported.
+pub fn parse_tf_model(path: &str) -> io::Result<Model> {
+    let mut model = if is_text_format(path) {
+        let graph = parse_pb::<GraphDef>(path)?;
+        process_graph(&graph, tf_info(&graph))
+    } else {
+        let buffer = fs::read(path)?;
+        parse_tf_bytes(&buffer).ok_or_else(|| invalid_data("no graph in the file"))?
+    };
+    model.name = model_name(path);
+    Ok(model)
+}
+
+/// A `GraphDef` has no name, the model is named after its file, `mnist` for `mnist.pb`
+/// or `mnist.pbtxt`, and an events file after its run directory, as in TensorBoard.
+fn model_name(path: &str) -> String {
+    let path = Path::new(path);
+    let file = path.file_name().and_then(|file| file.to_str()).unwrap_or_default();
+    let run = path.parent().and_then(|dir| dir.file_name()).and_then(|dir| dir.to_str());
+    match run {
+        Some(run) if file.contains("tfevents") => String::from(run),
+        _ => String::from(file.split_once('.').map_or(file, |(stem, _)| stem)),
+    }
+}
+
+fn parse_tf_bytes(buffer: &[u8]) -> Option<Model> {
+    match tfrecord::records(buffer).next() {
+        Some(Ok(_)) => parse_events(buffer),
+        _ => {
+            let graph = GraphDef::decode(buffer).ok()?;
+            Some(process_graph(&graph, tf_info(&graph)))
+        }
+    }
+}
+
+/// ## Note
+/// The first graph in the file is used, records after a truncated tail are ignored.
+fn parse_events(buffer: &[u8]) -> Option<Model> {
+    let mut version = String::new();
+    for record in tfrecord::records(buffer) {
+        let Ok(event) = Event::decode(record.ok()?) else {
+            continue;
+        };
+        match event.what {
+            Some(What::FileVersion(v)) => version = v.into(),
+            Some(What::GraphDef(bytes)) => {
+                let Ok(graph) = GraphDef::decode(&*bytes) else {
+                    continue;
+                };
+                let mut info = tf_info(&graph);
+                if !version.is_empty() {
+                    info.metadata.insert("event_version".into(), version);
+                }
+                info.metadata.insert("step".into(), String::from_i64(event.step));
+                return Some(process_graph(&graph, info));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn tf_info(graph: &GraphDef) -> ModelInfo {
+    let mut info = ModelInfo { format: String::from("tensorflow"), ..Default::default() };
+    if let Some(versions) = &graph.versions {
+        info.producer_version = String::from_i32(versions.producer);
+        info.metadata.insert("min_consumer".into(), String::from_i32(versions.min_consumer));
+    }
+    info
+}
+
+/// ## Explanation
+/// Each input of a `NodeDef` is one of:
+/// - `node` or `node:0`, the first output of `node`
+/// - `node:N`, the `N`-th output of `node`
+/// - `^node`, a control dependency
+///
+/// [`Node::input`] keeps the bare names of the nodes feeding data, each once, so that
+/// `add(split, split:1)` has one input `split` and the layout can use them as they are.
+/// Control dependencies are left out of it, as they carry no tensor,
+/// the port and kind of every input are kept in [`Model::typed_edges`].
+fn process_graph(graph: &GraphDef, info: ModelInfo) -> Model {
+    let mut nodes = HashMap::with_capacity(graph.node.len());
+    let mut typed_edges = vec![];
+    for (index, node) in graph.node.iter().enumerate() {
+        let name = String::from(&node.name);
+        let mut input = Vec::with_capacity(node.input.len());
+        for raw in &node.input {
+            let (source, output, kind) = parse_input(raw);
+            let source = String::from(source);
+            if kind == EdgeKind::Data && !input.contains(&source) {
+                input.push(source.clone());
+            }
+            typed_edges.push(Edge { source, target: name.clone(), output, kind, shape: None });
+        }
+        let attributes = node.attr.iter().map(|(k, v)| (String::from(k), parse_attr(v))).collect();
+
+        nodes.insert(name.clone(), Node {
+            name,
+            opType: String::from(&node.op),
+            input,
+            output: vec![],
+            attributes,
+            dynamic: false,
+            index,
+            domain: String::new(),
+            doc_string: String::new(),
+            output_shapes: vec![],
+        });
+    }
+
+    Model {
+        name: String::new(),
+        nodes,
+        edges: vec![],
+        typed_edges,
+        parameters: HashMap::new(),
+        value_info: HashMap::new(),
+        info,
+    }
+}
+
+/// A suffix that isn't a number is part of the name, e.g. `while/Enter:output:0`
+/// in a function body refers to an output argument rather than an index.
+fn parse_input(raw: &str) -> (&str, usize, EdgeKind) {
+    if let Some(source) = raw.strip_prefix('^') {
+        return (source, 0, EdgeKind::Control);
+    }
+    match raw.rsplit_once(':') {
+        Some((source, port)) => match port.parse() {
+            Ok(port) => (source, port, EdgeKind::Data),
+            _ => (raw, 0, EdgeKind::Data),
+        },
+        _ => (raw, 0, EdgeKind::Data),
+    }
+}
+
+/// ## Explanation
+/// Unlike the other formats, an `AttrValue` doesn't record its type, which is
+/// implied by which field of the `oneof` is set. A list records it neither,
+/// the first non-empty field of [`ListValue`] is taken, an empty list becomes an empty [`List`].
+fn parse_attr(attr: &TfAttrValue) -> AttrValue {
+    use tensorboard::attr_value::Value::*;
+
+    match &attr.value {
+        Some(S(bytes)) => Str(String::from_vecu8(bytes)),
+        Some(I(i)) => Int(*i),
+        Some(F(f)) => Float(widen(*f)),
+        Some(B(b)) => Bool(*b),
+        Some(Type(dt)) => Str(format_dtype(*dt)),
+        Some(Shape(shape)) => format_shape(shape),
+        Some(Tensor(t)) => AttrValue::Tensor(t.tensor_type()),
+        Some(List(list)) => parse_list(list),
+        Some(Func(func)) => Map([format_func(func)].into_iter().collect()),
+        Some(Placeholder(name)) => Str(format!("${}", name).into()),
+        None => AttrValue::List(vec![]),
+    }
+}
+
+fn parse_list(list: &ListValue) -> AttrValue {
+    if !list.s.is_empty() {
+        Strs(String::vecu8s2strings(&list.s))
+    } else if !list.i.is_empty() {
+        Ints(list.i.clone())
+    } else if !list.f.is_empty() {
+        Floats(list.f.iter().map(|&f| widen(f)).collect())
+    } else if !list.b.is_empty() {
+        Bools(list.b.clone())
+    } else if !list.r#type.is_empty() {
+        Strs(list.r#type.iter().map(|&dt| format_dtype(dt)).collect())
+    } else if !list.shape.is_empty() {
+        AttrValue::List(list.shape.iter().map(format_shape).collect())
+    } else if !list.tensor.is_empty() {
+        format_tensors(&list.tensor)
+    } else if !list.func.is_empty() {
+        Map(list.func.iter().map(format_func).collect())
+    } else {
+        AttrValue::List(vec![])
+    }
+}
+
+/// `DT_FLOAT` as TensorFlow prints it, an unknown value keeps its number.
+#[inline]
+fn format_dtype(dt: i32) -> String {
+    match DataType::try_from(dt) {
+        Ok(dt) => String::from(dt.as_str_name()),
+        _ => String::from_i32(dt),
+    }
+}
+
+/// A dim of `-1` is unknown, a shape of unknown rank has no dims at all.
+#[inline]
+fn format_shape(shape: &TensorShapeProto) -> AttrValue {
+    match shape.unknown_rank {
+        true => Str(String::from("<unknown>")),
+        _ => Ints(shape.dim.iter().map(|d| d.size).collect()),
+    }
+}
+
+fn format_func(func: &NameAttrList) -> (String, AttrValue) {
+    let attrs = func.attr.iter().map(|(k, v)| (String::from(k), parse_attr(v))).collect();
+    (String::from(&func.name), Map(attrs))
+}
+
+impl TensorFormatter for TensorProto {
+    fn tensor_type(&self) -> TensorType {
+        let dims = self.tensor_shape.as_ref().filter(|s| !s.unknown_rank);
+        TensorType {
+            dtype: format_dtype(self.dtype),
+            dims: dims.map(|s| s.dim.iter().map(|d| d.size).collect()),
+            layout: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tensorboard::{NodeDef, VersionDef, attr_value::Value, tensor_shape_proto::Dim};
+
+    use super::*;
+
+    fn node(name: &str, op: &str, input: &[&str]) -> NodeDef {
+        NodeDef {
+            name: name.into(),
+            op: op.into(),
+            input: input.iter().map(|i| i.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn attr(value: Value) -> TfAttrValue {
+        TfAttrValue { value: Some(value) }
+    }
+
+    fn graph() -> GraphDef {
+        let mut split = node("split", "Split", &["axis", "x"]);
+        split.attr.insert("num_split".into(), attr(Value::I(2)));
+        let mut add = node("add", "AddV2", &["split", "split:1", "^init"]);
+        add.attr.insert("T".into(), attr(Value::Type(DataType::DtFloat as i32)));
+        GraphDef {
+            node: vec![
+                node("x", "Placeholder", &[]),
+                node("axis", "Const", &[]),
+                node("init", "NoOp", &[]),
+                split,
+                add,
+            ],
+            versions: Some(VersionDef { producer: 1645, min_consumer: 12, bad_consumers: vec![] }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_graph_def() {
+        let model = parse_tf_bytes(&graph().encode_to_vec()).unwrap();
+        assert_eq!(5, model.nodes.len());
+        assert_eq!("tensorflow", model.info.format);
+        assert_eq!("1645", model.info.producer_version);
+
+        let add = &model.nodes["add"];
+        assert_eq!("AddV2", add.opType);
+        assert_eq!(vec!["split"], add.input);
+        assert_eq!(Some(&Str("DT_FLOAT".into())), add.attributes.get("T"));
+        assert_eq!(Some(&Int(2)), model.nodes["split"].attributes.get("num_split"));
+
+        let edges: Vec<(&str, &str, usize, EdgeKind)> = model
+            .typed_edges
+            .iter()
+            .filter(|e| e.target == "add")
+            .map(|e| (e.source.as_str(), e.target.as_str(), e.output, e.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("split", "add", 0, EdgeKind::Data),
+                ("split", "add", 1, EdgeKind::Data),
+                ("init", "add", 0, EdgeKind::Control),
+            ],
+            edges
+        );
+        assert_eq!(5, model.typed_edges.len());
+    }
+
+    #[test]
+    fn test_detect_pb_format() {
+        assert_eq!(Some("tensorflow"), super::super::detect_pb_bytes(&graph().encode_to_vec()));
+    }
+
+    #[test]
+    fn test_text_graph() {
+        let text = "node { name: 'x' op: 'Placeholder' }\n\
+                    node { name: 'relu' op: 'Relu' input: 'x' }\n\
+                    versions { producer: 1645 }\n";
+        let path = std::env::temp_dir().join(format!("tf_graph_{}.pbtxt", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let model = parse_tf_model(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let model = model.unwrap();
+        assert_eq!(2, model.nodes.len());
+        assert_eq!(format!("tf_graph_{}", std::process::id()), model.name);
+        assert_eq!(vec!["x"], model.nodes["relu"].input);
+        assert_eq!("1645", model.info.producer_version);
+    }
+
+    #[test]
+    fn test_events_file() {
+        let event =
+            |what| Event { wall_time: 0.0, step: 0, what: Some(what), source_metadata: None };
+        let mut file = vec![];
+        tfrecord::write_record(
+            &mut file,
+            &event(What::FileVersion("brain.Event:2".into())).encode_to_vec(),
+        );
+        tfrecord::write_record(
+            &mut file,
+            &event(What::GraphDef(graph().encode_to_vec())).encode_to_vec(),
+        );
+
+        let model = parse_tf_bytes(&file).unwrap();
+        assert_eq!(5, model.nodes.len());
+        assert_eq!("brain.Event:2", model.info.metadata["event_version"]);
+
+        let mut no_graph = vec![];
+        tfrecord::write_record(
+            &mut no_graph,
+            &event(What::FileVersion("v".into())).encode_to_vec(),
+        );
+        assert!(parse_tf_bytes(&no_graph).is_none());
+    }
+
+    #[test]
+    fn test_model_name() {
+        assert_eq!("mnist", model_name("models/mnist.pb"));
+        assert_eq!("mnist", model_name("mnist.pbtxt"));
+        assert_eq!("train", model_name("logs/train/events.out.tfevents.1700000000.host"));
+        assert_eq!("events", model_name("events.out.tfevents.1700000000.host"));
+    }
+
+    #[test]
+    fn test_inputs_and_attrs() {
+        assert_eq!(("a", 0, EdgeKind::Data), parse_input("a"));
+        assert_eq!(("a/b", 3, EdgeKind::Data), parse_input("a/b:3"));
+        assert_eq!(("a", 0, EdgeKind::Control), parse_input("^a"));
+        assert_eq!(("f:output", 0, EdgeKind::Data), parse_input("f:output:0"));
+        assert_eq!(("f:out", 0, EdgeKind::Data), parse_input("f:out"));
+
+        let dims = [1, -1, 3].map(|size| Dim { size, name: String::new().into() });
+        let shape = TensorShapeProto { dim: dims.into(), unknown_rank: false };
+        assert_eq!("1, -1, 3", parse_attr(&attr(Value::Shape(shape))).to_string());
+        let tensor = TensorProto {
+            dtype: DataType::DtInt32 as i32,
+            tensor_shape: Some(TensorShapeProto { dim: vec![], unknown_rank: false }),
+            ..Default::default()
+        };
+        assert_eq!("DT_INT32()", parse_attr(&attr(Value::Tensor(tensor))).to_string());
+        let list = ListValue { i: vec![1, 2, 2, 1], ..Default::default() };
+        assert_eq!(Ints(vec![1, 2, 2, 1]), parse_attr(&attr(Value::List(list))));
+        assert_eq!(AttrValue::List(vec![]), parse_attr(&attr(Value::List(ListValue::default()))));
+        assert_eq!(Float(0.1), parse_attr(&attr(Value::F(0.1))));
+    }
+}
@@ -0,0 +1,877 @@
+use ahash::{HashMap, HashMapExt};
+use serde::Serialize;
+use smartstring::alias::String;
+
+use crate::{AttrValue, Dim, Edge, EdgeKind, Model, Node, TensorShape, dtype_name};
+
+/// The outcome of [`infer_shapes`].
+#[derive(Debug, Default, Serialize)]
+pub struct ShapeReport {
+    /// The number of nodes whose outputs all have a shape
+    pub resolved: usize,
+    pub unresolved: Vec<Unresolved>,
+}
+
+/// A node left without shapes, because of its op or of its inputs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unresolved {
+    pub node: String,
+    pub op_type: String,
+    pub reason: String,
+}
+
+type Shapes = Result<Vec<TensorShape>, String>;
+
+/// ## Explanation
+/// Propagates shapes through the graph, in the order of [`Node::index`],
+/// from the shapes known in [`Model::value_info`]: graph inputs, initializers and constants.
+/// Inferred shapes are added to `value_info`, set on [`Node::output_shapes`]
+/// and on the [`Model::typed_edges`] carrying them.
+///
+/// ## Note
+/// - A shape already known, e.g. from ONNX `value_info`, is kept over an inferred one.
+/// - Ops outside the supported set, and nodes with an input of unknown shape, are reported
+///   in [`ShapeReport::unresolved`], the nodes after them are still visited.
+/// - So are nodes that don't name their output tensors, as in GEIR, TensorFlow and ANF IR
+///   where edges reference the producing node, shapes can't be attached to them.
+/// - Dims that can't be computed statically, e.g. the output of a `Conv` over a symbolic
+///   height, are [`Dim::Unknown`], the rank is always known.
+/// - A model with only untyped edges gets `typed_edges`, one for each input tensor
+///   produced by another node, so that shapes can be recorded on them.
+pub fn infer_shapes(model: &mut Model) -> ShapeReport {
+    let mut order: Vec<(usize, String)> =
+        model.nodes.iter().map(|(id, node)| (node.index, id.clone())).collect();
+    order.sort();
+
+    let mut report = ShapeReport::default();
+    for (_, id) in order {
+        let node = &model.nodes[&id];
+        if node.output.is_empty() {
+            report.unresolved.push(Unresolved {
+                node: id.clone(),
+                op_type: node.opType.clone(),
+                reason: "outputs not named".into(),
+            });
+            continue;
+        }
+        let known = |name: &String| model.value_info.contains_key(name);
+        let result = if node.output.iter().all(known) {
+            Ok(vec![])
+        } else {
+            let inputs: Vec<_> = node.input.iter().map(|name| model.value_info.get(name)).collect();
+            infer_node(node, &inputs)
+        };
+
+        match result {
+            Ok(shapes) => {
+                for (name, shape) in node.output.iter().zip(shapes) {
+                    if !name.is_empty() && !model.value_info.contains_key(name) {
+                        model.value_info.insert(name.clone(), shape);
+                    }
+                }
+            }
+            Err(reason) => report.unresolved.push(Unresolved {
+                node: id.clone(),
+                op_type: node.opType.clone(),
+                reason,
+            }),
+        }
+
+        let node = model.nodes.get_mut(&id).unwrap();
+        node.output_shapes =
+            node.output.iter().map(|name| model.value_info.get(name).cloned()).collect();
+        if node.output_shapes.iter().all(Option::is_some) {
+            report.resolved += 1;
+        }
+    }
+
+    if model.typed_edges.is_empty() {
+        model.typed_edges = tensor_edges(model);
+    }
+    for edge in &mut model.typed_edges {
+        edge.shape = model
+            .nodes
+            .get(&edge.source)
+            .and_then(|node| node.output_shapes.get(edge.output).cloned().flatten());
+    }
+
+    report
+}
+
+/// The data edges of a graph whose nodes name their input and output tensors, as in ONNX.
+fn tensor_edges(model: &Model) -> Vec<Edge> {
+    let mut producers = HashMap::new();
+    for (id, node) in &model.nodes {
+        for (output, name) in node.output.iter().enumerate() {
+            if !name.is_empty() {
+                producers.insert(name, (id, output));
+            }
+        }
+    }
+
+    let mut consumers: Vec<&Node> = model.nodes.values().collect();
+    consumers.sort_by_key(|node| node.index);
+    let mut edges = vec![];
+    for node in consumers {
+        for name in &node.input {
+            match producers.get(name) {
+                Some(&(source, output)) if *source != node.name => edges.push(Edge {
+                    source: source.clone(),
+                    target: node.name.clone(),
+                    output,
+                    kind: EdgeKind::Data,
+                    shape: None,
+                }),
+                _ => {}
+            }
+        }
+    }
+    edges
+}
+
+const UNARY: &[&str] = &[
+    "Abs",
+    "Acos",
+    "Asin",
+    "Atan",
+    "Ceil",
+    "Celu",
+    "Clip",
+    "Cos",
+    "Cosh",
+    "Elu",
+    "Erf",
+    "Exp",
+    "Floor",
+    "Gelu",
+    "HardSigmoid",
+    "HardSwish",
+    "Hardmax",
+    "LeakyRelu",
+    "Log",
+    "LogSoftmax",
+    "Mish",
+    "Neg",
+    "Not",
+    "Reciprocal",
+    "Relu",
+    "Round",
+    "Selu",
+    "Sigmoid",
+    "Sign",
+    "Sin",
+    "Sinh",
+    "Softmax",
+    "Softplus",
+    "Softsign",
+    "Sqrt",
+    "Tan",
+    "Tanh",
+    "ThresholdedRelu",
+    // normalizations, their optional statistics outputs are left unknown
+    "BatchNormalization",
+    "GroupNormalization",
+    "InstanceNormalization",
+    "LayerNormalization",
+    "LpNormalization",
+    "LRN",
+    "MeanVarianceNormalization",
+];
+
+const BROADCAST: &[&str] =
+    &["Add", "BitShift", "Div", "Max", "Mean", "Min", "Mod", "Mul", "Pow", "PRelu", "Sub", "Sum"];
+
+const LOGICAL: &[&str] =
+    &["And", "Equal", "Greater", "GreaterOrEqual", "Less", "LessOrEqual", "Or", "Xor"];
+
+fn infer_node(node: &Node, inputs: &[Option<&TensorShape>]) -> Shapes {
+    let input = |i: usize| -> Result<&TensorShape, String> {
+        inputs
+            .get(i)
+            .copied()
+            .flatten()
+            .ok_or_else(|| format!("unknown shape of input {}", i).into())
+    };
+    let op = node.opType.as_str();
+    if !node.domain.is_empty() && node.domain != "ai.onnx" {
+        return Err(format!("unsupported domain {}", node.domain).into());
+    }
+
+    match op {
+        _ if UNARY.contains(&op) => Ok(vec![same_shape(input(0)?)]),
+        "Identity" => Ok(vec![input(0)?.clone()]),
+        "IsNaN" | "IsInf" => Ok(vec![TensorShape::new("Bool", input(0)?.dims.clone())]),
+        "Dropout" => {
+            let x = input(0)?;
+            Ok(vec![same_shape(x), TensorShape::new("Bool", x.dims.clone())])
+        }
+        "Cast" => {
+            let to = dtype_name(int_attr(node, "to").ok_or("missing attribute to")? as i32);
+            let x = input(0)?;
+            let value = if to == "Int64" || to == "Int32" { x.value.clone() } else { None };
+            Ok(vec![TensorShape { value, ..TensorShape::new(to, x.dims.clone()) }])
+        }
+        _ if BROADCAST.contains(&op) || LOGICAL.contains(&op) => {
+            let shapes = (0..inputs.len()).map(input).collect::<Result<Vec<_>, _>>()?;
+            let first = shapes.first().ok_or("no inputs")?;
+            let dtype = if LOGICAL.contains(&op) { "Bool".into() } else { first.dtype.clone() };
+            Ok(vec![TensorShape::new(dtype, broadcast(&shapes)?)])
+        }
+        "Where" => {
+            let shapes = [input(0)?, input(1)?, input(2)?];
+            Ok(vec![TensorShape::new(shapes[1].dtype.clone(), broadcast(&shapes)?)])
+        }
+        "MatMul" => matmul(input(0)?, input(1)?).map(|shape| vec![shape]),
+        "Gemm" => gemm(node, input(0)?, input(1)?).map(|shape| vec![shape]),
+        "Conv" => {
+            let (x, w) = (input(0)?, input(1)?);
+            let kernel = match ints_attr(node, "kernel_shape") {
+                Some(kernel) => kernel.to_vec(),
+                None => w
+                    .dims
+                    .iter()
+                    .skip(2)
+                    .map(static_dim)
+                    .collect::<Option<_>>()
+                    .ok_or("unknown kernel shape")?,
+            };
+            let channels = w.dims.first().cloned().ok_or("weight of rank 0")?;
+            window(node, x, &kernel, channels).map(|shape| vec![shape])
+        }
+        "MaxPool" | "AveragePool" | "LpPool" => {
+            let x = input(0)?;
+            let kernel = ints_attr(node, "kernel_shape").ok_or("missing attribute kernel_shape")?;
+            let channels = x.dims.get(1).cloned().ok_or("input of rank < 2")?;
+            let y = window(node, x, kernel, channels)?;
+            let indices = TensorShape::new("Int64", y.dims.clone());
+            Ok(vec![y, indices])
+        }
+        "GlobalMaxPool" | "GlobalAveragePool" | "GlobalLpPool" => {
+            let x = input(0)?;
+            if x.dims.len() < 2 {
+                return Err("input of rank < 2".into());
+            }
+            let dims = x
+                .dims
+                .iter()
+                .enumerate()
+                .map(|(i, d)| if i < 2 { d.clone() } else { Dim::Static(1) });
+            Ok(vec![TensorShape::new(x.dtype.clone(), dims.collect())])
+        }
+        "Reshape" => reshape(node, input(0)?, input(1)?).map(|shape| vec![shape]),
+        "Flatten" => {
+            let x = input(0)?;
+            let rank = x.dims.len();
+            // the axis may also be the rank itself, everything is then flattened into the first dim
+            let axis = match int_attr(node, "axis").unwrap_or(1) {
+                axis if axis == rank as i64 => rank,
+                axis => normalize_axis(axis, rank)?,
+            };
+            let dims = vec![product(&x.dims[..axis]), product(&x.dims[axis..])];
+            Ok(vec![TensorShape::new(x.dtype.clone(), dims)])
+        }
+        "Transpose" => {
+            let x = input(0)?;
+            let rank = x.dims.len();
+            let perm = match ints_attr(node, "perm") {
+                Some(perm) => {
+                    perm.iter().map(|&p| normalize_axis(p, rank)).collect::<Result<_, _>>()?
+                }
+                None => (0..rank).rev().collect::<Vec<_>>(),
+            };
+            if perm.len() != rank {
+                return Err(format!("perm of length {} for rank {}", perm.len(), rank).into());
+            }
+            let dims = perm.iter().map(|&p| x.dims[p].clone()).collect();
+            Ok(vec![TensorShape::new(x.dtype.clone(), dims)])
+        }
+        "Concat" => {
+            let shapes = (0..inputs.len()).map(input).collect::<Result<Vec<_>, _>>()?;
+            concat(node, &shapes).map(|shape| vec![shape])
+        }
+        "Split" => split(node, input(0)?, inputs.get(1).copied().flatten()),
+        "Gather" => gather(node, input(0)?, input(1)?).map(|shape| vec![shape]),
+        "Shape" => {
+            let x = input(0)?;
+            let rank = x.dims.len() as i64;
+            let clamp = |i: i64| (if i < 0 { i + rank } else { i }).clamp(0, rank) as usize;
+            let start = clamp(int_attr(node, "start").unwrap_or(0));
+            let end = clamp(int_attr(node, "end").unwrap_or(rank)).max(start);
+            let dims = &x.dims[start..end];
+            Ok(vec![TensorShape {
+                value: dims.iter().map(static_dim).collect(),
+                ..TensorShape::new("Int64", vec![Dim::Static(dims.len() as i64)])
+            }])
+        }
+        "Unsqueeze" | "Squeeze" => {
+            let x = input(0)?;
+            let axes = match ints_attr(node, "axes") {
+                Some(axes) => Some(axes.to_vec()),
+                None if inputs.len() > 1 => {
+                    Some(input(1)?.value.clone().ok_or("unknown value of axes")?)
+                }
+                None => None,
+            };
+            let dims = if op == "Unsqueeze" {
+                unsqueeze(&x.dims, &axes.ok_or("missing axes")?)?
+            } else {
+                squeeze(&x.dims, axes.as_deref())?
+            };
+            Ok(vec![TensorShape {
+                value: x.value.clone(),
+                ..TensorShape::new(x.dtype.clone(), dims)
+            }])
+        }
+        _ => Err("unsupported op".into()),
+    }
+}
+
+fn int_attr(node: &Node, name: &str) -> Option<i64> {
+    match node.attributes.get(name) {
+        Some(AttrValue::Int(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+fn ints_attr<'a>(node: &'a Node, name: &str) -> Option<&'a [i64]> {
+    match node.attributes.get(name) {
+        Some(AttrValue::Ints(values)) => Some(values),
+        _ => None,
+    }
+}
+
+fn str_attr<'a>(node: &'a Node, name: &str) -> Option<&'a str> {
+    match node.attributes.get(name) {
+        Some(AttrValue::Str(value)) => Some(value),
+        _ => None,
+    }
+}
+
+fn same_shape(x: &TensorShape) -> TensorShape {
+    TensorShape::new(x.dtype.clone(), x.dims.clone())
+}
+
+fn static_dim(dim: &Dim) -> Option<i64> {
+    match dim {
+        Dim::Static(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// The product of the dims, unknown unless they are all static or there's only one.
+fn product(dims: &[Dim]) -> Dim {
+    if let [dim] = dims {
+        return dim.clone();
+    }
+    match dims.iter().map(static_dim).product::<Option<i64>>() {
+        Some(value) => Dim::Static(value),
+        None => Dim::Unknown,
+    }
+}
+
+/// Maps an axis in `[-rank, rank)` to `[0, rank)`.
+fn normalize_axis(axis: i64, rank: usize) -> Result<usize, String> {
+    let rank = rank as i64;
+    match axis {
+        _ if (0..rank).contains(&axis) => Ok(axis as usize),
+        _ if (-rank..0).contains(&axis) => Ok((axis + rank) as usize),
+        _ => Err(format!("axis {} out of range for rank {}", axis, rank).into()),
+    }
+}
+
+/// ## Note
+/// A symbolic dim against a static one other than 1 takes the static one,
+/// since the model is only valid when they are equal.
+fn broadcast_dim(a: &Dim, b: &Dim) -> Result<Dim, String> {
+    Ok(match (a, b) {
+        (Dim::Static(1), d) | (d, Dim::Static(1)) => d.clone(),
+        _ if a == b => a.clone(),
+        (Dim::Static(x), Dim::Static(y)) => {
+            return Err(format!("cannot broadcast {} and {}", x, y).into());
+        }
+        (Dim::Static(x), _) | (_, Dim::Static(x)) => Dim::Static(*x),
+        _ => Dim::Unknown,
+    })
+}
+
+/// Numpy broadcasting, dims are aligned from the last.
+fn broadcast(shapes: &[&TensorShape]) -> Result<Vec<Dim>, String> {
+    let rank = shapes.iter().map(|shape| shape.dims.len()).max().unwrap_or(0);
+    let mut dims = vec![Dim::Static(1); rank];
+    for shape in shapes {
+        let offset = rank - shape.dims.len();
+        for (i, dim) in shape.dims.iter().enumerate() {
+            dims[offset + i] = broadcast_dim(&dims[offset + i], dim)?;
+        }
+    }
+    Ok(dims)
+}
+
+/// ## Note
+/// Two static dims that should match but don't are an error,
+/// a symbolic one is assumed to match.
+fn check_inner(a: &Dim, b: &Dim) -> Result<(), String> {
+    match (a, b) {
+        (Dim::Static(x), Dim::Static(y)) if x != y => {
+            Err(format!("inner dims {} and {} differ", x, y).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn matmul(a: &TensorShape, b: &TensorShape) -> Result<TensorShape, String> {
+    if a.dims.is_empty() || b.dims.is_empty() {
+        return Err("matmul of a scalar".into());
+    }
+    // a vector is promoted to a matrix, and the added dim is removed from the result
+    let mut a_dims = a.dims.clone();
+    if a_dims.len() == 1 {
+        a_dims.insert(0, Dim::Static(1));
+    }
+    let mut b_dims = b.dims.clone();
+    if b_dims.len() == 1 {
+        b_dims.push(Dim::Static(1));
+    }
+    let (a_batch, a_mat) = a_dims.split_at(a_dims.len() - 2);
+    let (b_batch, b_mat) = b_dims.split_at(b_dims.len() - 2);
+    check_inner(&a_mat[1], &b_mat[0])?;
+
+    let mut dims = broadcast(&[
+        &TensorShape::new("", a_batch.to_vec()),
+        &TensorShape::new("", b_batch.to_vec()),
+    ])?;
+    if a.dims.len() > 1 {
+        dims.push(a_mat[0].clone());
+    }
+    if b.dims.len() > 1 {
+        dims.push(b_mat[1].clone());
+    }
+    Ok(TensorShape::new(a.dtype.clone(), dims))
+}
+
+fn gemm(node: &Node, a: &TensorShape, b: &TensorShape) -> Result<TensorShape, String> {
+    if a.dims.len() != 2 || b.dims.len() != 2 {
+        return Err("gemm inputs must be matrices".into());
+    }
+    let (m, k) = match int_attr(node, "transA") {
+        Some(1) => (&a.dims[1], &a.dims[0]),
+        _ => (&a.dims[0], &a.dims[1]),
+    };
+    let (k2, n) = match int_attr(node, "transB") {
+        Some(1) => (&b.dims[1], &b.dims[0]),
+        _ => (&b.dims[0], &b.dims[1]),
+    };
+    check_inner(k, k2)?;
+    Ok(TensorShape::new(a.dtype.clone(), vec![m.clone(), n.clone()]))
+}
+
+/// ## Explanation
+/// The output of a sliding window op, `Conv` or a pool, over an `N x C x D1 x ... x Dn` input:
+/// ```text
+/// NOTSET: (D + pad_begin + pad_end - ((kernel - 1) * dilation + 1)) / stride + 1
+/// VALID:  (D - ((kernel - 1) * dilation + 1)) / stride + 1
+/// SAME_*: ceil(D / stride)
+/// ```
+/// The division rounds down, or up with `ceil_mode`.
+fn window(
+    node: &Node,
+    x: &TensorShape,
+    kernel: &[i64],
+    channels: Dim,
+) -> Result<TensorShape, String> {
+    let spatial = kernel.len();
+    if x.dims.len() != spatial + 2 {
+        return Err(format!("input of rank {} for a {}-d kernel", x.dims.len(), spatial).into());
+    }
+    let attr = |name, default| match ints_attr(node, name) {
+        Some(values) => values.to_vec(),
+        None => vec![default; spatial],
+    };
+    let strides = attr("strides", 1);
+    let dilations = attr("dilations", 1);
+    let pads = match ints_attr(node, "pads") {
+        Some(pads) => pads.to_vec(),
+        None => vec![0; spatial * 2],
+    };
+    if strides.len() != spatial || dilations.len() != spatial || pads.len() != spatial * 2 {
+        return Err("strides, dilations or pads don't match the kernel".into());
+    }
+    let ceil_mode = int_attr(node, "ceil_mode") == Some(1);
+    let auto_pad = str_attr(node, "auto_pad").unwrap_or("NOTSET");
+
+    let mut dims = vec![x.dims[0].clone(), channels];
+    for i in 0..spatial {
+        let Some(size) = static_dim(&x.dims[i + 2]) else {
+            dims.push(Dim::Unknown);
+            continue;
+        };
+        let extent = (kernel[i] - 1) * dilations[i] + 1;
+        let stride = strides[i].max(1);
+        let div = |n: i64| {
+            if ceil_mode { (n + stride - 1).div_euclid(stride) } else { n.div_euclid(stride) }
+        };
+        let out = match auto_pad {
+            "SAME_UPPER" | "SAME_LOWER" => (size + stride - 1) / stride,
+            "VALID" => div(size - extent) + 1,
+            _ => div(size + pads[i] + pads[i + spatial] - extent) + 1,
+        };
+        if out < 1 {
+            return Err(format!("window larger than the input at axis {}", i + 2).into());
+        }
+        dims.push(Dim::Static(out));
+    }
+    Ok(TensorShape::new(x.dtype.clone(), dims))
+}
+
+/// ## Note
+/// `0` copies the input dim at the same position unless `allowzero` is set,
+/// `-1` takes what's left, which is only known when the input dims are static.
+fn reshape(node: &Node, x: &TensorShape, shape: &TensorShape) -> Result<TensorShape, String> {
+    let target = shape.value.as_ref().ok_or("unknown value of shape")?;
+    let allow_zero = int_attr(node, "allowzero") == Some(1);
+    let mut dims: Vec<Dim> = target
+        .iter()
+        .enumerate()
+        .map(|(i, &d)| match d {
+            0 if !allow_zero => x.dims.get(i).cloned().unwrap_or(Dim::Unknown),
+            -1 => Dim::Unknown,
+            _ => Dim::Static(d),
+        })
+        .collect();
+
+    match target.iter().filter(|&&d| d == -1).count() {
+        0 => {}
+        1 => {
+            let rest = target.iter().position(|&d| d == -1).unwrap();
+            let known = product(&[&dims[..rest], &dims[rest + 1..]].concat());
+            if let (Dim::Static(total), Dim::Static(known)) = (product(&x.dims), known) {
+                if known == 0 || total % known != 0 {
+                    return Err(format!("cannot reshape {} elements", total).into());
+                }
+                dims[rest] = Dim::Static(total / known);
+            }
+        }
+        _ => return Err("more than one -1 in shape".into()),
+    }
+    Ok(TensorShape { value: x.value.clone(), ..TensorShape::new(x.dtype.clone(), dims) })
+}
+
+fn concat(node: &Node, shapes: &[&TensorShape]) -> Result<TensorShape, String> {
+    let first = shapes.first().ok_or("no inputs")?;
+    let rank = first.dims.len();
+    let axis = normalize_axis(int_attr(node, "axis").ok_or("missing attribute axis")?, rank)?;
+
+    let mut dims = first.dims.clone();
+    for shape in &shapes[1..] {
+        if shape.dims.len() != rank {
+            return Err("inputs of different ranks".into());
+        }
+        for (i, dim) in shape.dims.iter().enumerate() {
+            dims[i] = match (i == axis, &dims[i], dim) {
+                (true, Dim::Static(a), Dim::Static(b)) => Dim::Static(a + b),
+                (true, ..) => Dim::Unknown,
+                (false, Dim::Static(a), _) | (false, _, Dim::Static(a)) => Dim::Static(*a),
+                (false, a, _) => a.clone(),
+            };
+        }
+    }
+    let value = match rank {
+        1 => shapes.iter().map(|shape| shape.value.clone()).collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+    Ok(TensorShape {
+        value: value.map(|v| v.concat()),
+        ..TensorShape::new(first.dtype.clone(), dims)
+    })
+}
+
+/// ## Note
+/// The sizes come from the `split` input or attribute, without them the dim is split
+/// in equal parts, the last one smaller when it doesn't divide evenly.
+fn split(node: &Node, x: &TensorShape, sizes: Option<&TensorShape>) -> Shapes {
+    let axis = normalize_axis(int_attr(node, "axis").unwrap_or(0), x.dims.len())?;
+    let sizes = match (sizes, ints_attr(node, "split")) {
+        (Some(sizes), _) => Some(sizes.value.clone().ok_or("unknown value of split")?),
+        (None, Some(sizes)) => Some(sizes.to_vec()),
+        (None, None) => None,
+    };
+    let parts = sizes.as_ref().map_or(node.output.len(), Vec::len);
+    let lengths: Vec<Dim> = match (sizes, &x.dims[axis]) {
+        (Some(sizes), _) => sizes.into_iter().map(Dim::Static).collect(),
+        (None, Dim::Static(total)) => {
+            let chunk = (total + parts as i64 - 1) / parts.max(1) as i64;
+            (0..parts as i64).map(|i| Dim::Static(chunk.min(total - chunk * i).max(0))).collect()
+        }
+        (None, _) => vec![Dim::Unknown; parts],
+    };
+
+    let part = |length| {
+        let mut dims = x.dims.clone();
+        dims[axis] = length;
+        TensorShape::new(x.dtype.clone(), dims)
+    };
+    Ok(lengths.into_iter().map(part).collect())
+}
+
+fn gather(node: &Node, data: &TensorShape, indices: &TensorShape) -> Result<TensorShape, String> {
+    let axis = normalize_axis(int_attr(node, "axis").unwrap_or(0), data.dims.len())?;
+    let dims = [&data.dims[..axis], &indices.dims, &data.dims[axis + 1..]].concat();
+
+    // indexing a shape, e.g. the batch size out of `Shape`
+    let value = match (&data.value, &indices.value) {
+        (Some(values), Some(picks)) if data.dims.len() == 1 => {
+            let len = values.len() as i64;
+            picks
+                .iter()
+                .map(|&i| values.get((if i < 0 { i + len } else { i }) as usize).copied())
+                .collect()
+        }
+        _ => None,
+    };
+    Ok(TensorShape { value, ..TensorShape::new(data.dtype.clone(), dims) })
+}
+
+fn unsqueeze(dims: &[Dim], axes: &[i64]) -> Result<Vec<Dim>, String> {
+    let rank = dims.len() + axes.len();
+    let mut axes = axes.iter().map(|&a| normalize_axis(a, rank)).collect::<Result<Vec<_>, _>>()?;
+    axes.sort();
+    if let Some(pair) = axes.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(format!("duplicate axis {}", pair[0]).into());
+    }
+    let mut out = dims.to_vec();
+    for axis in axes {
+        out.insert(axis, Dim::Static(1));
+    }
+    Ok(out)
+}
+
+fn squeeze(dims: &[Dim], axes: Option<&[i64]>) -> Result<Vec<Dim>, String> {
+    let Some(axes) = axes else {
+        if dims.iter().any(|d| static_dim(d).is_none()) {
+            return Err("squeeze of all dims of size 1 with unknown dims".into());
+        }
+        return Ok(dims.iter().filter(|d| **d != Dim::Static(1)).cloned().collect());
+    };
+    let axes =
+        axes.iter().map(|&a| normalize_axis(a, dims.len())).collect::<Result<Vec<_>, _>>()?;
+    for &axis in &axes {
+        match dims[axis] {
+            Dim::Static(d) if d != 1 => {
+                return Err(format!("squeeze of axis {} of size {}", axis, d).into());
+            }
+            _ => {}
+        }
+    }
+    Ok(dims.iter().enumerate().filter(|(i, _)| !axes.contains(i)).map(|(_, d)| d.clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{self, node};
+
+    fn model(nodes: Vec<Node>, value_info: &[(&str, TensorShape)]) -> Model {
+        let mut model = fixture::model(nodes);
+        model.value_info = value_info.iter().map(|(k, v)| (String::from(*k), v.clone())).collect();
+        model
+    }
+
+    fn float(dims: &[Dim]) -> TensorShape {
+        TensorShape::new("Float", dims.to_vec())
+    }
+
+    fn ints(values: &[i64]) -> TensorShape {
+        TensorShape {
+            value: Some(values.to_vec()),
+            ..TensorShape::new("Int64", vec![Dim::Static(values.len() as i64)])
+        }
+    }
+
+    use Dim::{Static as S, Unknown};
+
+    fn sym(name: &str) -> Dim {
+        Dim::Symbolic(name.into())
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let dims = |a: &[Dim], b: &[Dim]| broadcast(&[&float(a), &float(b)]);
+        assert_eq!(Ok(vec![S(2), S(3), S(4)]), dims(&[S(2), S(1), S(4)], &[S(3), S(1)]));
+        assert_eq!(Ok(vec![sym("n"), S(4)]), dims(&[sym("n"), S(4)], &[S(4)]));
+        assert_eq!(Ok(vec![S(5)]), dims(&[sym("n")], &[S(5)]));
+        assert_eq!(Ok(vec![Unknown]), dims(&[sym("n")], &[sym("m")]));
+        assert!(dims(&[S(2)], &[S(3)]).is_err());
+    }
+
+    #[test]
+    fn test_infer_cnn() {
+        let mut model = model(
+            vec![
+                node("Conv", &["x", "w", "b"], &["conv"], &[
+                    ("pads", AttrValue::Ints(vec![1, 1, 1, 1])),
+                    ("strides", AttrValue::Ints(vec![2, 2])),
+                ]),
+                node("Relu", &["conv"], &["relu"], &[]),
+                node("MaxPool", &["relu"], &["pool"], &[
+                    ("kernel_shape", AttrValue::Ints(vec![2, 2])),
+                    ("strides", AttrValue::Ints(vec![2, 2])),
+                    ("ceil_mode", AttrValue::Int(1)),
+                ]),
+                node("Shape", &["pool"], &["shape"], &[]),
+                node("Gather", &["shape", "zero"], &["batch"], &[]),
+                node("Unsqueeze", &["batch"], &["batch_1"], &[("axes", AttrValue::Ints(vec![0]))]),
+                node(
+                    "Concat",
+                    &["batch_1", "minus_one"],
+                    &["target"],
+                    &[("axis", AttrValue::Int(0))],
+                ),
+                node("Reshape", &["pool", "target"], &["flat"], &[]),
+                node("Gemm", &["flat", "fc"], &["logits"], &[("transB", AttrValue::Int(1))]),
+                node("Softmax", &["logits"], &["prob"], &[]),
+                node("TopK", &["prob", "k"], &["values", "indices"], &[]),
+            ],
+            &[
+                ("x", float(&[S(1), S(3), S(32), S(32)])),
+                ("w", float(&[S(8), S(3), S(3), S(3)])),
+                ("b", float(&[S(8)])),
+                ("fc", float(&[S(10), S(512)])),
+                ("zero", TensorShape { value: Some(vec![0]), ..TensorShape::new("Int64", vec![]) }),
+                ("minus_one", ints(&[-1])),
+            ],
+        );
+        let report = infer_shapes(&mut model);
+
+        let shape = |name: &str| model.value_info[name].dims.clone();
+        assert_eq!(vec![S(1), S(8), S(16), S(16)], shape("conv"));
+        assert_eq!(vec![S(1), S(8), S(8), S(8)], shape("pool"));
+        assert_eq!(Some(vec![1, 8, 8, 8]), model.value_info["shape"].value);
+        assert_eq!(Some(vec![1, -1]), model.value_info["target"].value);
+        assert_eq!(vec![S(1), S(512)], shape("flat"));
+        assert_eq!(vec![S(1), S(10)], shape("prob"));
+        assert_eq!("Float", model.value_info["prob"].dtype);
+
+        assert_eq!(10, report.resolved);
+        assert_eq!(1, report.unresolved.len());
+        assert_eq!(
+            ("values", "unsupported op"),
+            (report.unresolved[0].node.as_str(), report.unresolved[0].reason.as_str())
+        );
+        assert_eq!(vec![None, None], model.nodes["values"].output_shapes);
+
+        // edges are derived from tensor names, and carry the shape of their tensor
+        let edge = model.typed_edges.iter().find(|e| e.target == "relu").unwrap();
+        assert_eq!(
+            ("conv", Some(shape("conv"))),
+            (edge.source.as_str(), edge.shape.as_ref().map(|s| s.dims.clone()))
+        );
+        assert_eq!(11, model.typed_edges.len());
+    }
+
+    #[test]
+    fn test_infer_symbolic() {
+        let mut model = model(
+            vec![
+                node("MatMul", &["x", "w"], &["y"], &[]),
+                node("Add", &["y", "bias"], &["z"], &[]),
+                node("Transpose", &["z"], &["t"], &[("perm", AttrValue::Ints(vec![1, 0, 2]))]),
+                node("Split", &["t"], &["s0", "s1"], &[("axis", AttrValue::Int(-1))]),
+                node("Flatten", &["s1"], &["f"], &[]),
+                node("MatMul", &["x", "bias"], &["bad"], &[]),
+                node("Relu", &["bad"], &["after_bad"], &[]),
+                Node { output: vec![], ..node("Relu", &["y"], &["unnamed"], &[]) },
+            ],
+            &[
+                ("x", float(&[sym("batch"), sym("seq"), S(16)])),
+                ("w", float(&[S(16), S(7)])),
+                ("bias", float(&[S(7)])),
+                ("z", float(&[sym("batch"), sym("seq"), sym("hidden")])),
+            ],
+        );
+        let report = infer_shapes(&mut model);
+
+        let shape = |name: &str| model.value_info[name].dims.clone();
+        assert_eq!(vec![sym("batch"), sym("seq"), S(7)], shape("y"));
+        // the declared shape wins over the inferred one
+        assert_eq!(vec![sym("batch"), sym("seq"), sym("hidden")], shape("z"));
+        assert_eq!(vec![sym("seq"), sym("batch"), sym("hidden")], shape("t"));
+        assert_eq!(vec![sym("seq"), sym("batch"), Unknown], shape("s1"));
+        assert_eq!(vec![sym("seq"), Unknown], shape("f"));
+
+        let reasons: Vec<_> =
+            report.unresolved.iter().map(|u| (u.node.as_str(), u.reason.as_str())).collect();
+        assert_eq!(
+            vec![
+                ("bad", "inner dims 16 and 7 differ"),
+                ("after_bad", "unknown shape of input 0"),
+                ("unnamed", "outputs not named"),
+            ],
+            reasons
+        );
+        assert_eq!(5, report.resolved);
+    }
+
+    #[test]
+    fn test_ops() {
+        let one = |n: &Node, inputs: &[&TensorShape]| {
+            let inputs: Vec<_> = inputs.iter().map(|&s| Some(s)).collect();
+            infer_node(n, &inputs).map(|shapes| shapes[0].dims.clone())
+        };
+        let x = float(&[S(2), S(3), S(4)]);
+
+        let reshape = node("Reshape", &["x", "s"], &["y"], &[]);
+        assert_eq!(Ok(vec![S(2), S(12)]), one(&reshape, &[&x, &ints(&[0, -1])]));
+        assert!(one(&reshape, &[&x, &ints(&[5, -1])]).is_err());
+
+        let split = node("Split", &["x"], &["a", "b", "c"], &[("axis", AttrValue::Int(2))]);
+        let parts = infer_node(&split, &[Some(&float(&[S(2), S(3), S(5)]))]).unwrap();
+        let last: Vec<_> = parts.iter().map(|p| p.dims[2].clone()).collect();
+        assert_eq!(vec![S(2), S(2), S(1)], last);
+        let split = node("Split", &["x", "sizes"], &["a", "b"], &[("axis", AttrValue::Int(1))]);
+        let parts = infer_node(&split, &[Some(&x), Some(&ints(&[1, 2]))]).unwrap();
+        assert_eq!((S(1), S(2)), (parts[0].dims[1].clone(), parts[1].dims[1].clone()));
+
+        let matmul = node("MatMul", &["a", "b"], &["y"], &[]);
+        assert_eq!(Ok(vec![S(2), S(3)]), one(&matmul, &[&x, &float(&[S(4)])]));
+        assert!(one(&matmul, &[&x, &float(&[S(5), S(6)])]).is_err());
+
+        let squeeze = node("Squeeze", &["x"], &["y"], &[]);
+        assert_eq!(Ok(vec![S(3)]), one(&squeeze, &[&float(&[S(1), S(3), S(1)])]));
+        assert!(one(&squeeze, &[&float(&[S(1), sym("n")])]).is_err());
+
+        let flatten =
+            |axis: i64| node("Flatten", &["x"], &["y"], &[("axis", AttrValue::Int(axis))]);
+        assert_eq!(Ok(vec![S(6), S(4)]), one(&flatten(-1), &[&x]));
+        assert_eq!(Ok(vec![S(2), S(12)]), one(&flatten(-2), &[&x]));
+        assert_eq!(Ok(vec![S(1), S(24)]), one(&flatten(0), &[&x]));
+        assert_eq!(Ok(vec![S(24), S(1)]), one(&flatten(3), &[&x]));
+        assert!(one(&flatten(-4), &[&x]).is_err());
+        assert!(one(&flatten(4), &[&x]).is_err());
+
+        let unsqueeze = |axes: &[i64]| {
+            node("Unsqueeze", &["x"], &["y"], &[("axes", AttrValue::Ints(axes.to_vec()))])
+        };
+        assert_eq!(Ok(vec![S(2), S(1), S(3), S(4), S(1)]), one(&unsqueeze(&[1, -1]), &[&x]));
+        assert!(one(&unsqueeze(&[1, 1]), &[&x]).is_err());
+        assert!(one(&unsqueeze(&[4, 4]), &[&x]).is_err());
+        assert!(one(&unsqueeze(&[0, -5]), &[&x]).is_err());
+        assert!(one(&unsqueeze(&[5]), &[&x]).is_err());
+
+        let pool = node("GlobalAveragePool", &["x"], &["y"], &[]);
+        assert_eq!(Ok(vec![S(2), S(3), S(1)]), one(&pool, &[&x]));
+
+        let same =
+            node("Conv", &["x", "w"], &["y"], &[("auto_pad", AttrValue::Str("SAME_UPPER".into()))]);
+        assert_eq!(
+            Ok(vec![sym("n"), S(4), S(9), Unknown]),
+            one(
+                &same,
+                &[&float(&[sym("n"), S(3), S(9), sym("w")]), &float(&[S(4), S(3), S(3), S(3)])]
+            )
+        );
+
+        let cast = node("Cast", &["x"], &["y"], &[("to", AttrValue::Int(7))]);
+        let cast = infer_node(&cast, &[Some(&ints(&[1, 2]))]).unwrap();
+        assert_eq!(("Int64", Some(vec![1, 2])), (cast[0].dtype.as_str(), cast[0].value.clone()));
+    }
+}
@@ -0,0 +1,726 @@
+use std::{error::Error, fmt, sync::OnceLock};
+
+use ahash::HashMap;
+use prost::{Message, Name};
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FileDescriptorSet,
+    field_descriptor_proto::{Label, Type},
+};
+
+/// ## Explanation
+/// Reads the protobuf text format (`.pbtxt`, `.prototxt`), as written by `text_format.Print`
+/// in Python or `TextFormat::Print` in C++, into the same prost messages as the binary files.
+///
+/// The text is encoded into the binary wire format in a single pass, driven by the descriptors
+/// `prost-build` emits next to the generated code, and the result goes through
+/// [`Message::decode`]. So the generated types stay the only definition of the schemas
+/// and a text model behaves exactly like its binary counterpart.
+///
+/// ## Note
+/// Extensions and `Any` expansions (`[type.googleapis.com/...] { }`) are rejected,
+/// none of the supported schemas uses them. Unknown fields are errors as well.
+pub fn parse<T>(text: &str) -> Result<T, TextFormatError>
+where
+    T: Default + Message + Name,
+{
+    let buffer = to_binary(&T::full_name(), text)?;
+    // everything is checked while encoding, nesting included (see `MAX_DEPTH`),
+    // a buffer we've written only fails to decode if a check is missing
+    T::decode(buffer.as_slice())
+        .map_err(|e| TextFormatError::new(text.as_bytes(), 0, e.to_string()))
+}
+
+/// Encodes the text of a message, given by its full name e.g. `onnx.ModelProto`,
+/// into the binary wire format.
+pub fn to_binary(message: &str, text: &str) -> Result<Vec<u8>, TextFormatError> {
+    let text = text.as_bytes();
+    let Some(ty) = schema().messages.get(message) else {
+        return Err(TextFormatError::new(text, 0, format!("unknown message type `{}`", message)));
+    };
+
+    // binary messages are about a third of their text
+    let mut writer =
+        Writer { lexer: Lexer { text, pos: 0 }, out: Vec::with_capacity(text.len() / 3), depth: 0 };
+    writer.message(ty, None)?;
+    Ok(writer.out)
+}
+
+/// A syntax or schema error, located by 1-based line and column (in characters).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextFormatError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl TextFormatError {
+    /// Locates the byte offset `pos`, only done once an error happens.
+    fn new(text: &[u8], pos: usize, message: String) -> Self {
+        let before = &text[..pos.min(text.len())];
+        let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+        // count characters, not bytes, continuation bytes of UTF-8 are `0b10xx_xxxx`
+        let column = before[line_start..].iter().filter(|&&b| b & 0xc0 != 0x80).count() + 1;
+        Self { line, column, message }
+    }
+}
+
+impl fmt::Display for TextFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for TextFormatError {}
+
+static DESCRIPTORS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/file_descriptor_set.bin"));
+
+/// Messages and enums of every compiled proto file, by full name without the leading dot.
+struct Schema {
+    messages: HashMap<String, MessageType>,
+    enums: HashMap<String, HashMap<String, i32>>,
+}
+
+struct MessageType {
+    fields: HashMap<String, Field>,
+}
+
+struct Field {
+    number: u32,
+    kind: Type,
+    repeated: bool,
+    /// Full name of the message or enum type, empty for scalars
+    type_name: String,
+}
+
+fn schema() -> &'static Schema {
+    static SCHEMA: OnceLock<Schema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let set =
+            FileDescriptorSet::decode(DESCRIPTORS).expect("descriptors written by prost-build");
+        let mut schema = Schema { messages: Default::default(), enums: Default::default() };
+        for file in &set.file {
+            for message in &file.message_type {
+                schema.add_message(file.package(), message);
+            }
+            for enumeration in &file.enum_type {
+                schema.add_enum(file.package(), enumeration);
+            }
+        }
+        schema
+    })
+}
+
+fn full_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() { name.to_owned() } else { format!("{}.{}", scope, name) }
+}
+
+impl Schema {
+    fn add_message(&mut self, scope: &str, message: &DescriptorProto) {
+        let name = full_name(scope, message.name());
+        let fields = message
+            .field
+            .iter()
+            .map(|field| {
+                let ty = Field {
+                    number: field.number() as u32,
+                    kind: field.r#type(),
+                    repeated: field.label() == Label::Repeated,
+                    type_name: field.type_name().trim_start_matches('.').to_owned(),
+                };
+                (field.name().to_owned(), ty)
+            })
+            .collect();
+        for nested in &message.nested_type {
+            self.add_message(&name, nested);
+        }
+        for enumeration in &message.enum_type {
+            self.add_enum(&name, enumeration);
+        }
+        self.messages.insert(name, MessageType { fields });
+    }
+
+    fn add_enum(&mut self, scope: &str, enumeration: &EnumDescriptorProto) {
+        let values = enumeration.value.iter().map(|v| (v.name().to_owned(), v.number())).collect();
+        self.enums.insert(full_name(scope, enumeration.name()), values);
+    }
+}
+
+enum Token<'a> {
+    Ident(&'a str),
+    /// Integer or float literal, with its sign
+    Number(&'a str),
+    /// One or more adjacent string literals, unescaped and concatenated
+    Str(Vec<u8>),
+    Punct(u8),
+    End,
+}
+
+impl Token<'_> {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(s) | Token::Number(s) => format!("`{}`", s),
+            Token::Str(_) => "string".to_owned(),
+            Token::Punct(c) => format!("`{}`", *c as char),
+            Token::End => "end of input".to_owned(),
+        }
+    }
+}
+
+struct Lexer<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error<T>(&self, pos: usize, message: impl Into<String>) -> Result<T, TextFormatError> {
+        Err(TextFormatError::new(self.text, pos, message.into()))
+    }
+
+    /// Skips whitespace and `#` comments, returns the next byte without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(&b) = self.text.get(self.pos) {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c' => self.pos += 1,
+                b'#' => {
+                    let rest = &self.text[self.pos..];
+                    self.pos += rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                }
+                _ => return Some(b),
+            }
+        }
+        None
+    }
+
+    /// Offset of the next token.
+    fn start(&mut self) -> usize {
+        self.peek();
+        self.pos
+    }
+
+    /// Consumes `c` if it's the next byte.
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), TextFormatError> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        let pos = self.pos;
+        let found = self.next()?;
+        self.error(pos, format!("expected `{}`, found {}", c as char, found.describe()))
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, TextFormatError> {
+        let Some(b) = self.peek() else {
+            return Ok(Token::End);
+        };
+        let start = self.pos;
+        match b {
+            b'"' | b'\'' => {
+                let mut value = Vec::new();
+                while let Some(quote @ (b'"' | b'\'')) = self.peek() {
+                    let open = self.pos;
+                    self.pos += 1;
+                    self.string(open, quote, &mut value)?;
+                }
+                Ok(Token::Str(value))
+            }
+            b'-' | b'.' | b'0'..=b'9' => {
+                self.pos += 1;
+                if b == b'-' {
+                    // `- 1` and `-inf` are both valid
+                    self.peek();
+                }
+                self.word();
+                Ok(Token::Number(self.slice(start)))
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                self.word();
+                Ok(Token::Ident(self.slice(start)))
+            }
+            _ => {
+                self.pos += 1;
+                Ok(Token::Punct(b))
+            }
+        }
+    }
+
+    /// Consumes the rest of an identifier or a number, `1e-5` and `0x1F` included.
+    fn word(&mut self) {
+        while let Some(&b) = self.text.get(self.pos) {
+            let exponent_sign = matches!(b, b'+' | b'-')
+                && matches!(self.text[self.pos - 1], b'e' | b'E')
+                && self.text[..self.pos]
+                    .iter()
+                    .rev()
+                    .take_while(|b| b.is_ascii_alphanumeric())
+                    .all(|&b| b != b'x' && b != b'X');
+            if !(b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || exponent_sign) {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn slice(&self, start: usize) -> &'a str {
+        // only ASCII was consumed
+        std::str::from_utf8(&self.text[start..self.pos]).unwrap()
+    }
+
+    /// Unescapes a literal whose opening quote has been consumed.
+    fn string(
+        &mut self,
+        open: usize,
+        quote: u8,
+        value: &mut Vec<u8>,
+    ) -> Result<(), TextFormatError> {
+        loop {
+            let Some(&b) = self.text.get(self.pos) else {
+                return self.error(open, "unterminated string");
+            };
+            self.pos += 1;
+            match b {
+                b'\n' => return self.error(open, "unterminated string"),
+                b'\\' => self.escape(value)?,
+                _ if b == quote => return Ok(()),
+                _ => value.push(b),
+            }
+        }
+    }
+
+    fn escape(&mut self, value: &mut Vec<u8>) -> Result<(), TextFormatError> {
+        let start = self.pos - 1;
+        let Some(&b) = self.text.get(self.pos) else {
+            return self.error(start, "unterminated string");
+        };
+        self.pos += 1;
+        let c = match b {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'a' => b'\x07',
+            b'b' => b'\x08',
+            b'f' => b'\x0c',
+            b'v' => b'\x0b',
+            b'\\' | b'\'' | b'"' | b'?' => b,
+            b'0'..=b'7' => {
+                self.pos -= 1;
+                let code = self.digits(8, 3);
+                if code > 0xff {
+                    return self.error(start, "octal escape out of range");
+                }
+                code as u8
+            }
+            b'x' | b'X' => match self.digits(16, 2) {
+                _ if self.pos == start + 2 => return self.error(start, "`\\x` without hex digits"),
+                code => code as u8,
+            },
+            b'u' | b'U' => {
+                let len = if b == b'u' { 4 } else { 8 };
+                let from = self.pos;
+                let code = self.digits(16, len);
+                match char::from_u32(code) {
+                    Some(c) if self.pos - from == len => {
+                        value.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        return Ok(());
+                    }
+                    _ => return self.error(start, "invalid unicode escape"),
+                }
+            }
+            _ => return self.error(start, format!("invalid escape `\\{}`", b as char)),
+        };
+        value.push(c);
+        Ok(())
+    }
+
+    /// Reads up to `max` digits in `radix`.
+    fn digits(&mut self, radix: u32, max: usize) -> u32 {
+        let mut code = 0;
+        for _ in 0..max {
+            match self.text.get(self.pos).and_then(|&b| (b as char).to_digit(radix)) {
+                Some(digit) => code = code * radix + digit,
+                None => break,
+            }
+            self.pos += 1;
+        }
+        code
+    }
+}
+
+// wire types
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LENGTH_DELIMITED: u32 = 2;
+const FIXED32: u32 = 5;
+
+/// Bytes reserved for the length of a nested message, patched once the message is written.
+/// A varint padded with `0x80` continuation bytes is still valid, and lets the message be
+/// written in place rather than into a buffer of its own.
+const LENGTH_WIDTH: usize = 5;
+
+/// How deep messages may be nested, the recursion limit of [`Message::decode`].
+/// Deeper text is rejected where the nesting goes too deep, rather than overflowing the stack
+/// of the writer or failing to decode.
+const MAX_DEPTH: usize = 100;
+
+struct Writer<'a> {
+    lexer: Lexer<'a>,
+    out: Vec<u8>,
+    /// Messages opened and not closed yet, the top-level message excluded
+    depth: usize,
+}
+
+impl Writer<'_> {
+    /// Writes fields until `close`, or the end of input for the top-level message.
+    fn message(&mut self, ty: &MessageType, close: Option<u8>) -> Result<(), TextFormatError> {
+        loop {
+            let start = self.lexer.start();
+            let name = match self.lexer.next()? {
+                Token::End if close.is_none() => return Ok(()),
+                Token::Punct(c) if Some(c) == close => return Ok(()),
+                Token::Ident(name) => name,
+                Token::Punct(b'[') => {
+                    return self
+                        .lexer
+                        .error(start, "extensions and `Any` expansions are not supported");
+                }
+                token => {
+                    let expected = close.map_or("field name".to_owned(), |c| {
+                        format!("field name or `{}`", c as char)
+                    });
+                    return self.lexer.error(
+                        start,
+                        format!("expected {}, found {}", expected, token.describe()),
+                    );
+                }
+            };
+            let Some(field) = ty.fields.get(name) else {
+                return self.lexer.error(start, format!("unknown field `{}`", name));
+            };
+
+            if field.kind == Type::Message {
+                // the colon is optional before a message
+                self.lexer.eat(b':');
+            } else {
+                self.lexer.expect(b':')?;
+            }
+            if self.lexer.eat(b'[') {
+                if !field.repeated {
+                    return self
+                        .lexer
+                        .error(self.lexer.pos - 1, format!("field `{}` is not repeated", name));
+                }
+                if !self.lexer.eat(b']') {
+                    loop {
+                        self.value(field)?;
+                        if self.lexer.eat(b']') {
+                            break;
+                        }
+                        self.lexer.expect(b',')?;
+                    }
+                }
+            } else {
+                self.value(field)?;
+            }
+
+            // fields may be separated by `,` or `;`
+            if !self.lexer.eat(b',') {
+                self.lexer.eat(b';');
+            }
+        }
+    }
+
+    fn value(&mut self, field: &Field) -> Result<(), TextFormatError> {
+        let start = self.lexer.start();
+        let kind = field.kind;
+        match kind {
+            Type::Message => {
+                let close = match self.lexer.next()? {
+                    Token::Punct(b'{') => b'}',
+                    Token::Punct(b'<') => b'>',
+                    token => {
+                        return self
+                            .lexer
+                            .error(start, format!("expected `{{`, found {}", token.describe()));
+                    }
+                };
+                if self.depth == MAX_DEPTH {
+                    return self
+                        .lexer
+                        .error(start, format!("messages nested deeper than {}", MAX_DEPTH));
+                }
+                let ty = &schema().messages[field.type_name.as_str()];
+                self.key(field.number, LENGTH_DELIMITED);
+                let at = self.out.len();
+                self.out.extend_from_slice(&[0x80, 0x80, 0x80, 0x80, 0x00]);
+                self.depth += 1;
+                self.message(ty, Some(close))?;
+                self.depth -= 1;
+                let len = self.out.len() - at - LENGTH_WIDTH;
+                if len >> (7 * LENGTH_WIDTH) != 0 {
+                    return self.lexer.error(start, "message too large");
+                }
+                for (i, byte) in self.out[at..at + LENGTH_WIDTH].iter_mut().enumerate() {
+                    *byte |= (len >> (7 * i)) as u8 & 0x7f;
+                }
+            }
+            Type::String | Type::Bytes => {
+                let Token::Str(value) = self.lexer.next()? else {
+                    return self.lexer.error(start, "expected string");
+                };
+                if kind == Type::String && std::str::from_utf8(&value).is_err() {
+                    return self.lexer.error(start, "invalid UTF-8 in string");
+                }
+                self.key(field.number, LENGTH_DELIMITED);
+                self.varint(value.len() as u64);
+                self.out.extend_from_slice(&value);
+            }
+            Type::Double | Type::Float => {
+                let value = match self.lexer.next()? {
+                    Token::Number(s) | Token::Ident(s) => parse_float(s),
+                    _ => None,
+                };
+                let Some(value) = value else {
+                    return self.lexer.error(start, "expected number");
+                };
+                if kind == Type::Double {
+                    self.key(field.number, FIXED64);
+                    self.out.extend_from_slice(&value.to_le_bytes());
+                } else {
+                    self.key(field.number, FIXED32);
+                    self.out.extend_from_slice(&(value as f32).to_le_bytes());
+                }
+            }
+            Type::Bool => {
+                let value = match self.lexer.next()? {
+                    Token::Ident("true" | "True" | "t") | Token::Number("1") => true,
+                    Token::Ident("false" | "False" | "f") | Token::Number("0") => false,
+                    _ => return self.lexer.error(start, "expected `true` or `false`"),
+                };
+                self.key(field.number, VARINT);
+                self.varint(value as u64);
+            }
+            Type::Enum => {
+                let value = match self.lexer.next()? {
+                    Token::Ident(name) => {
+                        schema().enums[field.type_name.as_str()].get(name).copied()
+                    }
+                    Token::Number(s) => parse_int(s).and_then(|v| i32::try_from(v).ok()),
+                    _ => None,
+                };
+                let Some(value) = value else {
+                    return self
+                        .lexer
+                        .error(start, format!("expected a value of `{}`", field.type_name));
+                };
+                self.key(field.number, VARINT);
+                self.varint(value as i64 as u64);
+            }
+            Type::Group => return self.lexer.error(start, "groups are not supported"),
+            _ => {
+                let value = match self.lexer.next()? {
+                    Token::Number(s) => parse_int(s),
+                    _ => None,
+                };
+                let Some(value) = value.filter(|&v| in_range(kind, v)) else {
+                    return self.lexer.error(
+                        start,
+                        format!("expected {}", kind.as_str_name()[5..].to_lowercase()),
+                    );
+                };
+                match kind {
+                    Type::Sint32 | Type::Sint64 => {
+                        let v = value as i64;
+                        self.key(field.number, VARINT);
+                        self.varint(((v << 1) ^ (v >> 63)) as u64);
+                    }
+                    Type::Fixed32 | Type::Sfixed32 => {
+                        self.key(field.number, FIXED32);
+                        self.out.extend_from_slice(&(value as u32).to_le_bytes());
+                    }
+                    Type::Fixed64 | Type::Sfixed64 => {
+                        self.key(field.number, FIXED64);
+                        self.out.extend_from_slice(&(value as u64).to_le_bytes());
+                    }
+                    _ => {
+                        // negative int32s are sign extended to 64 bits, as prost expects
+                        self.key(field.number, VARINT);
+                        self.varint(value as i64 as u64);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, number: u32, wire_type: u32) {
+        self.varint(((number << 3) | wire_type) as u64);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+}
+
+/// Decimal, hex (`0x`) or octal (leading `0`) integer, with an optional `-`.
+fn parse_int(s: &str) -> Option<i128> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, s),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8).ok()?
+    } else {
+        digits.parse::<u64>().ok()?
+    } as i128;
+    Some(if negative { -value } else { value })
+}
+
+/// Float with an optional `f` suffix, integers, `inf` and `nan` in any case.
+fn parse_float(s: &str) -> Option<f64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, s),
+    };
+    let value = match digits.to_ascii_lowercase().as_str() {
+        "inf" | "infinity" => f64::INFINITY,
+        "nan" => f64::NAN,
+        _ if digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+            match digits.strip_suffix(['f', 'F']) {
+                Some(float) if !float.starts_with("0x") => float.parse().ok()?,
+                _ => match parse_int(digits) {
+                    Some(int) => int as f64,
+                    None => digits.parse().ok()?,
+                },
+            }
+        }
+        _ => return None,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn in_range(kind: Type, value: i128) -> bool {
+    match kind {
+        Type::Int32 | Type::Sint32 | Type::Sfixed32 => i32::try_from(value).is_ok(),
+        Type::Uint32 | Type::Fixed32 => u32::try_from(value).is_ok(),
+        Type::Uint64 | Type::Fixed64 => u64::try_from(value).is_ok(),
+        _ => i64::try_from(value).is_ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(message: &str, text: &str) -> (usize, usize, String) {
+        let e = to_binary(message, text).unwrap_err();
+        (e.line, e.column, e.message)
+    }
+
+    #[test]
+    fn test_scalars() {
+        // domain = 1 (string), version = 2 (int64)
+        let text = "# comment\ndomain: 'a' \"\\x62\\101\" version: -1";
+        let mut expected = vec![0x0a, 3, b'a', b'b', b'A', 0x10];
+        expected.extend([0xff; 9]);
+        expected.push(0x01);
+        assert_eq!(to_binary("onnx.OperatorSetIdProto", text).unwrap(), expected);
+
+        assert_eq!(to_binary("onnx.OperatorSetIdProto", "version: 0x10;").unwrap(), [0x10, 0x10]);
+        assert_eq!(to_binary("onnx.OperatorSetIdProto", "version: 010").unwrap(), [0x10, 0x08]);
+
+        // f = 2 (float), type = 20 (enum)
+        let bytes = to_binary("onnx.AttributeProto", "f: 1.5e-1f type: FLOAT").unwrap();
+        assert_eq!(bytes[0], 0x15);
+        assert_eq!(f32::from_le_bytes(bytes[1..5].try_into().unwrap()), 0.15);
+        assert_eq!(bytes[5..], [0xa0, 0x01, 0x01]);
+        let bytes = to_binary("onnx.AttributeProto", "f: -inf").unwrap();
+        assert_eq!(f32::from_le_bytes(bytes[1..5].try_into().unwrap()), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_nested() {
+        // dims = 1 (repeated int64), name = 8 (string)
+        let text = "initializer < dims: [2, 3] name: \"w\" > initializer {} node: { }";
+        let bytes = to_binary("onnx.GraphProto", text).unwrap();
+        let mut expected =
+            vec![0x2a, 0x87, 0x80, 0x80, 0x80, 0x00, 0x08, 2, 0x08, 3, 0x42, 1, b'w'];
+        expected.extend([0x2a, 0x80, 0x80, 0x80, 0x80, 0x00, 0x0a, 0x80, 0x80, 0x80, 0x80, 0x00]);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error("onnx.GraphProto", "name: \"g\"\nnode {\n  op_typ: \"Relu\"\n}"),
+            (3, 3, "unknown field `op_typ`".to_owned())
+        );
+        assert_eq!(
+            error("onnx.GraphProto", "node {\n  name: \"a\"\n"),
+            (3, 1, "expected field name or `}`, found end of input".to_owned())
+        );
+        assert_eq!(
+            error("onnx.GraphProto", "name: \"中文\" doc_string \"x\""),
+            (1, 23, "expected `:`, found string".to_owned())
+        );
+        assert_eq!(error("onnx.OperatorSetIdProto", "version: 1.5").1, 10);
+        assert_eq!(error("onnx.OperatorSetIdProto", "domain: \"a").1, 9);
+        assert_eq!(error("onnx.OperatorSetIdProto", "domain: [\"a\"]").1, 9);
+        assert_eq!(error("onnx.TensorProto", "data_type: 1 data_type: BFLOAT").1, 25);
+        assert_eq!(error("onnx.Nothing", "").2, "unknown message type `onnx.Nothing`");
+    }
+
+    // `onnx.TypeProto`, as far as sequences go
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct TypeProto {
+        #[prost(message, optional, boxed, tag = "4")]
+        sequence_type: Option<Box<Sequence>>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Sequence {
+        #[prost(message, optional, boxed, tag = "1")]
+        elem_type: Option<Box<TypeProto>>,
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        // a sequence nests 2 messages: `sequence_type { elem_type { ... } }`
+        let nested = |levels: usize| {
+            let mut text = "sequence_type { elem_type { ".repeat(levels / 2);
+            text += &"} } ".repeat(levels / 2);
+            text
+        };
+
+        let deepest = to_binary("onnx.TypeProto", &nested(MAX_DEPTH)).unwrap();
+        assert!(TypeProto::decode(deepest.as_slice()).is_ok());
+        // one more level and prost gives up
+        let mut deeper = TypeProto::default();
+        for _ in 0..(MAX_DEPTH + 2) / 2 {
+            let elem_type = Some(Box::new(deeper));
+            deeper = TypeProto { sequence_type: Some(Box::new(Sequence { elem_type })) };
+        }
+        assert!(TypeProto::decode(deeper.encode_to_vec().as_slice()).is_err());
+
+        let column = "sequence_type { elem_type { ".len() * MAX_DEPTH / 2 + "sequence_type ".len();
+        assert_eq!(
+            (1, column + 1, format!("messages nested deeper than {}", MAX_DEPTH)),
+            error("onnx.TypeProto", &nested(MAX_DEPTH + 2))
+        );
+
+        // far deeper than the stack would allow without the limit
+        assert!(to_binary("onnx.TypeProto", &nested(1_000_000)).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{error::Error, fmt};
+
+/// ## Explanation
+/// TensorFlow event files and MindSpore summary files share the TFRecord framing:
+/// ```text
+/// | data length | CRC sum  | pb data | CRC sum  |
+/// | uint64_t    | uint32_t | ....    | uint32_t |
+/// ```
+/// Both CRC sums are masked CRC32C, little endian, the first one covers the 8 length bytes.
+///
+/// ## Note
+/// The iterator stops at the first broken record, later records can't be located reliably.
+pub struct Records<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+/// Reads the records of a whole file kept in memory.
+pub fn records(data: &[u8]) -> Records<'_> {
+    Records { data, offset: 0 }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordError {
+    /// The file ends inside the record starting at `offset`
+    Truncated { offset: usize },
+    /// The length of the record starting at `offset` fails its CRC
+    LengthCrc { offset: usize },
+    /// The data of the record starting at `offset` fails its CRC
+    DataCrc { offset: usize },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Truncated { offset } => write!(f, "truncated record at {}", offset),
+            RecordError::LengthCrc { offset } => write!(f, "corrupted length at {}", offset),
+            RecordError::DataCrc { offset } => write!(f, "corrupted data at {}", offset),
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+/// The length and its CRC sum before the data of a record
+pub const HEADER_LEN: usize = 12;
+/// The CRC sum after the data of a record
+pub const FOOTER_LEN: usize = 4;
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<&'a [u8], RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let rest = self.data.get(offset..)?;
+        if rest.is_empty() {
+            return None;
+        }
+
+        let ret = read_record(rest, offset);
+        self.offset = match ret {
+            Ok(data) => offset + HEADER_LEN + data.len() + FOOTER_LEN,
+            Err(_) => self.data.len(),
+        };
+        Some(ret)
+    }
+}
+
+fn read_record(rest: &[u8], offset: usize) -> Result<&[u8], RecordError> {
+    let truncated = RecordError::Truncated { offset };
+    let header = rest.first_chunk().ok_or(truncated)?;
+    let len = check_header(header, offset)?;
+    let len = usize::try_from(len).map_err(|_| truncated)?;
+    let end = HEADER_LEN.checked_add(len).ok_or(truncated)?;
+    let data = rest.get(HEADER_LEN..end).ok_or(truncated)?;
+    let footer = rest.get(end..).and_then(<[u8]>::first_chunk).ok_or(truncated)?;
+    check_data(data, footer, offset)?;
+
+    Ok(data)
+}
+
+/// The data length of the record starting at `offset`, once checked against its CRC.
+pub fn check_header(header: &[u8; HEADER_LEN], offset: usize) -> Result<u64, RecordError> {
+    let (len, crc) = header.split_at(8);
+    if masked_crc32c(len) != u32::from_le_bytes(crc.try_into().unwrap()) {
+        return Err(RecordError::LengthCrc { offset });
+    }
+    Ok(u64::from_le_bytes(len.try_into().unwrap()))
+}
+
+/// Checks the data of the record starting at `offset` against the CRC of its footer.
+pub fn check_data(
+    data: &[u8],
+    footer: &[u8; FOOTER_LEN],
+    offset: usize,
+) -> Result<(), RecordError> {
+    if masked_crc32c(data) != u32::from_le_bytes(*footer) {
+        return Err(RecordError::DataCrc { offset });
+    }
+    Ok(())
+}
+
+/// Appends `data` as one record, the inverse of [`records`].
+pub fn write_record(out: &mut Vec<u8>, data: &[u8]) {
+    let len = (data.len() as u64).to_le_bytes();
+    out.extend_from_slice(&len);
+    out.extend_from_slice(&masked_crc32c(&len).to_le_bytes());
+    out.extend_from_slice(data);
+    out.extend_from_slice(&masked_crc32c(data).to_le_bytes());
+}
+
+/// CRC32C (Castagnoli), reflected polynomial `0x82F63B78`
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// The mask TFRecord applies, so that a CRC of data containing CRCs stays well distributed.
+pub fn masked_crc32c(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    crc.rotate_right(15).wrapping_add(0xA282_EAD8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(0xE306_9283, crc32c(b"123456789"));
+        assert_eq!(0, crc32c(b""));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut file = vec![];
+        write_record(&mut file, b"first");
+        write_record(&mut file, b"");
+        write_record(&mut file, b"third");
+        let data: Vec<&[u8]> = records(&file).map(Result::unwrap).collect();
+        assert_eq!(vec![&b"first"[..], b"", b"third"], data);
+    }
+
+    #[test]
+    fn test_broken_records() {
+        let mut file = vec![];
+        write_record(&mut file, b"first");
+        let second = file.len();
+        write_record(&mut file, b"second");
+
+        let mut truncated = records(&file[..file.len() - 1]);
+        assert_eq!(Some(Ok(&b"first"[..])), truncated.next());
+        assert_eq!(Some(Err(RecordError::Truncated { offset: second })), truncated.next());
+        assert_eq!(None, truncated.next());
+
+        let mut corrupted = file.clone();
+        corrupted[second + HEADER_LEN] ^= 1;
+        let ret: Vec<_> = records(&corrupted).collect();
+        assert_eq!(Err(RecordError::DataCrc { offset: second }), ret[1]);
+
+        corrupted = file.clone();
+        corrupted[0] ^= 1;
+        let ret: Vec<_> = records(&corrupted).collect();
+        assert_eq!(vec![Err(RecordError::LengthCrc { offset: 0 })], ret);
+    }
+}
@@ -0,0 +1,429 @@
+use std::{env, fs, io};
+
+use parser::{
+    AttrFormat, Dim, Model, PreviewValues, TensorRef, infer_shapes, parse_onnx_model,
+    preview_onnx_tensor, with_attr_format, with_attr_source,
+};
+use serde_json::json;
+
+// Minimal protobuf writer for length-delimited and varint fields.
+fn field(tag: u32, payload: &[u8]) -> Vec<u8> {
+    let mut buf = vec![];
+    varint(&mut buf, (tag << 3 | 2) as u64);
+    varint(&mut buf, payload.len() as u64);
+    buf.extend_from_slice(payload);
+    buf
+}
+
+fn int_field(tag: u32, value: u64) -> Vec<u8> {
+    let mut buf = vec![];
+    varint(&mut buf, (tag << 3) as u64);
+    varint(&mut buf, value);
+    buf
+}
+
+fn varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+// NodeProto: input = 1, output = 2, name = 3, op_type = 4, doc_string = 6, domain = 7
+fn node(name: &str, op_type: &str, input: &[&str], output: &[&str], domain: &str) -> Vec<u8> {
+    let mut buf = vec![];
+    for i in input {
+        buf.extend(field(1, i.as_bytes()));
+    }
+    for o in output {
+        buf.extend(field(2, o.as_bytes()));
+    }
+    buf.extend(field(3, name.as_bytes()));
+    buf.extend(field(4, op_type.as_bytes()));
+    buf.extend(field(6, format!("doc of {}", op_type).as_bytes()));
+    buf.extend(field(7, domain.as_bytes()));
+    buf
+}
+
+// ModelProto.graph = 7, GraphProto: node = 1, name = 2
+fn graph(nodes: &[Vec<u8>]) -> Vec<u8> {
+    let mut graph = field(2, b"g");
+    for n in nodes {
+        graph.extend(field(1, n));
+    }
+    field(7, &graph)
+}
+
+fn parse_bytes(file: &str, bytes: &[u8]) -> Model {
+    parse_file(&format!("{}.onnx", file), bytes).unwrap()
+}
+
+fn parse_file(file: &str, bytes: &[u8]) -> io::Result<Model> {
+    let path = env::temp_dir().join(format!("{}_{}", std::process::id(), file));
+    fs::write(&path, bytes).unwrap();
+    let model = parse_onnx_model(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+    model
+}
+
+fn parse(file: &str, nodes: &[Vec<u8>]) -> Model {
+    parse_bytes(file, &graph(nodes))
+}
+
+fn sorted_edges(model: &Model) -> Vec<(String, String)> {
+    let mut edges: Vec<(String, String)> =
+        model.edges.iter().map(|(s, t)| (s.to_string(), t.to_string())).collect();
+    edges.sort();
+    edges
+}
+
+#[test]
+fn test_unique_names_are_ids() {
+    let model = parse("unique_names", &[
+        node("/conv/Conv", "Conv", &["x"], &["a"], ""),
+        node("/act/Relu", "Relu", &["a"], &["b"], ""),
+        node("custom", "Gelu", &["b", "b"], &["y"], "com.microsoft"),
+    ]);
+
+    assert_eq!(3, model.nodes.len());
+    let gelu = &model.nodes["custom"];
+    assert_eq!(2, gelu.index);
+    assert_eq!("com.microsoft", gelu.domain);
+    assert_eq!("doc of Gelu", gelu.doc_string);
+    assert_eq!("", model.nodes["/conv/Conv"].domain);
+    // a node consuming the same tensor twice still gets a single edge
+    assert_eq!(
+        vec![
+            ("/act/Relu".to_string(), "custom".to_string()),
+            ("/conv/Conv".to_string(), "/act/Relu".to_string()),
+        ],
+        sorted_edges(&model)
+    );
+}
+
+#[test]
+fn test_missing_and_duplicate_names() {
+    let nodes = [
+        node("", "Conv", &["x"], &["a"], ""),
+        node("block", "Relu", &["a"], &["b"], ""),
+        node("block", "Relu", &["b"], &["c"], ""),
+        // collides with the id generated for the first unnamed node
+        node("Conv_0", "Add", &["c", "a"], &["y"], ""),
+    ];
+    let model = parse("duplicate_names", &nodes);
+
+    let mut ids: Vec<&str> = model.nodes.keys().map(|k| k.as_str()).collect();
+    ids.sort();
+    assert_eq!(vec!["Conv_0", "Conv_0_", "block_1", "block_2"], ids);
+    assert_eq!(0, model.nodes["Conv_0_"].index);
+    assert_eq!("Add", model.nodes["Conv_0"].opType);
+    assert_eq!(
+        vec![
+            ("Conv_0_".to_string(), "Conv_0".to_string()),
+            ("Conv_0_".to_string(), "block_1".to_string()),
+            ("block_1".to_string(), "block_2".to_string()),
+            ("block_2".to_string(), "Conv_0".to_string()),
+        ],
+        sorted_edges(&model)
+    );
+
+    // ids are deterministic
+    let again = parse("duplicate_names_again", &nodes);
+    for (id, node) in &model.nodes {
+        assert_eq!(node.index, again.nodes[id].index);
+    }
+}
+
+#[test]
+fn test_model_info() {
+    // ir_version = 1, producer_name = 2, producer_version = 3, domain = 4,
+    // model_version = 5, doc_string = 6, opset_import = 8, metadata_props = 14
+    let mut bytes = int_field(1, 8);
+    bytes.extend(field(2, b"pytorch"));
+    bytes.extend(field(3, b"2.1.0"));
+    bytes.extend(field(4, b"ai.example"));
+    bytes.extend(int_field(5, 3));
+    bytes.extend(field(6, b"a tiny model"));
+    bytes.extend(field(8, &int_field(2, 17)));
+    bytes.extend(field(8, &[field(1, b"com.microsoft"), int_field(2, 1)].concat()));
+    bytes.extend(field(14, &[field(1, b"author"), field(2, b"me")].concat()));
+    bytes.extend(graph(&[node("relu", "Relu", &["x"], &["y"], "")]));
+
+    let info = parse_bytes("model_info", &bytes).info;
+    assert_eq!("onnx", info.format);
+    assert_eq!("8", info.ir_version);
+    assert_eq!("pytorch", info.producer_name);
+    assert_eq!("2.1.0", info.producer_version);
+    assert_eq!("ai.example", info.domain);
+    assert_eq!("3", info.model_version);
+    assert_eq!("a tiny model", info.doc_string);
+    let opsets: Vec<(&str, i64)> =
+        info.opset_import.iter().map(|o| (o.domain.as_str(), o.version)).collect();
+    assert_eq!(vec![("", 17), ("com.microsoft", 1)], opsets);
+    assert_eq!(Some("me"), info.metadata.get("author").map(|v| v.as_str()));
+    assert!(info.attributes.is_empty());
+}
+
+// TensorProto: dims = 1, data_type = 2, name = 8, raw_data = 9,
+// external_data = 13 (key = 1, value = 2), data_location = 14
+fn initializer(name: &str, dims: &[u64], external: &[(&str, &str)]) -> Vec<u8> {
+    let mut buf = vec![];
+    for &d in dims {
+        buf.extend(int_field(1, d));
+    }
+    buf.extend(int_field(2, 1));
+    buf.extend(field(8, name.as_bytes()));
+    if external.is_empty() {
+        buf.extend(field(9, &vec![0; dims.iter().product::<u64>() as usize * 4]));
+    } else {
+        for (key, value) in external {
+            buf.extend(field(13, &[field(1, key.as_bytes()), field(2, value.as_bytes())].concat()));
+        }
+        buf.extend(int_field(14, 1));
+    }
+    buf
+}
+
+#[test]
+fn test_initializers() {
+    let mut graph = field(2, b"g");
+    graph.extend(field(1, &node("mm", "MatMul", &["x", "w", "b"], &["y"], "")));
+    graph.extend(field(5, &initializer("w", &[2, 3], &[])));
+    let external = [("location", "model.onnx.data"), ("offset", "4096"), ("length", "24")];
+    graph.extend(field(5, &initializer("b", &[2, 3], &external)));
+    graph.extend(field(5, &initializer("c", &[2], &[("location", "model.onnx.data")])));
+
+    let model = parse_bytes("initializers", &field(7, &graph));
+    assert_eq!("Float(2, 3)", model.parameters["w"]);
+    assert_eq!("Float(2, 3) @ model.onnx.data[4096..4120]", model.parameters["b"]);
+    assert_eq!("Float(2) @ model.onnx.data[0..]", model.parameters["c"]);
+    assert_eq!("model.onnx.data", model.info.metadata["external_data"]);
+}
+
+// a tensor whose payload is given as its encoded fields
+fn tensor(name: &str, dtype: u64, dims: &[u64], payload: &[u8]) -> Vec<u8> {
+    let mut buf = vec![];
+    for &d in dims {
+        buf.extend(int_field(1, d));
+    }
+    buf.extend(int_field(2, dtype));
+    buf.extend(field(8, name.as_bytes()));
+    buf.extend_from_slice(payload);
+    buf
+}
+
+fn packed_varints(values: &[u64]) -> Vec<u8> {
+    let mut buf = vec![];
+    for &v in values {
+        varint(&mut buf, v);
+    }
+    buf
+}
+
+#[test]
+fn test_preview_tensors() {
+    let floats: Vec<u8> =
+        [1.5f32, f32::NAN, -2.5, f32::INFINITY, 4.0].iter().flat_map(|f| f.to_le_bytes()).collect();
+    let dir = env::temp_dir().join(format!("preview_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // 8 bytes of padding, then two bfloat16
+    fs::write(dir.join("weights.bin"), [vec![0; 8], vec![0x80, 0x3f, 0x00, 0xc0]].concat())
+        .unwrap();
+
+    let mut graph = field(2, b"g");
+    // a Constant with a Reshape target, AttributeProto: name = 1, t = 5, type = 20
+    let target = tensor("", 7, &[3], &field(7, &packed_varints(&[2, u64::MAX, 8])));
+    let value = [field(1, b"value"), field(5, &target), int_field(20, 4)].concat();
+    let constant =
+        [field(2, b"shape"), field(3, b"const"), field(4, b"Constant"), field(5, &value)];
+    graph.extend(field(1, &constant.concat()));
+    // FLOAT = 1 in raw_data, FLOAT16 = 10 in int32_data, BFLOAT16 = 16 external, STRING = 8
+    graph.extend(field(5, &tensor("w", 1, &[5], &field(9, &floats))));
+    graph.extend(field(5, &tensor("h", 10, &[2], &field(5, &packed_varints(&[0x3c00, 0xc000])))));
+    let external = [("location", "weights.bin"), ("offset", "8"), ("length", "4")]
+        .iter()
+        .map(|(k, v)| field(13, &[field(1, k.as_bytes()), field(2, v.as_bytes())].concat()))
+        .chain([int_field(14, 1)])
+        .collect::<Vec<_>>()
+        .concat();
+    graph.extend(field(5, &tensor("e", 16, &[2], &external)));
+    graph.extend(field(5, &tensor("s", 8, &[2], &[field(6, b"a"), field(6, b"b")].concat())));
+
+    let path = dir.join("model.onnx");
+    fs::write(&path, field(7, &graph)).unwrap();
+    let path = path.to_str().unwrap();
+    let initializer = |name: &str| TensorRef::Initializer { name: name.into() };
+
+    let w = preview_onnx_tensor(path, &initializer("w"), 3).unwrap();
+    assert_eq!(("Float", vec![5], 5), (w.dtype.as_str(), w.dims.clone(), w.count));
+    let PreviewValues::Numbers(values) = &w.values else { panic!() };
+    assert_eq!(3, values.len());
+    assert!(values[1].is_nan());
+    assert_eq!((Some(-2.5), Some(4.0), Some(1.0)), (w.min, w.max, w.mean));
+    assert_eq!((1, 1), (w.nan_count, w.inf_count));
+
+    let h = preview_onnx_tensor(path, &initializer("h"), 8).unwrap();
+    assert_eq!(PreviewValues::Numbers(vec![1.0, -2.0]), h.values);
+    let e = preview_onnx_tensor(path, &initializer("e"), 8).unwrap();
+    assert_eq!(PreviewValues::Numbers(vec![1.0, -2.0]), e.values);
+    let s = preview_onnx_tensor(path, &initializer("s"), 1).unwrap();
+    assert_eq!((PreviewValues::Strings(vec!["a".into()]), 2), (s.values, s.count));
+
+    let attribute = TensorRef::Attribute { node: "const".into(), attribute: "value".into() };
+    let target = preview_onnx_tensor(path, &attribute, 8).unwrap();
+    assert_eq!(PreviewValues::Numbers(vec![2.0, -1.0, 8.0]), target.values);
+
+    assert!(preview_onnx_tensor(path, &initializer("missing"), 8).is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// ValueInfoProto: name = 1, type = 2, TypeProto.tensor_type = 1 (elem_type = 1, shape = 2),
+// TensorShapeProto.dim = 1 (dim_value = 1, dim_param = 2)
+fn graph_input(name: &str, elem_type: u64, dims: &[Result<u64, &str>]) -> Vec<u8> {
+    let dims: Vec<u8> = dims
+        .iter()
+        .flat_map(|dim| match dim {
+            Ok(value) => field(1, &int_field(1, *value)),
+            Err(param) => field(1, &field(2, param.as_bytes())),
+        })
+        .collect();
+    let tensor_type = [int_field(1, elem_type), field(2, &dims)].concat();
+    [field(1, name.as_bytes()), field(2, &field(1, &tensor_type))].concat()
+}
+
+#[test]
+fn test_infer_shapes() {
+    let mut graph = field(2, b"g");
+    graph.extend(field(1, &node("mm", "MatMul", &["x", "w"], &["y"], "")));
+    // a Constant holding the Reshape target, AttributeProto: name = 1, ints = 8, type = 20
+    let value = [field(1, b"value_ints"), field(8, &packed_varints(&[0, 3])), int_field(20, 7)];
+    let constant = [field(2, b"target"), field(3, b"const"), field(4, b"Constant")].concat();
+    graph.extend(field(1, &[constant, field(5, &value.concat())].concat()));
+    graph.extend(field(1, &node("reshape", "Reshape", &["y", "target"], &["z"], "")));
+    graph.extend(field(1, &node("custom", "Custom", &["z"], &["out"], "com.example")));
+    graph.extend(field(5, &initializer("w", &[4, 3], &[])));
+    graph.extend(field(11, &graph_input("x", 1, &[Err("batch"), Ok(4)])));
+
+    let mut model = parse_bytes("infer_shapes", &field(7, &graph));
+    let report = infer_shapes(&mut model);
+
+    let batch = Dim::Symbolic("batch".into());
+    assert_eq!(vec![batch.clone(), Dim::Static(3)], model.value_info["y"].dims);
+    assert_eq!(Some(vec![0, 3]), model.value_info["target"].value);
+    let z = model.nodes["reshape"].output_shapes[0].as_ref().unwrap();
+    assert_eq!(("Float", vec![batch, Dim::Static(3)]), (z.dtype.as_str(), z.dims.clone()));
+
+    assert_eq!(3, report.resolved);
+    assert_eq!(1, report.unresolved.len());
+    assert_eq!(
+        ("custom", "Custom"),
+        (report.unresolved[0].node.as_str(), report.unresolved[0].op_type.as_str())
+    );
+
+    let edge = model.typed_edges.iter().find(|e| e.target == "custom").unwrap();
+    assert_eq!(Some(z), edge.shape.as_ref());
+}
+
+// as printed by `print(model)` in Python
+const TEXT_MODEL: &str = r#"ir_version: 8
+producer_name: "pytorch"
+graph {
+  node {
+    input: "x"
+    input: "w"
+    output: "a"
+    name: "mm"
+    op_type: "MatMul"
+    doc_string: "doc of MatMul"
+  }
+  node {
+    input: "a"
+    output: "y"
+    name: "relu"
+    op_type: "Relu"
+    doc_string: "doc of Relu"
+  }
+  name: "g"
+  initializer {
+    dims: 2
+    dims: 3
+    data_type: 1
+    name: "w"
+    raw_data: "\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000\000"
+  }
+}
+opset_import {
+  version: 17
+}
+"#;
+
+#[test]
+fn test_text_format() {
+    let text = parse_file("text.pbtxt", TEXT_MODEL.as_bytes()).unwrap();
+
+    let mut graph = field(2, b"g");
+    graph.extend(field(1, &node("mm", "MatMul", &["x", "w"], &["a"], "")));
+    graph.extend(field(1, &node("relu", "Relu", &["a"], &["y"], "")));
+    graph.extend(field(5, &initializer("w", &[2, 3], &[])));
+    let mut bytes = int_field(1, 8);
+    bytes.extend(field(2, b"pytorch"));
+    bytes.extend(field(7, &graph));
+    bytes.extend(field(8, &int_field(2, 17)));
+    let binary = parse_bytes("text_binary", &bytes);
+
+    let json = |model: &Model| serde_json::to_value(model).unwrap();
+    assert_eq!(json(&binary), json(&text));
+    assert_eq!("Float(2, 3)", text.parameters["w"]);
+
+    // a broken dump is reported, not half read
+    let broken = TEXT_MODEL.replace("op_type: \"Relu\"", "op_type: Relu");
+    let error = parse_file("broken.pbtxt", broken.as_bytes()).unwrap_err();
+    assert_eq!("16:14: expected string", error.to_string());
+}
+
+const ATTR_MODEL: &str = r#"
+graph {
+  node {
+    name: "n"
+    op_type: "If"
+    attribute { name: "undefined" }
+    attribute { name: "f" f: 0.1 type: FLOAT }
+    attribute { name: "ints" ints: [1, 2] type: INTS }
+    attribute { name: "strings" strings: ["a", "b"] type: STRINGS }
+    attribute { name: "t" t { dims: [2, 3] data_type: 1 } type: TENSOR }
+    attribute { name: "sparse" sparse_tensor { dims: [4, 4] } type: SPARSE_TENSOR }
+    attribute { name: "g" g { name: "then" } type: GRAPH }
+    attribute { name: "graphs" graphs { name: "a" } graphs { name: "b" } type: GRAPHS }
+    attribute { name: "one" tensors { dims: 1 data_type: 1 } type: TENSORS }
+    attribute { name: "two" tensors { dims: 1 data_type: 1 } tensors { dims: 2 data_type: 1 } type: TENSORS }
+    attribute { name: "none" type: TENSORS }
+  }
+}
+"#;
+
+#[test]
+fn test_compact_attributes() {
+    let model = parse_file("attrs.pbtxt", ATTR_MODEL.as_bytes()).unwrap();
+    let attributes = &model.nodes["n"].attributes;
+    let compact = |name: &str| {
+        with_attr_format(AttrFormat::Compact, || {
+            with_attr_source(&model.info.format, || serde_json::to_value(&attributes[name]))
+        })
+        .unwrap()
+    };
+    let string_like = |value: &str| json!({"type": "string-like", "value": value});
+
+    // the shapes ONNX attributes were sent in before values were typed
+    assert_eq!(string_like(""), compact("undefined"));
+    assert_eq!(string_like("0.1"), compact("f"));
+    assert_eq!(string_like("1, 2"), compact("ints"));
+    assert_eq!(string_like("a;b"), compact("strings"));
+    assert_eq!(string_like("Float(2, 3)"), compact("t"));
+    assert_eq!(string_like("4, 4"), compact("sparse"));
+    assert_eq!(string_like("Graph(then)"), compact("g"));
+    assert_eq!(string_like("Graphs[a;b]"), compact("graphs"));
+    assert_eq!(string_like("Float(1)"), compact("one"));
+    assert_eq!(json!({"type": "tensor-vals", "value": ["Float(1)", "Float(2)"]}), compact("two"));
+    assert_eq!(json!({"type": "tensor-vals", "value": []}), compact("none"));
+}
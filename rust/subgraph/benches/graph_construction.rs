@@ -0,0 +1,131 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+//! 大规模合成模型上的建图与挖掘基准，输出耗时与峰值内存
+//!
+//! ```text
+//! cargo bench -p subgraph --bench graph_construction -- [建图的块数] [挖掘的块数]
+//! ```
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashMap,
+    fs::File,
+    hint::black_box,
+    io::BufWriter,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use subgraph::{
+    gspan::gspan::GSpan,
+    io::{model_graph::ModelGraph, node::Node},
+    models::{edge::EdgeLabel, graph::Graph},
+    result::{OutType, PatternMode},
+};
+
+// 统计当前与峰值堆内存的分配器
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// 每个块为 Conv -> Mul -> Relu -> Tanh，并带一条 Conv -> Relu 的残差边，块之间首尾相连
+const BLOCK: [&str; 4] = ["Conv", "Mul", "Relu", "Tanh"];
+
+fn synthetic_model(blocks: usize) -> ModelGraph {
+    let mut nodes: HashMap<String, Node> = HashMap::with_capacity(blocks * BLOCK.len());
+    let mut edges: Vec<(String, String)> = Vec::with_capacity(blocks * (BLOCK.len() + 1));
+    let name = |block: usize, i: usize| format!("block_{}/{}_{}", block, BLOCK[i], i);
+    let mut prev: Option<String> = None;
+    for block in 0..blocks {
+        for (i, op_type) in BLOCK.iter().enumerate() {
+            let node_name = name(block, i);
+            let mut input: Vec<String> = prev.iter().map(|p| format!("{}:0", p)).collect();
+            if i == 2 {
+                input.push(format!("{}:0", name(block, 0)));
+                edges.push((name(block, 0), node_name.clone()));
+            }
+            if let Some(p) = &prev {
+                edges.push((p.clone(), node_name.clone()));
+            }
+            nodes.insert(
+                node_name.clone(),
+                Node {
+                    name: node_name.clone(),
+                    opType: op_type.to_string(),
+                    input,
+                    output: vec![format!("{}:0", node_name)],
+                },
+            );
+            prev = Some(node_name);
+        }
+    }
+    ModelGraph { name: String::from("synthetic"), nodes, edges, parameters: HashMap::new() }
+}
+
+// 执行 f 并返回 (结果, 耗时, 相对执行前的峰值内存增量)
+fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration, usize) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let now = Instant::now();
+    let value = f();
+    let elapsed = now.elapsed();
+    (value, elapsed, PEAK.load(Ordering::Relaxed) - base)
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn main() {
+    let counts: Vec<usize> = std::env::args().skip(1).filter_map(|a| a.parse().ok()).collect();
+    let build_blocks = counts.first().copied().unwrap_or(50_000);
+    let mine_blocks = counts.get(1).copied().unwrap_or(5_000);
+
+    let model = synthetic_model(build_blocks);
+    let (graph, elapsed, peak) =
+        measure(|| Graph::graph_from_model_graph(black_box(model), true, EdgeLabel::Port));
+    println!(
+        "construction: {} vertices, {} edges, {:?}, peak {:.2} MiB",
+        graph.vertices.len(),
+        graph.edge_size,
+        elapsed,
+        mib(peak)
+    );
+    drop(graph);
+
+    let graph = Graph::graph_from_model_graph(synthetic_model(mine_blocks), true, EdgeLabel::Nil);
+    let vertices = graph.vertices.len();
+    let ((subgraphs, result), elapsed, peak) = measure(|| {
+        let mut gspan = GSpan::new(vec![graph], 1, 2, 2, BLOCK.len(), true);
+        gspan.set_pattern_mode(PatternMode::All);
+        gspan.run::<BufWriter<File>>(OutType::JSON, None, None)
+    });
+    println!(
+        "mining: {} vertices, {} patterns, {} instances, {:?}, peak {:.2} MiB",
+        vertices,
+        subgraphs,
+        result.get_sum_subgraphs(),
+        elapsed,
+        mib(peak)
+    );
+}
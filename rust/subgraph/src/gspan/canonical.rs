@@ -0,0 +1,293 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use std::collections::HashSet;
+
+use crate::gspan::models::{dfs_code::DFSCode, graph::Graph};
+
+/**
+ * 最小 DFS 编码的一个候选遍历状态
+ * order: 发现序 -> 顶点下标；index: 顶点下标 -> 发现序
+ * used: 边是否已编码；rm_path: 最右路径上顶点的发现序，自根到最右顶点
+ */
+#[derive(Clone)]
+struct State {
+    order: Vec<usize>,
+    index: Vec<Option<usize>>,
+    used: Vec<bool>,
+    rm_path: Vec<usize>,
+}
+
+// (已编码的边, 最右路径上的 (发现序, 顶点下标))
+type StateKey = (Vec<bool>, Vec<(usize, usize)>);
+
+/**
+ * 一步扩展的排序键，按字段顺序比较：
+ * 后向边先于前向边；后向边按目标顶点的发现序升序，前向边按起点在最右路径上从深到浅；
+ * 同一位置再比较边标签、方向（出边优先）与新顶点标签
+ */
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Step<'a> {
+    forward: bool,
+    pos: usize,
+    e_label: &'a str,
+    incoming: bool,
+    to_label: &'a str,
+}
+
+// 一步扩展及其使用的边、边另一端的顶点
+struct Candidate<'a> {
+    step: Step<'a>,
+    from: usize,
+    edge: usize,
+    other: usize,
+}
+
+/**
+ * 计算模式图的最小 DFS 编码（规范编码），同构的模式得到相同的编码
+ * 每一步在全部候选遍历中选取最小的扩展，只保留产生该扩展的遍历继续搜索
+ * 与挖掘过程不同，这里沿边的两个方向遍历，边方向记录在编码的 from/to 中，
+ * 因此任意弱连通的模式都有完整编码，且编码可以由 [`DFSCode::to_graph`] 还原
+ */
+pub fn min_dfs_code(graph: &Graph) -> DFSCode {
+    let edges: Vec<(usize, usize, &str)> = graph
+        .vertices
+        .iter()
+        .flat_map(|v| v.edges.iter())
+        .map(|e| (e.from, e.to, e.e_label.as_str()))
+        .collect();
+    // 每个顶点关联的边：(边下标, 另一端, 是否为入边)
+    let mut adj: Vec<Vec<(usize, usize, bool)>> = vec![vec![]; graph.vertices.len()];
+    for (i, &(from, to, _)) in edges.iter().enumerate() {
+        adj[from].push((i, to, false));
+        if from != to {
+            adj[to].push((i, from, true));
+        }
+    }
+    let label = |v: usize| graph.vertices[v].label.as_str();
+
+    let mut dfs_code = DFSCode::new();
+    let root = edges
+        .iter()
+        .filter(|(from, to, _)| from != to)
+        .flat_map(|&(from, to, e_label)| {
+            [(label(from), e_label, false, label(to)), (label(to), e_label, true, label(from))]
+        })
+        .min();
+    let Some(root) = root else {
+        return dfs_code;
+    };
+
+    let mut states: Vec<State> = vec![];
+    for (i, &(from, to, e_label)) in edges.iter().enumerate() {
+        for (a, b, incoming) in [(from, to, false), (to, from, true)] {
+            if a != b && (label(a), e_label, incoming, label(b)) == root {
+                let mut state = State {
+                    order: vec![a, b],
+                    index: vec![None; graph.vertices.len()],
+                    used: vec![false; edges.len()],
+                    rm_path: vec![0, 1],
+                };
+                state.index[a] = Some(0);
+                state.index[b] = Some(1);
+                state.used[i] = true;
+                states.push(state);
+            }
+        }
+    }
+    push_entry(&mut dfs_code, 0, 1, root.2, root.0, root.1, root.3);
+
+    loop {
+        let candidates: Vec<Vec<Candidate>> =
+            states.iter().map(|s| extensions(s, &edges, &adj, &label)).collect();
+        let Some(min) = candidates.iter().flatten().map(|c| &c.step).min().cloned() else {
+            break;
+        };
+
+        // 所有候选遍历的编码前缀相同，同一发现序上的顶点标签也相同
+        let first = &states[0];
+        let from = if min.forward { usize::MAX - min.pos } else { *first.rm_path.last().unwrap() };
+        let (to, to_label) = if min.forward {
+            (first.order.len(), min.to_label)
+        } else {
+            (min.pos, label(first.order[min.pos]))
+        };
+        let from_label = label(first.order[from]);
+        push_entry(&mut dfs_code, from, to, min.incoming, from_label, min.e_label, to_label);
+
+        // 已编码的边与最右路径都相同的遍历，后续扩展完全一致，只保留一个
+        let mut next: Vec<State> = vec![];
+        let mut seen: HashSet<StateKey> = HashSet::new();
+        for (state, candidates) in states.iter().zip(&candidates) {
+            for c in candidates.iter().filter(|c| c.step == min) {
+                let mut state = state.clone();
+                state.used[c.edge] = true;
+                if state.index[c.other].is_none() {
+                    state.order.push(c.other);
+                    state.index[c.other] = Some(to);
+                    let depth = state.rm_path.iter().position(|&p| p == c.from).unwrap();
+                    state.rm_path.truncate(depth + 1);
+                    state.rm_path.push(to);
+                }
+                let key = state.rm_path.iter().map(|&d| (d, state.order[d])).collect();
+                if seen.insert((state.used.clone(), key)) {
+                    next.push(state);
+                }
+            }
+        }
+        states = next;
+    }
+    dfs_code
+}
+
+// 按边的实际方向写入编码：入边时交换 from/to
+fn push_entry(
+    dfs_code: &mut DFSCode,
+    from: usize,
+    to: usize,
+    incoming: bool,
+    from_label: &str,
+    e_label: &str,
+    to_label: &str,
+) {
+    let (from, to, from_label, to_label) =
+        if incoming { (to, from, to_label, from_label) } else { (from, to, from_label, to_label) };
+    dfs_code.push(from, to, from_label.to_string(), e_label.to_string(), to_label.to_string());
+}
+
+// 枚举一个遍历状态的全部最右扩展
+fn extensions<'a>(
+    state: &State,
+    edges: &[(usize, usize, &'a str)],
+    adj: &[Vec<(usize, usize, bool)>],
+    label: &impl Fn(usize) -> &'a str,
+) -> Vec<Candidate<'a>> {
+    let mut result = vec![];
+    let rightmost = *state.rm_path.last().unwrap();
+    // 后向边：最右顶点到最右路径上的顶点（含自环）
+    for &(edge, other, incoming) in &adj[state.order[rightmost]] {
+        if state.used[edge] {
+            continue;
+        }
+        if let Some(to) = state.index[other].filter(|to| state.rm_path.contains(to)) {
+            result.push(Candidate {
+                step: Step {
+                    forward: false,
+                    pos: to,
+                    e_label: edges[edge].2,
+                    incoming,
+                    to_label: "",
+                },
+                from: rightmost,
+                edge,
+                other,
+            });
+        }
+    }
+    // 前向边：最右路径上的顶点到未发现的顶点
+    for &from in &state.rm_path {
+        for &(edge, other, incoming) in &adj[state.order[from]] {
+            if state.used[edge] || state.index[other].is_some() {
+                continue;
+            }
+            result.push(Candidate {
+                step: Step {
+                    forward: true,
+                    pos: usize::MAX - from,
+                    e_label: edges[edge].2,
+                    incoming,
+                    to_label: label(other),
+                },
+                from,
+                edge,
+                other,
+            });
+        }
+    }
+    result
+}
+
+/**
+ * 规范编码的文本形式，每条边 `from to from_label e_label to_label`，以 `; ` 分隔
+ * 可以直接作为 [`crate::query::QueryPattern::DfsCode`] 使用
+ */
+pub fn dfs_code_text(dfs_code: &DFSCode) -> String {
+    dfs_code
+        .dfs_vec
+        .iter()
+        .map(|d| format!("{} {} {} {} {}", d.from, d.to, d.from_label, d.e_label, d.to_label))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+/**
+ * 模式的稳定 id：规范编码文本的 64 位 FNV-1a 哈希（16 位十六进制）
+ * 不依赖标准库哈希的实现，跨运行、跨版本保持一致
+ */
+pub fn pattern_hash(graph: &Graph) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let hash = dfs_code_text(&min_dfs_code(graph))
+        .bytes()
+        .fold(OFFSET, |hash, b| (hash ^ b as u64).wrapping_mul(PRIME));
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gspan::models::graph::fixture::build_graph, query::parse_dfs_code};
+
+    #[test]
+    fn test_isomorphic_patterns_share_hash() {
+        // 残差块：Conv -> Relu -> Add, Conv -> Add，顶点名与插入顺序不同
+        let a = build_graph(
+            &[("c", "Conv"), ("r", "Relu"), ("a", "Add")],
+            &[("c", "r", "0:0"), ("r", "a", "0:0"), ("c", "a", "0:1")],
+        );
+        let b = build_graph(
+            &[("x", "Add"), ("y", "Conv"), ("z", "Relu")],
+            &[("y", "x", "0:1"), ("z", "x", "0:0"), ("y", "z", "0:0")],
+        );
+        assert_eq!(dfs_code_text(&min_dfs_code(&a)), dfs_code_text(&min_dfs_code(&b)));
+        assert_eq!(pattern_hash(&a), pattern_hash(&b));
+        assert_eq!(16, pattern_hash(&a).len());
+    }
+
+    #[test]
+    fn test_direction_and_labels_change_hash() {
+        let forward = build_graph(&[("a", "Conv"), ("b", "Relu")], &[("a", "b", "<NIL>")]);
+        let backward = build_graph(&[("a", "Conv"), ("b", "Relu")], &[("b", "a", "<NIL>")]);
+        let port = build_graph(&[("a", "Conv"), ("b", "Relu")], &[("a", "b", "0:1")]);
+        assert_ne!(pattern_hash(&forward), pattern_hash(&backward));
+        assert_ne!(pattern_hash(&forward), pattern_hash(&port));
+        assert_eq!("0 1 Conv <NIL> Relu", dfs_code_text(&min_dfs_code(&forward)));
+        assert_eq!("1 0 Relu <NIL> Conv", dfs_code_text(&min_dfs_code(&backward)));
+    }
+
+    #[test]
+    fn test_code_round_trip() {
+        let graph = build_graph(
+            &[("ln", "LayerNorm"), ("q", "MatMul"), ("k", "MatMul"), ("s", "Softmax")],
+            &[("ln", "q", "<NIL>"), ("ln", "k", "<NIL>"), ("q", "s", "<NIL>"), ("k", "s", "<NIL>")],
+        );
+        let code = min_dfs_code(&graph);
+        assert_eq!(graph.edge_size, code.dfs_vec.len());
+        let text = dfs_code_text(&code);
+        let again = parse_dfs_code(&text).unwrap().to_graph(0, true);
+        assert_eq!(text, dfs_code_text(&min_dfs_code(&again)));
+    }
+
+    #[test]
+    fn test_symmetric_pattern() {
+        // 中心顶点连接多个相同标签的叶子，候选遍历需要去重
+        let leaves: Vec<String> = (0..12).map(|i| format!("leaf_{}", i)).collect();
+        let mut vertices = vec![("hub", "Split")];
+        vertices.extend(leaves.iter().map(|l| (l.as_str(), "Relu")));
+        let edges: Vec<(&str, &str, &str)> =
+            leaves.iter().map(|l| ("hub", l.as_str(), "<NIL>")).collect();
+        let code = min_dfs_code(&build_graph(&vertices, &edges));
+        assert_eq!(12, code.dfs_vec.len());
+        assert!(code.dfs_vec.iter().all(|d| d.from == 1));
+    }
+}
@@ -5,16 +5,17 @@ use std::{
     collections::BTreeMap,
     fs::File,
     io::{BufWriter, Write},
+    sync::Arc,
     usize,
 };
 
 use rustc_hash::FxHashSet;
 
-use super::result::{OutSource, OutType};
+use super::result::{OutSource, OutType, PatternMode};
 use crate::gspan::{
     misc::{
-        get_backward, get_forward_edges, get_forward_pure, get_forward_rm_path, inner_support,
-        support,
+        SupportMode, get_backward, get_forward_edges, get_forward_pure, get_forward_rm_path,
+        inner_support_of, select_instances, select_instances_checked, support,
     },
     models::{
         dfs_code::DFSCode, edge::Edge, graph::Graph, history::History, projected::Projected,
@@ -24,12 +25,14 @@ use crate::gspan::{
 };
 
 pub struct GSpan {
-    trans: Vec<Graph>,    // 图列表
-    min_sup: usize,       // Min support, 相同结果在不同图中出现的最小次数
-    inner_min_sup: usize, // 相同结构在同一图中出现的最小次数
-    max_pat_min: usize,   // Minimum number of patterns(vertices) to be output
-    max_pat_max: usize,   // Maximum number of patterns(vertices) to be output
-    directed: bool,       // 是否有向图
+    trans: Arc<Vec<Graph>>, // 图列表，与结果共享以便输出时还原顶点名
+    min_sup: usize,         // Min support, 相同结果在不同图中出现的最小次数
+    inner_min_sup: usize,   // 相同结构在同一图中出现的最小次数
+    max_pat_min: usize,     // Minimum number of patterns(vertices) to be output
+    max_pat_max: usize,     // Maximum number of patterns(vertices) to be output
+    directed: bool,         // 是否有向图
+    pattern_mode: PatternMode,
+    support_mode: SupportMode,
 }
 
 impl GSpan {
@@ -41,7 +44,24 @@ impl GSpan {
         max_pat_max: usize,
         directed: bool,
     ) -> GSpan {
-        GSpan { trans: graphs, min_sup, inner_min_sup, max_pat_min, max_pat_max, directed }
+        GSpan {
+            trans: Arc::new(graphs),
+            min_sup,
+            inner_min_sup,
+            max_pat_min,
+            max_pat_max,
+            directed,
+            pattern_mode: PatternMode::default(),
+            support_mode: SupportMode::default(),
+        }
+    }
+
+    pub fn set_pattern_mode(&mut self, pattern_mode: PatternMode) {
+        self.pattern_mode = pattern_mode;
+    }
+
+    pub fn set_support_mode(&mut self, support_mode: SupportMode) {
+        self.support_mode = support_mode;
     }
 
     pub fn run<W: Write + Send + Sync + 'static>(
@@ -59,6 +79,9 @@ impl GSpan {
             self.max_pat_max,
             out_type,
         );
+        result.set_pattern_mode(self.pattern_mode);
+        result.set_support_mode(self.support_mode);
+        result.set_graphs(Arc::clone(&self.trans));
         if let Some(out_source) = out_source {
             match out_source {
                 OutSource::Path(path) =>
@@ -95,7 +118,7 @@ impl GSpan {
         // root: [from_label][e_label][to_label] -> Projected
         let mut root: BTreeMap<String, BTreeMap<String, BTreeMap<String, Projected>>> =
             BTreeMap::new();
-        for g in &self.trans {
+        for g in self.trans.iter() {
             for from in &g.vertices {
                 let edges: Vec<&Edge> = get_forward_edges(&g, from);
                 if edges.is_empty() {
@@ -106,7 +129,7 @@ impl GSpan {
                     let root_1 = root.entry(key_1).or_default();
                     let key_2 = edge.e_label.clone();
                     let root_2 = root_1.entry(key_2).or_default();
-                    let key_3 = g.find_vertex(&edge.to).unwrap().label.clone();
+                    let key_3 = g.vertices[edge.to].label.clone();
                     let root_3 = root_2.entry(key_3).or_insert(Projected::new());
                     root_3.push(g.id, edge, None);
                 }
@@ -134,6 +157,7 @@ impl GSpan {
                 }
             }
         }
+        result.finish();
         (next_gid, result)
     }
 
@@ -142,7 +166,7 @@ impl GSpan {
         single_vertex_graph_map: &mut BTreeMap<usize, BTreeMap<String, (FxHashSet<String>, usize)>>,
         single_vertex_label_frequent_map: &mut BTreeMap<String, usize>,
     ) {
-        for graph in &self.trans {
+        for graph in self.trans.iter() {
             for vertex in &graph.vertices {
                 let key = &vertex.label;
                 let d = single_vertex_graph_map.entry(graph.id).or_default();
@@ -224,7 +248,7 @@ impl GSpan {
         process: &mut Option<W>,
         result: &mut MaxDFSCodeGraphResult,
     ) {
-        if self.should_stop_mining(projected, dfs_code, next_gid, process) {
+        if self.should_stop_mining(projected, dfs_code, next_gid, process, result) {
             return;
         }
 
@@ -322,8 +346,7 @@ impl GSpan {
                     let root_1 = new_fwd_root.entry(max_to_code).or_default();
                     let key_2: &String = &it.e_label;
                     let root_2 = root_1.entry(key_2.to_string()).or_default();
-                    let key_3: &String =
-                        &self.trans.get(gid).unwrap().find_vertex(&it.to).unwrap().label;
+                    let key_3: &String = &self.trans.get(gid).unwrap().vertices[it.to].label;
                     let root_3 = root_2.entry(key_3.to_string()).or_insert(Projected::new());
                     root_3.push(gid, it, Some(&a_projected));
                 }
@@ -344,8 +367,7 @@ impl GSpan {
                     let root_1 = new_fwd_root.entry(key_1).or_default();
                     let key_2: &String = &it.e_label;
                     let root_2 = root_1.entry(key_2.to_string()).or_default();
-                    let key_3: &String =
-                        &self.trans.get(gid).unwrap().find_vertex(&it.to).unwrap().label;
+                    let key_3: &String = &self.trans.get(gid).unwrap().vertices[it.to].label;
                     let root_3 = root_2.entry(key_3.to_string()).or_insert(Projected::new());
                     root_3.push(gid, it, Some(&a_projected));
                 }
@@ -360,6 +382,7 @@ impl GSpan {
         dfs_code: &mut DFSCode,
         next_gid: &mut usize,
         process: &mut Option<W>,
+        result: &mut MaxDFSCodeGraphResult,
     ) -> bool {
         // Check if the pattern is frequent enough, between graphs
         let sup: usize = support(projected);
@@ -367,8 +390,15 @@ impl GSpan {
             return true;
         }
         // Check if the pattern is frequent enough, within graphs
-        let (min_inner_sup, max_inner_sup) = inner_support(projected);
-        if max_inner_sup < self.inner_min_sup {
+        // 贪心的最大独立集不满足反单调性，此时按 Distinct 计数剪枝，贪心解只决定是否输出
+        let (selected, exact) = select_instances_checked(projected, self.support_mode);
+        let (min_inner_sup, max_inner_sup) = inner_support_of(projected, &selected);
+        let bound = if exact {
+            max_inner_sup
+        } else {
+            inner_support_of(projected, &select_instances(projected, SupportMode::Distinct)).1
+        };
+        if bound < self.inner_min_sup {
             return true;
         }
         // Check if the pattern is not min
@@ -386,6 +416,11 @@ impl GSpan {
             return true;
         }
 
+        // 贪心解低于阈值时不输出该模式，但仍继续扩展
+        if max_inner_sup < self.inner_min_sup {
+            return false;
+        }
+
         // Output the frequent substructures
         if let Some(process) = process {
             let gid = next_gid.clone();
@@ -401,6 +436,10 @@ impl GSpan {
         }
         *next_gid += 1;
 
+        if self.pattern_mode.records_all() {
+            result.add_value(dfs_code, projected);
+        }
+
         false
     }
 }
@@ -640,7 +679,7 @@ impl GSpan {
                 for it in edges {
                     let key_1 = it.e_label.clone();
                     let root_1 = root.entry(key_1).or_default();
-                    let key_2 = graph_is_min.vertex_name_label_map.get(&it.to).unwrap();
+                    let key_2 = &graph_is_min.vertices[it.to].label;
                     let root_2 = root_1.entry(key_2.to_string()).or_insert(Projected::new());
                     root_2.push(0, it, Some(cur));
                 }
@@ -714,13 +753,17 @@ impl GSpan {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gspan::{
+        misc::{EXACT_MIS_LIMIT, is_sub_pattern},
+        models::edge::EdgeLabel,
+    };
 
     #[test]
     fn test_run_single_graph() {
         // JSON 文件路径
         let filename = r#"tests\json\single-graph.json"#;
 
-        match Graph::graph_from_file(&filename, true) {
+        match Graph::graph_from_file(&filename, true, EdgeLabel::Nil) {
             Ok(graph) => {
                 println!("{}", graph.to_str_repr(None));
 
@@ -747,7 +790,7 @@ mod tests {
         // JSON 文件路径
         let filename = r#"tests\json\lenet.json"#;
 
-        match Graph::graph_from_file(&filename, true) {
+        match Graph::graph_from_file(&filename, true, EdgeLabel::Nil) {
             Ok(graph) => {
                 println!("{}", graph.to_str_repr(None));
 
@@ -771,4 +814,255 @@ mod tests {
             }
         }
     }
+
+    // 两棵出树：a1 -> (b1 -> c1, b2 -> c2)，a2 -> b3 -> (c3, d)
+    fn tree_graph() -> Graph {
+        let mut g = Graph::new(0, true);
+        for (name, label) in [
+            ("a1", "A"),
+            ("b1", "B"),
+            ("c1", "C"),
+            ("b2", "B"),
+            ("c2", "C"),
+            ("a2", "A"),
+            ("b3", "B"),
+            ("c3", "C"),
+            ("d", "D"),
+        ] {
+            g.insert_vertex(name, label);
+        }
+        g.build_edge(
+            [("a1", "b1"), ("b1", "c1"), ("a1", "b2"), ("b2", "c2"), ("a2", "b3"), ("b3", "c3")]
+                .into_iter()
+                .chain([("b3", "d")])
+                .map(|(f, t)| (f.to_string(), t.to_string(), None))
+                .collect(),
+        );
+        g
+    }
+
+    // 暴力枚举所有连通的边子集，按同构类分组，统计不同顶点集合的个数作为图内支持度
+    fn brute_force_patterns(
+        g: &Graph,
+        min_vertices: usize,
+        max_vertices: usize,
+    ) -> Vec<(Graph, usize)> {
+        let edges: Vec<&Edge> = g.vertices.iter().flat_map(|v| v.edges.iter()).collect();
+        let mut classes: Vec<(Graph, Vec<FxHashSet<String>>)> = vec![];
+        for mask in 1usize..(1 << edges.len()) {
+            let subset: Vec<&Edge> =
+                (0..edges.len()).filter(|i| mask & (1 << i) != 0).map(|i| edges[i]).collect();
+            let mut pattern = Graph::new(0, true);
+            for e in &subset {
+                for (id, label) in [(e.from, &e.from_label), (e.to, &e.to_label)] {
+                    let name = g.vertex_name(id);
+                    if !pattern.vertex_index.contains_key(name) {
+                        pattern.insert_vertex(name, label);
+                    }
+                }
+            }
+            if pattern.vertices.len() < min_vertices || pattern.vertices.len() > max_vertices {
+                continue;
+            }
+            pattern.build_edge(
+                subset
+                    .iter()
+                    .map(|e| {
+                        let (from, to) = (g.vertex_name(e.from), g.vertex_name(e.to));
+                        (from.to_string(), to.to_string(), Some(e.e_label.clone()))
+                    })
+                    .collect(),
+            );
+            if !is_connected(&pattern) {
+                continue;
+            }
+            let names: FxHashSet<String> =
+                pattern.vertices.iter().map(|v| v.name.clone()).collect();
+            match classes.iter_mut().find(|(c, _)| is_isomorphic(c, &pattern)) {
+                Some((_, sets)) =>
+                    if !sets.contains(&names) {
+                        sets.push(names)
+                    },
+                None => classes.push((pattern, vec![names])),
+            }
+        }
+        classes.into_iter().map(|(c, sets)| (c, sets.len())).collect()
+    }
+
+    fn is_connected(g: &Graph) -> bool {
+        let mut seen: FxHashSet<usize> = FxHashSet::default();
+        let mut stack = vec![0];
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            for v in &g.vertices {
+                for e in &v.edges {
+                    if e.from == id {
+                        stack.push(e.to);
+                    } else if e.to == id {
+                        stack.push(e.from);
+                    }
+                }
+            }
+        }
+        seen.len() == g.vertices.len()
+    }
+
+    fn is_isomorphic(a: &Graph, b: &Graph) -> bool {
+        a.vertices.len() == b.vertices.len() && a.edge_size == b.edge_size && is_sub_pattern(a, b)
+    }
+
+    // 在暴力结果上按定义计算 Closed / Maximal
+    fn brute_force_filter(patterns: &[(Graph, usize)], mode: PatternMode) -> Vec<&(Graph, usize)> {
+        patterns
+            .iter()
+            .filter(|(p, p_sup)| {
+                !patterns.iter().any(|(q, q_sup)| {
+                    q.edge_size > p.edge_size
+                        && (mode == PatternMode::Maximal || q_sup == p_sup)
+                        && is_sub_pattern(p, q)
+                })
+            })
+            .collect()
+    }
+
+    fn mine(mode: PatternMode) -> MaxDFSCodeGraphResult {
+        let mut gspan = GSpan::new(vec![tree_graph()], 1, 2, 2, 10, true);
+        gspan.set_pattern_mode(mode);
+        let (_, result) = gspan.run::<BufWriter<File>>(OutType::JSON, None, None);
+        result
+    }
+
+    fn assert_same_patterns(expected: &[&(Graph, usize)], result: &MaxDFSCodeGraphResult) {
+        assert_eq!(expected.len(), result.get_value_len());
+        for (dfs_code, instances) in result.get_values() {
+            let pattern = dfs_code.to_graph(0, true);
+            let inner_sup = instances
+                .iter()
+                .fold(Vec::<&FxHashSet<(usize, usize)>>::new(), |mut acc, set| {
+                    if !acc.contains(&set) {
+                        acc.push(set);
+                    }
+                    acc
+                })
+                .len();
+            assert!(
+                expected.iter().any(|(p, sup)| *sup == inner_sup && is_isomorphic(p, &pattern)),
+                "unexpected pattern:\n{}",
+                pattern.to_str_repr(None)
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_patterns_match_brute_force() {
+        let patterns: Vec<(Graph, usize)> = brute_force_patterns(&tree_graph(), 2, 10)
+            .into_iter()
+            .filter(|(_, sup)| *sup >= 2)
+            .collect();
+        let expected: Vec<&(Graph, usize)> = patterns.iter().collect();
+        assert_eq!(4, expected.len());
+        assert_same_patterns(&expected, &mine(PatternMode::All));
+    }
+
+    #[test]
+    fn test_closed_patterns_match_brute_force() {
+        let patterns: Vec<(Graph, usize)> = brute_force_patterns(&tree_graph(), 2, 10)
+            .into_iter()
+            .filter(|(_, sup)| *sup >= 2)
+            .collect();
+        let expected = brute_force_filter(&patterns, PatternMode::Closed);
+        assert_eq!(2, expected.len());
+        assert_same_patterns(&expected, &mine(PatternMode::Closed));
+    }
+
+    #[test]
+    fn test_maximal_patterns_match_brute_force() {
+        let patterns: Vec<(Graph, usize)> = brute_force_patterns(&tree_graph(), 2, 10)
+            .into_iter()
+            .filter(|(_, sup)| *sup >= 2)
+            .collect();
+        let expected = brute_force_filter(&patterns, PatternMode::Maximal);
+        assert_eq!(1, expected.len());
+        assert_same_patterns(&expected, &mine(PatternMode::Maximal));
+    }
+
+    // 由相同层组成的链 n0 -> n1 -> ... -> n5，相邻窗口的实例相互重叠
+    fn chain_graph() -> Graph {
+        let mut g = Graph::new(0, true);
+        for i in 0..6 {
+            g.insert_vertex(&format!("n{}", i), "Layer");
+        }
+        g.build_edge((0..5).map(|i| (format!("n{}", i), format!("n{}", i + 1), None)).collect());
+        g
+    }
+
+    // 按模式的边数返回实例数
+    fn mine_chain(support_mode: SupportMode) -> Vec<(usize, usize)> {
+        let mut gspan = GSpan::new(vec![chain_graph()], 1, 2, 2, 3, true);
+        gspan.set_pattern_mode(PatternMode::All);
+        gspan.set_support_mode(support_mode);
+        let (_, result) = gspan.run::<BufWriter<File>>(OutType::JSON, None, None);
+        let mut counts: Vec<(usize, usize)> = result
+            .get_values()
+            .iter()
+            .map(|(code, sets)| (code.dfs_vec.len(), sets.len()))
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    #[test]
+    fn test_distinct_support_counts_overlapping_instances() {
+        assert_eq!(vec![(1, 5), (2, 4)], mine_chain(SupportMode::Distinct));
+    }
+
+    #[test]
+    fn test_mis_support_selects_non_overlapping_instances() {
+        assert_eq!(vec![(1, 3), (2, 2)], mine_chain(SupportMode::GreedyMis));
+        assert_eq!(vec![(1, 3), (2, 2)], mine_chain(SupportMode::ExactMis));
+
+        let mut gspan = GSpan::new(vec![chain_graph()], 1, 2, 2, 3, true);
+        gspan.set_pattern_mode(PatternMode::All);
+        gspan.set_support_mode(SupportMode::ExactMis);
+        let (_, result) = gspan.run::<BufWriter<File>>(OutType::JSON, None, None);
+        for (_, sets) in result.get_values() {
+            for (i, a) in sets.iter().enumerate() {
+                for b in &sets[i + 1..] {
+                    assert!(a.is_disjoint(b), "instances overlap: {:?} {:?}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact_mis_reports_fallback() {
+        let mine = |len: usize| {
+            let mut g = Graph::new(0, true);
+            for i in 0..len {
+                g.insert_vertex(&format!("n{}", i), "Layer");
+            }
+            g.build_edge(
+                (0..len - 1).map(|i| (format!("n{}", i), format!("n{}", i + 1), None)).collect(),
+            );
+            let mut gspan = GSpan::new(vec![g], 1, 2, 2, 2, true);
+            gspan.set_pattern_mode(PatternMode::All);
+            gspan.set_support_mode(SupportMode::ExactMis);
+            gspan.run::<BufWriter<File>>(OutType::JSON, None, None).1
+        };
+        assert!(!mine(6).is_mis_fallback());
+        // 单边模式有 EXACT_MIS_LIMIT + 5 个实例，只能贪心求解
+        assert!(mine(EXACT_MIS_LIMIT + 6).is_mis_fallback());
+    }
+
+    #[test]
+    fn test_mis_support_prunes_below_threshold() {
+        // 3 条边的窗口只有 1 个不重叠实例，低于图内最小支持度 2
+        let mut gspan = GSpan::new(vec![chain_graph()], 1, 2, 2, 10, true);
+        gspan.set_pattern_mode(PatternMode::All);
+        gspan.set_support_mode(SupportMode::GreedyMis);
+        let (_, result) = gspan.run::<BufWriter<File>>(OutType::JSON, None, None);
+        assert!(result.get_values().iter().all(|(code, _)| code.dfs_vec.len() <= 2));
+    }
 }
@@ -4,12 +4,80 @@
 use std::collections::{BTreeMap, HashMap};
 
 use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
 
 use super::models::dfs_code::DFSCode;
 use crate::gspan::models::{
     edge::Edge, graph::Graph, history::History, projected::Projected, vertex::Vertex,
 };
 
+// 判断模式图 sub 是否为模式图 sup 的子图（保持顶点标签、边标签与边方向）
+// 模式图规模很小，直接按 sub 的顶点插入顺序回溯匹配；DFSCode 生成的顶点顺序保证了已匹配部分是连通的
+pub fn is_sub_pattern(sub: &Graph, sup: &Graph) -> bool {
+    if sub.vertices.len() > sup.vertices.len() || sub.edge_size > sup.edge_size {
+        return false;
+    }
+    // 每个 sub 顶点关联的边：(另一端的索引, 是否为出边, e_label)
+    let mut sub_adj: Vec<Vec<(usize, bool, &str)>> = vec![vec![]; sub.vertices.len()];
+    for v in &sub.vertices {
+        for e in &v.edges {
+            sub_adj[e.from].push((e.to, true, &e.e_label));
+            sub_adj[e.to].push((e.from, false, &e.e_label));
+        }
+    }
+    let sup_edges: FxHashSet<(usize, usize, &str)> = sup
+        .vertices
+        .iter()
+        .flat_map(|v| v.edges.iter())
+        .map(|e| (e.from, e.to, e.e_label.as_str()))
+        .collect();
+
+    let mut mapping: Vec<Option<usize>> = vec![None; sub.vertices.len()];
+    let mut used = vec![false; sup.vertices.len()];
+    match_sub_vertex(0, sub, sup, &sub_adj, &sup_edges, &mut mapping, &mut used)
+}
+
+fn match_sub_vertex(
+    i: usize,
+    sub: &Graph,
+    sup: &Graph,
+    sub_adj: &[Vec<(usize, bool, &str)>],
+    sup_edges: &FxHashSet<(usize, usize, &str)>,
+    mapping: &mut [Option<usize>],
+    used: &mut [bool],
+) -> bool {
+    if i == sub.vertices.len() {
+        return true;
+    }
+    for j in 0..sup.vertices.len() {
+        if used[j] || sup.vertices[j].label != sub.vertices[i].label {
+            continue;
+        }
+        // 与已匹配顶点之间的边必须在 sup 中同样存在
+        let consistent = sub_adj[i].iter().all(|&(other, outgoing, e_label)| {
+            let other = if other == i { Some(j) } else { mapping[other] };
+            match other {
+                None => true,
+                Some(k) => {
+                    let (from, to) = if outgoing { (j, k) } else { (k, j) };
+                    sup_edges.contains(&(from, to, e_label))
+                }
+            }
+        });
+        if !consistent {
+            continue;
+        }
+        mapping[i] = Some(j);
+        used[j] = true;
+        if match_sub_vertex(i + 1, sub, sup, sub_adj, sup_edges, mapping, used) {
+            return true;
+        }
+        mapping[i] = None;
+        used[j] = false;
+    }
+    false
+}
+
 // 计算某个子图模式在图集合中的支持度
 // 计算 projected 内的 PrevDFS 的实值个数，即 projected 中所表示的子图模式出现过的不同的图的数量
 pub fn support(projected: &Projected) -> usize {
@@ -26,23 +94,44 @@ pub fn support(projected: &Projected) -> usize {
     size
 }
 
+/// 图内支持度的计算方式
+/// - `Distinct`: 去掉节点完全相同的投影后计数，相互重叠的实例都会被计入
+/// - `GreedyMis`: 在实例重叠图上贪心求最大独立集，只统计互不重叠的实例
+/// - `ExactMis`: 单个图内的实例数不超过 [`EXACT_MIS_LIMIT`] 时精确求解最大独立集，否则退化为贪心
+///
+/// 贪心解只是最大独立集的下界，超模式的贪心解可能反而更大，不能用于剪枝：
+/// 贪心解（`GreedyMis`，以及超过上限后退化的 `ExactMis`）只用于输出的支持度和结果筛选，
+/// 剪枝仍按 `Distinct` 的计数进行，见 [`select_instances_checked`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupportMode {
+    #[default]
+    Distinct,
+    GreedyMis,
+    ExactMis,
+}
+
+impl SupportMode {
+    pub fn is_mis(&self) -> bool {
+        *self != SupportMode::Distinct
+    }
+}
+
+pub const EXACT_MIS_LIMIT: usize = 64;
+
 // 计算某个子图模式在单个图中的支持度
 // 计算 projected 内的 PrevDFS 的实值个数，即 projected 中所表示的子图模式出现过的相同图的数量
 // CORE：特殊处理，要去掉节点完全相同的投影情况
 pub fn inner_support(projected: &Projected) -> (usize, usize) {
-    let mut count_map = HashMap::<usize, usize>::new();
-
-    let mut unify_vertices_list: Vec<FxHashSet<String>> = vec![];
-    for cur in projected.projections.iter() {
-        let set: FxHashSet<String> =
-            cur.get_vertex_names().iter().map(|f| format!("{}/{}", &f.0, &f.1)).collect();
-        // 如果存在
-        if unify_vertices_list.contains(&set) {
-            continue;
-        }
+    let selected = select_instances(projected, SupportMode::Distinct);
+    inner_support_of(projected, &selected)
+}
 
-        unify_vertices_list.push(set);
-        count_map.entry(cur.gid).and_modify(|v| *v += 1).or_insert(1);
+// 按选中的投影下标统计每个图内的实例数，返回 (最小值, 最大值)
+pub fn inner_support_of(projected: &Projected, selected: &[usize]) -> (usize, usize) {
+    let mut count_map = HashMap::<usize, usize>::new();
+    for &i in selected {
+        count_map.entry(projected.projections[i].gid).and_modify(|v| *v += 1).or_insert(1);
     }
 
     let mut min = usize::MAX;
@@ -55,11 +144,138 @@ pub fn inner_support(projected: &Projected) -> (usize, usize) {
     (min, max)
 }
 
+// 按支持度计算方式选出计入图内支持度的投影下标（升序）
+pub fn select_instances(projected: &Projected, mode: SupportMode) -> Vec<usize> {
+    select_instances_checked(projected, mode).0
+}
+
+/**
+ * 同 [`select_instances`]，另外返回选择是否精确：
+ * `Distinct` 与未超过 [`EXACT_MIS_LIMIT`] 的 `ExactMis` 是精确的，可以直接用于剪枝，
+ * 任一图内使用了贪心解时不是
+ */
+pub fn select_instances_checked(projected: &Projected, mode: SupportMode) -> (Vec<usize>, bool) {
+    // 去掉节点完全相同的投影
+    let mut unify_vertices_list: FxHashSet<Vec<(usize, usize)>> = FxHashSet::default();
+    let mut distinct: Vec<(usize, FxHashSet<(usize, usize)>)> = vec![];
+    for (i, cur) in projected.projections.iter().enumerate() {
+        let set = cur.get_vertex_ids();
+        let mut key: Vec<(usize, usize)> = set.iter().copied().collect();
+        key.sort_unstable();
+        if unify_vertices_list.insert(key) {
+            distinct.push((i, set));
+        }
+    }
+    if !mode.is_mis() {
+        return (distinct.into_iter().map(|(i, _)| i).collect(), true);
+    }
+
+    // 不同图之间的实例不会重叠，按图分别求解
+    let mut by_gid: BTreeMap<usize, Vec<(usize, FxHashSet<(usize, usize)>)>> = BTreeMap::new();
+    for (i, set) in distinct {
+        by_gid.entry(projected.projections[i].gid).or_default().push((i, set));
+    }
+
+    let mut selected: Vec<usize> = vec![];
+    let mut exact = true;
+    for (_, instances) in by_gid {
+        let adj = build_overlap_graph(&instances);
+        let chosen = if mode == SupportMode::ExactMis && instances.len() <= EXACT_MIS_LIMIT {
+            exact_mis(&adj)
+        } else {
+            exact = false;
+            greedy_mis(&adj)
+        };
+        selected.extend(chosen.into_iter().map(|k| instances[k].0));
+    }
+    selected.sort_unstable();
+    (selected, exact)
+}
+
+// 实例重叠图：共享任一节点的两个实例之间连边
+fn build_overlap_graph(instances: &[(usize, FxHashSet<(usize, usize)>)]) -> Vec<Vec<usize>> {
+    let mut vertex_instances: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (k, (_, set)) in instances.iter().enumerate() {
+        for id in set {
+            vertex_instances.entry(*id).or_default().push(k);
+        }
+    }
+    let mut adj: Vec<FxHashSet<usize>> = vec![FxHashSet::default(); instances.len()];
+    for ks in vertex_instances.values() {
+        for &a in ks {
+            for &b in ks {
+                if a != b {
+                    adj[a].insert(b);
+                }
+            }
+        }
+    }
+    adj.into_iter()
+        .map(|set| {
+            let mut list: Vec<usize> = set.into_iter().collect();
+            list.sort_unstable();
+            list
+        })
+        .collect()
+}
+
+// 贪心：每次选择剩余图中度数最小的实例，并删除与其重叠的实例
+fn greedy_mis(adj: &[Vec<usize>]) -> Vec<usize> {
+    let mut alive = vec![true; adj.len()];
+    let mut degree: Vec<usize> = adj.iter().map(|a| a.len()).collect();
+    let mut chosen = vec![];
+    while let Some(v) = (0..adj.len()).filter(|&v| alive[v]).min_by_key(|&v| (degree[v], v)) {
+        chosen.push(v);
+        alive[v] = false;
+        for &u in &adj[v] {
+            if alive[u] {
+                alive[u] = false;
+                for &w in &adj[u] {
+                    degree[w] -= 1;
+                }
+            }
+        }
+    }
+    chosen.sort_unstable();
+    chosen
+}
+
+// 精确：位集上的分支限界，要求实例数不超过 64
+fn exact_mis(adj: &[Vec<usize>]) -> Vec<usize> {
+    let masks: Vec<u64> =
+        adj.iter().map(|a| a.iter().fold(0u64, |mask, &u| mask | (1 << u))).collect();
+    let all = if adj.len() == 64 { u64::MAX } else { (1u64 << adj.len()) - 1 };
+    let mut best = 0u64;
+    branch_mis(&masks, all, 0, &mut best);
+    (0..adj.len()).filter(|&v| best & (1 << v) != 0).collect()
+}
+
+fn branch_mis(masks: &[u64], candidates: u64, current: u64, best: &mut u64) {
+    if candidates == 0 {
+        if current.count_ones() > best.count_ones() {
+            *best = current;
+        }
+        return;
+    }
+    if current.count_ones() + candidates.count_ones() <= best.count_ones() {
+        return;
+    }
+    let v = candidates.trailing_zeros() as usize;
+    let bit = 1u64 << v;
+    // 与剩余候选不重叠的实例一定可以选入
+    if masks[v] & candidates == 0 {
+        branch_mis(masks, candidates & !bit, current | bit, best);
+        return;
+    }
+    branch_mis(masks, candidates & !bit & !masks[v], current | bit, best);
+    branch_mis(masks, candidates & !bit, current, best);
+}
+
 // 获取图中某节点引出的有效边（from.label <= to.label）, 用于构造 DFSCode
 pub fn get_forward_edges<'a>(g: &Graph, v: &'a Vertex) -> Vec<&'a Edge> {
     let mut result: Vec<&Edge> = Vec::with_capacity(8);
     for edge in &v.edges {
-        if v.label <= g.vertex_name_label_map.get(&edge.to).unwrap().to_string() {
+        if v.label <= g.vertices[edge.to].label {
             result.push(edge);
         }
     }
@@ -79,15 +295,13 @@ pub fn get_backward<'a, 'b>(
         return None;
     }
     // 遍历从e2的终点出发的所有边
-    for edge in &g.find_vertex(&e2.to).unwrap().edges {
+    for edge in &g.vertices[e2.to].edges {
         if history.has_edge(&edge.id) || edge.to != e1.from {
             continue;
         }
         // 找到一个边的终点是e1的起点
         if e1.e_label < edge.e_label
-            || (e1.e_label == edge.e_label
-                && g.vertex_name_label_map.get(&e1.to).unwrap()
-                    <= g.vertex_name_label_map.get(&e2.to).unwrap())
+            || (e1.e_label == edge.e_label && g.vertices[e1.to].label <= g.vertices[e2.to].label)
         {
             return Some(&edge);
         }
@@ -103,10 +317,8 @@ pub fn get_forward_pure<'a, 'b>(
     history: &'b History,
 ) -> Vec<&'a Edge> {
     let mut result: Vec<&Edge> = Vec::with_capacity(8);
-    for edge in &g.find_vertex(&e.to).unwrap().edges {
-        if min_label > g.vertex_name_label_map.get(&edge.to).unwrap().as_str()
-            || history.has_vertex(&edge.to)
-        {
+    for edge in &g.vertices[e.to].edges {
+        if min_label > g.vertices[edge.to].label.as_str() || history.has_vertex(edge.to) {
             continue;
         }
         result.push(&edge);
@@ -122,11 +334,11 @@ pub fn get_forward_rm_path<'a, 'b>(
     history: &'b History,
 ) -> Vec<&'a Edge> {
     let mut result: Vec<&Edge> = Vec::with_capacity(8);
-    let to_label = g.vertex_name_label_map.get(&e.to).unwrap().as_str();
+    let to_label = g.vertices[e.to].label.as_str();
 
-    for edge in &g.find_vertex(&e.from).unwrap().edges {
-        let to_label_2 = g.vertex_name_label_map.get(&edge.to).unwrap().as_str();
-        if e.to == edge.to || min_label > to_label_2 || history.has_vertex(&edge.to) {
+    for edge in &g.vertices[e.from].edges {
+        let to_label_2 = g.vertices[edge.to].label.as_str();
+        if e.to == edge.to || min_label > to_label_2 || history.has_vertex(edge.to) {
             continue;
         }
         if e.e_label < edge.e_label || (e.e_label == edge.e_label && to_label <= to_label_2) {
@@ -135,3 +347,43 @@ pub fn get_forward_rm_path<'a, 'b>(
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 由边列表构造无向邻接表
+    fn adjacency(n: usize, edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+        let mut adj = vec![vec![]; n];
+        for &(a, b) in edges {
+            adj[a].push(b);
+            adj[b].push(a);
+        }
+        adj
+    }
+
+    #[test]
+    fn test_mis_on_path() {
+        // 0-1-2-3-4
+        let adj = adjacency(5, &[(0, 1), (1, 2), (2, 3), (3, 4)]);
+        assert_eq!(vec![0, 2, 4], greedy_mis(&adj));
+        assert_eq!(vec![0, 2, 4], exact_mis(&adj));
+    }
+
+    #[test]
+    fn test_mis_on_cycle() {
+        // 五元环的最大独立集大小为 2
+        let adj = adjacency(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+        assert_eq!(2, greedy_mis(&adj).len());
+        assert_eq!(2, exact_mis(&adj).len());
+    }
+
+    #[test]
+    fn test_mis_on_hubs() {
+        // 0 与 4、5 相连；4、5 相连且分别与 1、2、3 全部相连
+        let adj =
+            adjacency(6, &[(0, 4), (0, 5), (4, 1), (4, 2), (4, 3), (5, 1), (5, 2), (5, 3), (4, 5)]);
+        assert_eq!(vec![0, 1, 2, 3], greedy_mis(&adj));
+        assert_eq!(vec![0, 1, 2, 3], exact_mis(&adj));
+    }
+}
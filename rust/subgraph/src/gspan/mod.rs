@@ -1,6 +1,7 @@
 /*
  * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
  */
+pub mod canonical;
 pub mod gspan;
 pub mod misc;
 pub mod models;
@@ -42,7 +42,8 @@ impl DFSCode {
         projected: &Projected,
         result: &mut MaxDFSCodeGraphResult,
     ) -> Option<DFS> {
-        if !self.is_push_result {
+        // 记录全部频繁模式时由 GSpan 在确认最小 DFSCode 后写入结果，此处不再记录
+        if !self.is_push_result && !result.get_pattern_mode().records_all() {
             // 记录尽可能远的深度搜索的结果
             // println!("pop {} {} {:?}", singleton.min_sup, singleton.inner_min_sup, singleton.out);
             self.is_push_result = result.add_value(self, projected);
@@ -59,11 +60,11 @@ impl DFSCode {
         let mut edge_data = Vec::<(String, String, Option<String>)>::with_capacity(8);
         for it in &self.dfs_vec {
             let from_name = it.from.to_string();
-            if it.from_label != "" && !g.vertex_name_label_map.contains_key(&from_name) {
+            if it.from_label != "" && !g.vertex_index.contains_key(&from_name) {
                 g.insert_vertex(&from_name, &it.from_label);
             }
             let to_name = it.to.to_string();
-            if it.to_label != "" && !g.vertex_name_label_map.contains_key(&to_name) {
+            if it.to_label != "" && !g.vertex_index.contains_key(&to_name) {
                 g.insert_vertex(&to_name, &it.to_label);
             }
 
@@ -3,11 +3,39 @@
  */
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use serde::{Deserialize, Serialize};
+
+/// 边标签的取值方式
+/// - `Nil`: 所有边使用 [`Edge::NIL_E_LABEL`]，不区分操作数顺序
+/// - `InputSlot`: 使用目标节点的输入序号，如 `1`
+/// - `Port`: 使用 `输出序号:输入序号`，如 `0:1`
+///
+/// 对于 `Sub`、`Div`、`MatMul` 等非交换算子，`InputSlot`/`Port` 可以区分 `Sub(a, b)` 与 `Sub(b, a)`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeLabel {
+    #[default]
+    Nil,
+    InputSlot,
+    Port,
+}
+
+impl EdgeLabel {
+    pub fn format(&self, out_slot: usize, in_slot: usize) -> String {
+        match self {
+            EdgeLabel::Nil => String::from(Edge::NIL_E_LABEL),
+            EdgeLabel::InputSlot => in_slot.to_string(),
+            EdgeLabel::Port => format!("{}:{}", out_slot, in_slot),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Edge {
     pub id: usize,
-    pub from: String,
-    pub to: String,
+    // 起止顶点在所属图 vertices 中的下标
+    pub from: usize,
+    pub to: usize,
     pub from_label: String,
     pub to_label: String,
     pub e_label: String,
@@ -17,8 +45,8 @@ impl Edge {
     pub const NIL_E_LABEL: &'static str = "<NIL>";
 
     pub fn new(
-        from: String,
-        to: String,
+        from: usize,
+        to: usize,
         from_label: String,
         to_label: String,
         e_label: Option<String>,
@@ -37,11 +65,12 @@ impl Edge {
         }
     }
 
-    pub fn to_str_repr(&self) -> String {
+    // 边只记录顶点下标，输出时由调用方提供顶点名
+    pub fn to_str_repr(&self, from: &str, to: &str) -> String {
         vec![
             "e".to_string(),
-            self.from.to_string(),
-            self.to.to_string(),
+            from.to_string(),
+            to.to_string(),
             self.from_label.to_string(),
             self.to_label.to_string(),
             self.e_label.to_string(),
@@ -64,30 +93,19 @@ mod tests {
 
     #[test]
     fn test_equal_edge() {
-        let edge1 = Edge::new(
-            String::from("node_1"),
-            String::from("node_2"),
-            String::from("a"),
-            String::from("b"),
-            Some(String::from("A")),
-        );
-        let edge2 = Edge::new(
-            String::from("node_1"),
-            String::from("node_2"),
-            String::from("a"),
-            String::from("b"),
-            Some(String::from("A")),
-        );
-        let edge3 = Edge::new(
-            String::from("node_2"),
-            String::from("node_1"),
-            String::from("a"),
-            String::from("b"),
-            Some(String::from("C")),
-        );
+        let edge1 = Edge::new(0, 1, String::from("a"), String::from("b"), Some(String::from("A")));
+        let edge2 = Edge::new(0, 1, String::from("a"), String::from("b"), Some(String::from("A")));
+        let edge3 = Edge::new(1, 0, String::from("a"), String::from("b"), Some(String::from("C")));
 
         assert_eq!(edge1, edge2);
         assert_ne!(edge1, edge3);
         assert_ne!(edge2, edge3);
     }
+
+    #[test]
+    fn test_edge_label_format() {
+        assert_eq!(Edge::NIL_E_LABEL, EdgeLabel::Nil.format(0, 1));
+        assert_eq!("1", EdgeLabel::InputSlot.format(0, 1));
+        assert_eq!("2:1", EdgeLabel::Port.format(2, 1));
+    }
 }
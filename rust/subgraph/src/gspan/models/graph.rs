@@ -4,8 +4,17 @@
 use std::{collections::HashMap, fmt, fs};
 
 use crate::{
-    gspan::models::{edge::Edge, vertex::Vertex},
-    io::{model_graph::ModelGraph, node::Node},
+    gspan::models::{
+        edge::{Edge, EdgeLabel},
+        vertex::Vertex,
+    },
+    io::{
+        graphml::read_graphml,
+        model_graph::ModelGraph,
+        node::Node,
+        output::{Edge as OutputEdge, Structure, Vertex as OutputVertex},
+        transaction::read_transactions,
+    },
 };
 
 #[derive(Debug)]
@@ -13,6 +22,12 @@ pub struct GraphSetParseError {
     message: String,
 }
 
+impl GraphSetParseError {
+    pub fn new(message: String) -> GraphSetParseError {
+        GraphSetParseError { message }
+    }
+}
+
 impl fmt::Display for GraphSetParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.message)
@@ -27,7 +42,8 @@ pub struct Graph {
     pub edge_size: usize,
     pub directed: bool,
     pub vertices: Vec<Vertex>,
-    pub vertex_name_label_map: HashMap<String, String>,
+    // 顶点名 -> 顶点在 vertices 中的下标
+    pub vertex_index: HashMap<String, usize>,
 }
 
 impl Graph {
@@ -38,61 +54,98 @@ impl Graph {
             edge_size: 0,
             directed,
             vertices: Vec::with_capacity(32),
-            vertex_name_label_map: HashMap::new(),
+            vertex_index: HashMap::new(),
         }
     }
 
-    pub fn insert_vertex(&mut self, name: &str, label: &str) {
-        self.vertex_name_label_map.insert(name.to_string(), label.to_string());
-        let vertex = Vertex::new(name.to_string(), Some(label.to_string()));
-        self.vertices.push(vertex);
+    pub fn insert_vertex(&mut self, name: &str, label: &str) -> usize {
+        let id = self.vertices.len();
+        self.vertex_index.insert(name.to_string(), id);
+        self.vertices.push(Vertex::new(id, name.to_string(), Some(label.to_string())));
+        id
     }
 
     pub fn build_edge(&mut self, data: Vec<(String, String, Option<String>)>) {
         for (from, to, e_label) in data {
-            if let Some(f_vertex) = self.vertices.iter_mut().find(|x| x.name == from) {
-                if let Some(to_label) = self.vertex_name_label_map.get(&to) {
-                    let edge =
-                        Edge::new(from, to, f_vertex.label.clone(), to_label.to_string(), e_label);
-                    f_vertex.push(edge);
-                    self.edge_size += 1;
-                } else {
-                    println!("Error: build_edge => {} 不存在 to_label.", to);
-                }
-            } else {
-                println!("Error: build_edge => {} 不存在 vertex.", from);
+            match (self.vertex_id(&from), self.vertex_id(&to)) {
+                (Some(from), Some(to)) => self.push_edge(from, to, e_label),
+                (None, _) => println!("Error: build_edge => {} 不存在 vertex.", from),
+                (_, None) => println!("Error: build_edge => {} 不存在 to_label.", to),
             }
         }
     }
+
+    fn push_edge(&mut self, from: usize, to: usize, e_label: Option<String>) {
+        let edge = Edge::new(
+            from,
+            to,
+            self.vertices[from].label.clone(),
+            self.vertices[to].label.clone(),
+            e_label,
+        );
+        self.vertices[from].push(edge);
+        self.edge_size += 1;
+    }
 }
 
 impl Graph {
+    // 无向图的每条边按两个方向分别存储，挖掘过程只沿出边扩展
+    pub fn build_edge_by_direction(&mut self, mut data: Vec<(String, String, Option<String>)>) {
+        if !self.directed {
+            let reversed: Vec<(String, String, Option<String>)> = data
+                .iter()
+                .filter(|(from, to, _)| from != to)
+                .map(|(from, to, e_label)| (to.clone(), from.clone(), e_label.clone()))
+                .collect();
+            data.extend(reversed);
+        }
+        self.build_edge(data);
+    }
+
+    /**
+     * 按存储顺序返回需要写出的边，无向图中成对存储的反向边只返回一次
+     */
+    pub fn output_edges(&self) -> Vec<&Edge> {
+        let mut written: HashMap<(usize, usize, &str), usize> = HashMap::new();
+        let mut edges = Vec::new();
+        for edge in self.vertices.iter().flat_map(|v| v.edges.iter()) {
+            if !self.directed && edge.from != edge.to {
+                let reverse = (edge.to, edge.from, edge.e_label.as_str());
+                if let Some(count) = written.get_mut(&reverse).filter(|c| **c > 0) {
+                    *count -= 1;
+                    continue;
+                }
+                *written.entry((edge.from, edge.to, edge.e_label.as_str())).or_default() += 1;
+            }
+            edges.push(edge);
+        }
+        edges
+    }
+
+    pub fn vertex_id(&self, name: &str) -> Option<usize> {
+        self.vertex_index.get(name).copied()
+    }
+
+    pub fn vertex_name(&self, id: usize) -> &str {
+        &self.vertices[id].name
+    }
+
     pub fn find_vertex(&self, name: &str) -> Option<&Vertex> {
-        self.vertices.iter().find(|x| x.name == name)
+        self.vertex_id(name).map(|id| &self.vertices[id])
     }
 
     fn push_node(&mut self, node: &Node) {
-        let vertex = Vertex::from(node);
-        self.vertex_name_label_map.insert(vertex.name.clone(), vertex.label.clone());
-        self.vertices.push(vertex);
+        let id = self.vertices.len();
+        self.vertex_index.insert(node.name.clone(), id);
+        self.vertices.push(Vertex::from(id, node));
     }
 
     #[allow(dead_code)]
     fn build_edge_by_node(&mut self, node: &Node) {
-        let to = &node.name;
-
-        if let Some(to_label) = self.vertex_name_label_map.get(to) {
+        if let Some(to) = self.vertex_id(&node.name) {
             for from_name in &node.input {
-                if let Some(from) = self.vertices.iter_mut().find(|x| &x.name == from_name) {
-                    let edge = Edge::new(
-                        from_name.clone(),
-                        to.to_string(),
-                        from.label.clone(),
-                        to_label.to_string(),
-                        None,
-                    );
-                    from.push(edge);
-                    self.edge_size += 1;
+                if let Some(from) = self.vertex_id(from_name) {
+                    self.push_edge(from, to, None);
                 } else {
                     // do nothing
                     // println!("WARN: build_edge => {} 不存在 vertex.", from_name);
@@ -101,31 +154,78 @@ impl Graph {
         }
     }
 
-    fn build_edges_for_nodes(&mut self, edges: Vec<(String, String)>) {
+    fn build_edges_for_nodes(
+        &mut self,
+        edges: Vec<(String, String)>,
+        node_map: &HashMap<String, Node>,
+        edge_label: EdgeLabel,
+    ) {
+        // 同一对节点之间可能由多个张量相连，按出现次序依次匹配输入序号
+        let mut occurrences: HashMap<(usize, usize), usize> = HashMap::new();
         for (from, to) in edges {
-            if let Some(f_vertex) = self.vertices.iter_mut().find(|x| x.name == from) {
-                if let Some(to_label) = self.vertex_name_label_map.get(&to) {
-                    let edge = Edge::new(
-                        from,
-                        to,
-                        f_vertex.label.clone(),
-                        to_label.to_string(),
-                        Some(Edge::NIL_E_LABEL.to_string()),
-                    );
-                    f_vertex.push(edge);
-                    self.edge_size += 1;
+            let (Some(from_id), Some(to_id)) = (self.vertex_id(&from), self.vertex_id(&to)) else {
+                continue;
+            };
+            let e_label = match edge_label {
+                EdgeLabel::Nil => Edge::NIL_E_LABEL.to_string(),
+                _ => {
+                    let nth = occurrences.entry((from_id, to_id)).or_insert(0);
+                    let (out_slot, in_slot) =
+                        resolve_slots(&from, node_map.get(&from), node_map.get(&to), *nth);
+                    *nth += 1;
+                    edge_label.format(out_slot, in_slot)
                 }
-            }
+            };
+            self.push_edge(from_id, to_id, Some(e_label));
         }
     }
 }
 
+/**
+ * 计算边 from -> to 的 (输出序号, 输入序号)
+ * 目标节点的 input 可能是：源节点 output 中的张量名（ONNX）、源节点名（MindIR）或 `源节点名:端口`（GEIR）
+ * 无法确定时退化为 (0, 0)
+ */
+fn resolve_slots(
+    from: &str,
+    from_node: Option<&Node>,
+    to_node: Option<&Node>,
+    nth: usize,
+) -> (usize, usize) {
+    let Some(to_node) = to_node else {
+        return (0, 0);
+    };
+    let outputs: &[String] = from_node.map_or(&[], |n| n.output.as_slice());
+    to_node
+        .input
+        .iter()
+        .enumerate()
+        .filter_map(|(in_slot, input)| {
+            if let Some(out_slot) = outputs.iter().position(|o| o == input) {
+                return Some((out_slot, in_slot));
+            }
+            if input == from {
+                return Some((0, in_slot));
+            }
+            match input.rsplit_once(':') {
+                Some((name, port)) if name == from => port.parse().ok().map(|p| (p, in_slot)),
+                _ => None,
+            }
+        })
+        .nth(nth)
+        .unwrap_or((0, 0))
+}
+
 impl Graph {
-    pub fn graph_from_file(filename: &str, directed: bool) -> Result<Graph, GraphSetParseError> {
+    pub fn graph_from_file(
+        filename: &str,
+        directed: bool,
+        edge_label: EdgeLabel,
+    ) -> Result<Graph, GraphSetParseError> {
         //读取文件内容
         match fs::read_to_string(filename) {
             Ok(json_content) => {
-                return Graph::graph_from_json_string(json_content, directed);
+                return Graph::graph_from_json_string(json_content, directed, edge_label);
             }
             Err(_) => {
                 return Err(GraphSetParseError {
@@ -135,13 +235,34 @@ impl Graph {
         }
     }
 
+    /**
+     * 读取可能包含多张图的文件，按内容判断格式：
+     * `{` 开头为 ModelGraph JSON，`<` 开头为 GraphML，其余按 gSpan 事务格式读取
+     * `directed` 只对事务格式生效，GraphML 的方向由文件本身决定
+     */
+    pub fn graphs_from_file(
+        filename: &str,
+        directed: bool,
+        edge_label: EdgeLabel,
+    ) -> Result<Vec<Graph>, GraphSetParseError> {
+        let content = fs::read_to_string(filename).map_err(|_| GraphSetParseError {
+            message: format!("Error reading file : {}", filename),
+        })?;
+        match content.trim_start().chars().next() {
+            Some('{') => Ok(vec![Graph::graph_from_json_string(content, directed, edge_label)?]),
+            Some('<') => read_graphml(&content),
+            _ => read_transactions(&content, directed),
+        }
+    }
+
     pub fn graph_from_json_string(
         json_content: String,
         directed: bool,
+        edge_label: EdgeLabel,
     ) -> Result<Graph, GraphSetParseError> {
         match serde_json::from_str::<ModelGraph>(&json_content) {
             Ok(model_graph) => {
-                return Ok(Graph::graph_from_model_graph(model_graph, directed));
+                return Ok(Graph::graph_from_model_graph(model_graph, directed, edge_label));
             }
             Err(e) => {
                 return Err(GraphSetParseError {
@@ -151,17 +272,23 @@ impl Graph {
         }
     }
 
-    pub fn graph_from_model_graph(model_graph: ModelGraph, directed: bool) -> Graph {
+    pub fn graph_from_model_graph(
+        model_graph: ModelGraph,
+        directed: bool,
+        edge_label: EdgeLabel,
+    ) -> Graph {
         let node_map = model_graph.nodes;
         let mut graph = Graph::new(0, directed);
         graph.name = model_graph.name;
+        graph.vertices.reserve(node_map.len());
+        graph.vertex_index.reserve(node_map.len());
         for (_, val) in &node_map {
             graph.push_node(val);
         }
         // for (_, val) in &node_map {
         //     graph.build_edge_by_node(val);
         // }
-        graph.build_edges_for_nodes(model_graph.edges);
+        graph.build_edges_for_nodes(model_graph.edges, &node_map, edge_label);
         return graph;
     }
 
@@ -181,10 +308,59 @@ impl Graph {
             edges.extend(vertex.edges.iter());
         }
         for edge in edges {
-            lines.push(edge.to_str_repr());
+            lines.push(edge.to_str_repr(self.vertex_name(edge.from), self.vertex_name(edge.to)));
         }
         lines.join("\n")
     }
+
+    // 转换为输出格式，顶点按 vertices 中的顺序排列
+    pub fn to_structure(&self) -> Structure {
+        let mut output_vertices: Vec<OutputVertex> = Vec::new();
+        let mut output_edges: Vec<OutputEdge> = Vec::new();
+        for vertex in &self.vertices {
+            output_vertices
+                .push(OutputVertex { name: vertex.name.clone(), label: vertex.label.clone() });
+            output_edges.extend(vertex.edges.iter().map(|e| OutputEdge::from_graph_edge(e, self)));
+        }
+
+        Structure { tid: self.id, vertices: output_vertices, edges: output_edges }
+    }
+}
+
+/// 测试共用的构图与取边函数
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::Graph;
+
+    // 顶点为 (顶点名, 标签)，按给定顺序插入；边为 (起点名, 终点名, 边标签)
+    pub(crate) fn build_graph(vertices: &[(&str, &str)], edges: &[(&str, &str, &str)]) -> Graph {
+        let mut graph = Graph::new(0, true);
+        for (name, label) in vertices {
+            graph.insert_vertex(name, label);
+        }
+        graph.build_edge(
+            edges
+                .iter()
+                .map(|(f, t, l)| (f.to_string(), t.to_string(), Some(l.to_string())))
+                .collect(),
+        );
+        graph
+    }
+
+    // 图中所有边的 (起点名, 终点名, 边标签)，排序后便于比较
+    pub(crate) fn edge_triples(graph: &Graph) -> Vec<(String, String, String)> {
+        let mut edges: Vec<(String, String, String)> = graph
+            .vertices
+            .iter()
+            .flat_map(|v| v.edges.iter())
+            .map(|e| {
+                let (from, to) = (graph.vertex_name(e.from), graph.vertex_name(e.to));
+                (from.to_string(), to.to_string(), e.e_label.clone())
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
 }
 
 #[cfg(test)]
@@ -197,7 +373,7 @@ mod tests {
     fn test_load_single_graph() {
         let filename = r#"tests\json\single-graph.json"#;
 
-        match Graph::graph_from_file(&filename, true) {
+        match Graph::graph_from_file(&filename, true, EdgeLabel::Nil) {
             Ok(graph) => {
                 println!("{}", graph.to_str_repr(None));
             }
@@ -211,7 +387,7 @@ mod tests {
     fn test_load_graph() {
         let filename = r#"tests\json\single-graph.json"#;
 
-        match Graph::graph_from_file(&filename, true) {
+        match Graph::graph_from_file(&filename, true, EdgeLabel::Nil) {
             Ok(graph) => {
                 // 使用flat_map展开内部有的集合，并收集到一个新的FxHashSet<String>
                 let result: Vec<Edge> = graph
@@ -230,10 +406,13 @@ mod tests {
                         format!("{}->{}->{}", &edge.from_label, &edge.e_label, &edge.to_label);
                     map.entry(key)
                         .and_modify(|v| {
-                            v.0.push((&edge.from, &edge.to));
+                            v.0.push((graph.vertex_name(edge.from), graph.vertex_name(edge.to)));
                             v.1 += 1
                         })
-                        .or_insert((vec![(&edge.from, &edge.to)], 1));
+                        .or_insert((
+                            vec![(graph.vertex_name(edge.from), graph.vertex_name(edge.to))],
+                            1,
+                        ));
                 }
 
                 print!("{:?}", map)
@@ -243,4 +422,94 @@ mod tests {
             }
         }
     }
+
+    fn sub_model_graph() -> ModelGraph {
+        let node = |name: &str, op_type: &str, input: &[&str], output: &[&str]| Node {
+            name: name.to_string(),
+            opType: op_type.to_string(),
+            input: input.iter().map(|s| s.to_string()).collect(),
+            output: output.iter().map(|s| s.to_string()).collect(),
+        };
+        // Sub(a, b) 与 Sub(b, a)
+        let nodes = vec![
+            node("a", "Const", &[], &["a_out"]),
+            node("b", "Const", &[], &["b_out"]),
+            node("sub_0", "Sub", &["a_out", "b_out"], &["sub_0_out"]),
+            node("sub_1", "Sub", &["b_out", "a_out"], &["sub_1_out"]),
+        ];
+        ModelGraph {
+            name: "sub".to_string(),
+            nodes: nodes.into_iter().map(|n| (n.name.clone(), n)).collect(),
+            edges: vec![
+                ("a".to_string(), "sub_0".to_string()),
+                ("b".to_string(), "sub_0".to_string()),
+                ("b".to_string(), "sub_1".to_string()),
+                ("a".to_string(), "sub_1".to_string()),
+            ],
+            parameters: HashMap::new(),
+        }
+    }
+
+    fn edge_labels(graph: &Graph, from: &str) -> Vec<(String, String)> {
+        let mut labels: Vec<(String, String)> = graph
+            .find_vertex(from)
+            .unwrap()
+            .edges
+            .iter()
+            .map(|e| (graph.vertex_name(e.to).to_string(), e.e_label.clone()))
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn test_edge_label_nil() {
+        let graph = Graph::graph_from_model_graph(sub_model_graph(), true, EdgeLabel::Nil);
+        assert_eq!(
+            vec![
+                ("sub_0".to_string(), Edge::NIL_E_LABEL.to_string()),
+                ("sub_1".to_string(), Edge::NIL_E_LABEL.to_string())
+            ],
+            edge_labels(&graph, "a")
+        );
+    }
+
+    #[test]
+    fn test_edge_label_port() {
+        let graph = Graph::graph_from_model_graph(sub_model_graph(), true, EdgeLabel::Port);
+        assert_eq!(
+            vec![
+                ("sub_0".to_string(), "0:0".to_string()),
+                ("sub_1".to_string(), "0:1".to_string())
+            ],
+            edge_labels(&graph, "a")
+        );
+        assert_eq!(
+            vec![
+                ("sub_0".to_string(), "0:1".to_string()),
+                ("sub_1".to_string(), "0:0".to_string())
+            ],
+            edge_labels(&graph, "b")
+        );
+    }
+
+    #[test]
+    fn test_edge_label_named_port() {
+        // GEIR 风格的 `name:port` 输入，同一对节点之间存在两条边
+        let mut model_graph = sub_model_graph();
+        model_graph.nodes.get_mut("sub_0").unwrap().input =
+            vec!["a:1".to_string(), "a:0".to_string()];
+        model_graph.nodes.get_mut("a").unwrap().output = vec![];
+        model_graph.edges =
+            vec![("a".to_string(), "sub_0".to_string()), ("a".to_string(), "sub_0".to_string())];
+
+        let graph = Graph::graph_from_model_graph(model_graph, true, EdgeLabel::Port);
+        assert_eq!(
+            vec![
+                ("sub_0".to_string(), "0:1".to_string()),
+                ("sub_0".to_string(), "1:0".to_string())
+            ],
+            edge_labels(&graph, "a")
+        );
+    }
 }
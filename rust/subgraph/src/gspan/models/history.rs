@@ -13,7 +13,7 @@ use crate::gspan::models::{edge::Edge, prev_dfs::PrevDFS};
 pub struct History<'a> {
     pub histories: Vec<&'a Edge>,
     pub edges: FxHashSet<usize>,
-    pub vertices: FxHashSet<String>,
+    pub vertices: FxHashSet<usize>,
 }
 
 impl<'a> History<'a> {
@@ -27,8 +27,8 @@ impl<'a> History<'a> {
         loop {
             history.histories.push(e.edge);
             history.edges.insert(e.edge.id);
-            history.vertices.insert(e.edge.from.clone());
-            history.vertices.insert(e.edge.to.clone());
+            history.vertices.insert(e.edge.from);
+            history.vertices.insert(e.edge.to);
             if e.prev.is_none() {
                 break;
             }
@@ -42,7 +42,7 @@ impl<'a> History<'a> {
         self.edges.contains(&id)
     }
 
-    pub fn has_vertex(&self, name: &str) -> bool {
-        self.vertices.contains(name)
+    pub fn has_vertex(&self, id: usize) -> bool {
+        self.vertices.contains(&id)
     }
 }
@@ -33,14 +33,15 @@ impl<'a> PrevDFS<'a> {
         }
     }
 
-    pub fn get_vertex_names(&self) -> FxHashSet<(usize, String)> {
-        let mut names: FxHashSet<(usize, String)> = FxHashSet::default();
+    // 返回投影覆盖的 (gid, 顶点下标)
+    pub fn get_vertex_ids(&self) -> FxHashSet<(usize, usize)> {
+        let mut ids: FxHashSet<(usize, usize)> = FxHashSet::default();
 
         let mut cur = self;
 
         loop {
-            names.insert((cur.gid.clone(), cur.edge.from.clone()));
-            names.insert((cur.gid.clone(), cur.edge.to.clone()));
+            ids.insert((cur.gid, cur.edge.from));
+            ids.insert((cur.gid, cur.edge.to));
 
             if let Some(prev) = &cur.prev {
                 cur = **prev;
@@ -49,7 +50,7 @@ impl<'a> PrevDFS<'a> {
             }
         }
 
-        names
+        ids
     }
 
     pub fn get_edges(&self) -> Vec<&Edge> {
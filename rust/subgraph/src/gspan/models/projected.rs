@@ -26,8 +26,8 @@ impl<'a> Projected<'a> {
         self.projections.push(Box::new(new_pdfs));
     }
 
-    pub fn to_vertex_names_list(&self) -> Vec<FxHashSet<(usize, String)>> {
-        self.projections.iter().map(|p| p.get_vertex_names()).collect()
+    pub fn to_vertex_ids_list(&self) -> Vec<FxHashSet<(usize, usize)>> {
+        self.projections.iter().map(|p| p.get_vertex_ids()).collect()
     }
 
     pub fn to_edges_list(&self) -> Vec<Vec<&Edge>> {
@@ -5,6 +5,8 @@ use crate::{gspan::models::edge::Edge, io::node::Node};
 
 #[derive(Debug, Clone)]
 pub struct Vertex {
+    // 顶点在所属图 vertices 中的下标，边通过它引用顶点
+    pub id: usize,
     pub name: String,
     pub label: String,
     pub edges: Vec<Edge>,
@@ -13,8 +15,9 @@ pub struct Vertex {
 impl Vertex {
     pub const NIL_V_LABEL: &str = "<NIL>";
 
-    pub fn new(name: String, label: Option<String>) -> Vertex {
+    pub fn new(id: usize, name: String, label: Option<String>) -> Vertex {
         Vertex {
+            id,
             name,
             label: match label {
                 None => String::new(),
@@ -32,8 +35,8 @@ impl Vertex {
         vec!["v".to_string(), self.name.to_string(), self.label.to_string()].join(" ")
     }
 
-    pub fn from(node: &Node) -> Vertex {
-        Vertex::new(node.name.clone(), Some(node.opType.clone()))
+    pub fn from(id: usize, node: &Node) -> Vertex {
+        Vertex::new(id, node.name.clone(), Some(node.opType.clone()))
     }
 }
 
@@ -49,28 +52,24 @@ mod tests {
 
     #[test]
     fn test_create_vertex() {
-        let v1 = Vertex::new(String::from("node_1"), None);
+        let v1 = Vertex::new(0, String::from("node_1"), None);
+        assert_eq!(v1.id, 0);
         assert_eq!(v1.name, "node_1");
     }
 
     #[test]
     fn test_add_edge() {
-        let mut v1 = Vertex::new(String::from("node_1"), Some(String::from("2")));
+        let mut v1 = Vertex::new(0, String::from("node_1"), Some(String::from("2")));
         assert_eq!(v1.edges.len(), 0);
         assert_eq!(v1.label, "2");
-        let e1 = Edge::new(
-            v1.name.clone(),
-            String::from("node_2"),
-            v1.label.clone(),
-            String::from("2"),
-            Some(String::from("e_2")),
-        );
+        let e1 =
+            Edge::new(v1.id, 1, v1.label.clone(), String::from("2"), Some(String::from("e_2")));
         v1.push(e1);
         assert_eq!(v1.edges.len(), 1);
         let e = v1.edges.pop().unwrap();
         assert_eq!(v1.edges.len(), 0);
-        assert_eq!(&e.from, &v1.name);
-        assert_eq!(e.to, "node_2");
+        assert_eq!(e.from, v1.id);
+        assert_eq!(e.to, 1);
         assert_eq!(e.e_label, "e_2");
     }
 }
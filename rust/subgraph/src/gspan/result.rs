@@ -1,23 +1,63 @@
 /*
  * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
  */
-use std::{io::Write, sync::mpsc::Sender};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    sync::{Arc, mpsc::Sender},
+};
 
 use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     gspan::{
-        misc::{inner_support, support},
-        models::{dfs_code::DFSCode, edge::Edge, projected::Projected},
+        canonical::pattern_hash,
+        misc::{SupportMode, inner_support_of, is_sub_pattern, select_instances_checked, support},
+        models::{dfs_code::DFSCode, edge::Edge, graph::Graph, projected::Projected},
+    },
+    io::{
+        output::{Edge as OutputEdge, Instance, NodeId, Structure},
+        writer::ResultWriter,
     },
-    io::output::{Edge as OutputEdge, Instance, NodeId, Structure, Vertex as OutputVertex},
 };
 
+/// 结果输出格式
+/// - `TXT`: gSpan 文本格式
+/// - `JSON`: 所有结果组成一个 JSON 数组
+/// - `NDJSON`: 每行一个 JSON 结果，便于流式处理大规模输出
 #[derive(Debug, Clone)]
 pub enum OutType {
     TXT,
     JSON,
+    NDJSON,
+}
+
+/// 结果模式的筛选方式
+/// - `Deepest`: 只记录每条深度搜索路径上尽可能远的模式
+/// - `All`: 记录所有满足支持度与顶点数约束的频繁模式
+/// - `Closed`: 闭合模式，若存在支持度相同的超模式则丢弃
+/// - `Maximal`: 极大模式，只保留不存在频繁超模式的模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternMode {
+    #[default]
+    Deepest,
+    All,
+    Closed,
+    Maximal,
+}
+
+impl PatternMode {
+    // 除 Deepest 外，其余模式需要记录每一个频繁模式
+    pub fn records_all(&self) -> bool {
+        *self != PatternMode::Deepest
+    }
+
+    // Closed、Maximal 需要在挖掘结束后才能判断，因此延迟输出
+    pub fn is_deferred(&self) -> bool {
+        matches!(self, PatternMode::Closed | PatternMode::Maximal)
+    }
 }
 
 pub enum OutSource<W: Write + Send + Sync + 'static> {
@@ -32,13 +72,55 @@ pub struct JSONResult {
     pub inner_min_sup: usize,
     pub inner_max_sup: usize,
     pub total: usize,
+    // 由最小 DFS 编码得到的模式 id，同一模式在不同模型、不同运行中相同
+    #[serde(default)]
+    pub pattern_id: String,
     pub structure: Structure,
     pub instances: Vec<Instance>,
 }
 
+// 一个结果模式：DFSCode 及其每个实例的 (gid, 顶点下标) 集合
+pub type PatternItem = (DFSCode, Vec<FxHashSet<(usize, usize)>>);
+
+/// 多模型挖掘的结果：模式本身及包含该模式的模型
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SharedPattern {
+    pub models: Vec<ModelOccurrence>,
+    #[serde(flatten)]
+    pub pattern: JSONResult,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelOccurrence {
+    pub gid: usize,
+    pub name: String,
+    pub instances: usize,
+}
+
+impl SharedPattern {
+    // names 按 gid 索引
+    pub fn from_result(pattern: JSONResult, names: &[String]) -> SharedPattern {
+        let mut gid_counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for instance in &pattern.instances {
+            if let Some(node_id) = instance.node_ids.first() {
+                *gid_counts.entry(node_id.gid).or_default() += 1;
+            }
+        }
+        let models = gid_counts
+            .into_iter()
+            .map(|(gid, instances)| ModelOccurrence {
+                gid,
+                name: names.get(gid).cloned().unwrap_or_default(),
+                instances,
+            })
+            .collect();
+        SharedPattern { models, pattern }
+    }
+}
+
 #[derive(Default)]
 pub struct MaxDFSCodeGraphResult {
-    out: Option<Box<dyn Write + Send + Sync>>,
+    out: Option<ResultWriter>,
     out_type: Option<OutType>,
     channel: bool, // 如果 channel 为 true，则 out 流失效
     sender: Option<Sender<String>>,
@@ -46,7 +128,21 @@ pub struct MaxDFSCodeGraphResult {
     inner_min_sup: usize,
     max_pat_min: usize, // Minimum number of vertices
     max_pat_max: usize, // Maximum number of vertices
-    value: Vec<(DFSCode, Vec<FxHashSet<(usize, String)>>)>,
+    pattern_mode: PatternMode,
+    support_mode: SupportMode,
+    mis_fallback: bool, // ExactMis 是否在某个图内因实例数超过上限退化为贪心
+    value: Vec<PatternItem>,
+    candidates: Vec<Candidate>, // Closed、Maximal 模式下等待筛选的频繁模式
+    graphs: Arc<Vec<Graph>>,    // 挖掘的图列表，输出时将顶点下标还原为顶点名
+}
+
+// 延迟输出的频繁模式，边需持有所有权以脱离 Projected 的生命周期
+struct Candidate {
+    sup: usize,
+    min_inner_sup: usize,
+    max_inner_sup: usize,
+    item: PatternItem,
+    edges_list: Vec<Vec<Edge>>,
 }
 
 // // 单例结构体
@@ -96,9 +192,28 @@ impl MaxDFSCodeGraphResult {
         self.inner_min_sup = inner_min_sup;
         self.max_pat_min = max_pat_min;
         self.max_pat_max = max_pat_max;
+        if let Some(out) = &mut self.out {
+            out.set_out_type(out_type.clone());
+        }
         self.out_type = Some(out_type);
     }
 
+    pub fn set_pattern_mode(&mut self, pattern_mode: PatternMode) {
+        self.pattern_mode = pattern_mode;
+    }
+
+    pub fn get_pattern_mode(&self) -> PatternMode {
+        self.pattern_mode
+    }
+
+    pub fn set_support_mode(&mut self, support_mode: SupportMode) {
+        self.support_mode = support_mode;
+    }
+
+    pub fn set_graphs(&mut self, graphs: Arc<Vec<Graph>>) {
+        self.graphs = graphs;
+    }
+
     pub fn add_value(&mut self, dfs_code: &DFSCode, projected: &Projected) -> bool {
         // Check if the pattern is frequent enough, between graphs
         let sup: usize = support(projected);
@@ -106,7 +221,9 @@ impl MaxDFSCodeGraphResult {
             return false;
         }
         // Check if the pattern is frequent enough, inner graph
-        let (_min_inner_sup, max_inner_sup) = inner_support(projected);
+        let (selected, exact) = select_instances_checked(projected, self.support_mode);
+        self.mis_fallback |= self.support_mode == SupportMode::ExactMis && !exact;
+        let (_min_inner_sup, max_inner_sup) = inner_support_of(projected, &selected);
         if max_inner_sup < self.inner_min_sup {
             return false;
         }
@@ -117,47 +234,130 @@ impl MaxDFSCodeGraphResult {
         if self.max_pat_min > 0 && dfs_code.count_node() < self.max_pat_min {
             return false;
         }
-        let item = (dfs_code.clone(), projected.to_vertex_names_list());
-        let edges_list = projected.to_edges_list();
+        let mut item = (dfs_code.clone(), projected.to_vertex_ids_list());
+        let mut edges_list = projected.to_edges_list();
+        // 基于最大独立集的支持度只输出互不重叠的实例
+        if self.support_mode.is_mis() {
+            item.1 = selected.iter().map(|&i| std::mem::take(&mut item.1[i])).collect();
+            edges_list = selected.iter().map(|&i| std::mem::take(&mut edges_list[i])).collect();
+        }
+        if self.pattern_mode.is_deferred() {
+            let edges_list =
+                edges_list.into_iter().map(|es| es.into_iter().cloned().collect()).collect();
+            self.candidates.push(Candidate {
+                sup,
+                min_inner_sup: _min_inner_sup,
+                max_inner_sup,
+                item,
+                edges_list,
+            });
+            return true;
+        }
+        self.emit(sup, _min_inner_sup, max_inner_sup, item, edges_list);
+        true
+    }
+
+    fn emit(
+        &mut self,
+        sup: usize,
+        min_inner_sup: usize,
+        max_inner_sup: usize,
+        item: PatternItem,
+        edges_list: Vec<Vec<&Edge>>,
+    ) {
         if self.channel {
-            self.send_result(sup, _min_inner_sup, max_inner_sup, &item, edges_list);
+            self.send_result(sup, min_inner_sup, max_inner_sup, &item, edges_list);
         } else if Option::is_some(&self.out) {
-            self.write_result(sup, _min_inner_sup, max_inner_sup, &item, edges_list);
+            self.write_result(sup, min_inner_sup, max_inner_sup, &item, edges_list);
         }
         self.value.push(item);
-        true
+    }
+
+    /**
+     * 挖掘结束后调用：Closed、Maximal 模式下对候选模式做超模式筛选并输出，随后结束输出流
+     * 超模式 Q ⊃ P 要求 Q 的边数严格大于 P，且 P 可以保持标签与方向地嵌入 Q
+     */
+    pub fn finish(&mut self) {
+        if self.pattern_mode.is_deferred() {
+            self.emit_candidates();
+        }
+        if let Some(out) = &mut self.out {
+            out.finish().expect("ERR: MaxDFSCodeGraphResult Stream");
+        }
+    }
+
+    fn emit_candidates(&mut self) {
+        let candidates = std::mem::take(&mut self.candidates);
+        let graphs: Vec<Graph> = candidates.iter().map(|c| c.item.0.to_graph(0, true)).collect();
+
+        let keep: Vec<bool> = (0..candidates.len())
+            .map(|i| {
+                !(0..candidates.len()).any(|j| {
+                    let (p, q) = (&candidates[i], &candidates[j]);
+                    q.item.0.dfs_vec.len() > p.item.0.dfs_vec.len()
+                        && (self.pattern_mode == PatternMode::Maximal
+                            || (q.sup == p.sup && q.max_inner_sup == p.max_inner_sup))
+                        && is_sub_pattern(&graphs[i], &graphs[j])
+                })
+            })
+            .collect();
+
+        for (candidate, keep) in candidates.into_iter().zip(keep) {
+            if !keep {
+                continue;
+            }
+            let Candidate { sup, min_inner_sup, max_inner_sup, item, edges_list } = candidate;
+            let edges_list = edges_list.iter().map(|es| es.iter().collect()).collect();
+            self.emit(sup, min_inner_sup, max_inner_sup, item, edges_list);
+        }
     }
 
     pub fn get_value_len(&self) -> usize {
         self.value.len()
     }
 
+    pub fn get_values(&self) -> &[PatternItem] {
+        &self.value
+    }
+
     pub fn get_result(&self) -> Vec<JSONResult> {
         self.value
             .iter()
-            .map(|v| {
+            .enumerate()
+            .map(|(id, v)| {
                 let instances =
                     v.1.iter()
                         .map(|set| {
-                            let node_ids = set
-                                .iter()
-                                .map(|p| NodeId { gid: p.0, nid: p.1.clone() })
-                                .collect::<Vec<NodeId>>();
+                            let node_ids = to_node_ids(&self.graphs, set);
                             return Instance { node_num: node_ids.len(), node_ids, edges: vec![] };
                         })
                         .collect::<Vec<Instance>>();
+                // 按图统计实例数，得到图间与图内支持度
+                let mut gid_counts: BTreeMap<usize, usize> = BTreeMap::new();
+                for set in &v.1 {
+                    if let Some((gid, _)) = set.iter().next() {
+                        *gid_counts.entry(*gid).or_default() += 1;
+                    }
+                }
+                let (pattern_id, structure) = pattern_structure(&v.0, id);
                 return JSONResult {
-                    between_sup: 0,
-                    inner_min_sup: 0,
-                    inner_max_sup: 0,
+                    between_sup: gid_counts.len(),
+                    inner_min_sup: gid_counts.values().copied().min().unwrap_or(0),
+                    inner_max_sup: gid_counts.values().copied().max().unwrap_or(0),
                     total: instances.len(),
-                    structure: Structure { tid: 0, vertices: vec![], edges: vec![] },
+                    pattern_id,
+                    structure,
                     instances,
                 };
             })
             .collect::<Vec<JSONResult>>()
     }
 
+    /// `ExactMis` 下是否有模式的图内支持度退化为贪心解，此时支持度只是下界
+    pub fn is_mis_fallback(&self) -> bool {
+        self.mis_fallback
+    }
+
     pub fn get_sum_subgraphs(&self) -> usize {
         self.value.iter().map(|e| e.1.len()).sum()
     }
@@ -189,7 +389,7 @@ impl MaxDFSCodeGraphResult {
         sup: usize,
         min_inner_sup: usize,
         max_inner_sup: usize,
-        item: &(DFSCode, Vec<FxHashSet<(usize, String)>>),
+        item: &PatternItem,
         edges_list: Vec<Vec<&Edge>>,
     ) {
         let id = self.value.len();
@@ -197,14 +397,28 @@ impl MaxDFSCodeGraphResult {
             match out_type {
                 OutType::TXT =>
                     if let Some(sender) = &mut self.sender {
-                        let line =
-                            report_txt(id, sup, min_inner_sup, max_inner_sup, item, edges_list);
+                        let line = report_txt(
+                            &self.graphs,
+                            id,
+                            sup,
+                            min_inner_sup,
+                            max_inner_sup,
+                            item,
+                            edges_list,
+                        );
                         sender.send(line).unwrap();
                     },
-                OutType::JSON =>
+                OutType::JSON | OutType::NDJSON =>
                     if let Some(sender) = &mut self.sender {
-                        let line =
-                            report_json(id, sup, min_inner_sup, max_inner_sup, item, edges_list);
+                        let line = report_json(
+                            &self.graphs,
+                            id,
+                            sup,
+                            min_inner_sup,
+                            max_inner_sup,
+                            item,
+                            edges_list,
+                        );
                         sender.send(line).expect("ERR: MaxDFSCodeGraphResult Channel");
                     },
             }
@@ -215,7 +429,7 @@ impl MaxDFSCodeGraphResult {
 impl MaxDFSCodeGraphResult {
     pub fn set_stream<W: Write + Send + Sync + 'static>(&mut self, out: W) {
         self.channel = false;
-        self.out = Some(Box::new(out));
+        self.out = Some(ResultWriter::new(out, self.out_type.clone().unwrap_or(OutType::TXT)));
     }
 
     fn write_result(
@@ -223,48 +437,42 @@ impl MaxDFSCodeGraphResult {
         sup: usize,
         min_inner_sup: usize,
         max_inner_sup: usize,
-        item: &(DFSCode, Vec<FxHashSet<(usize, String)>>),
+        item: &PatternItem,
         edges_list: Vec<Vec<&Edge>>,
     ) {
         let id = self.value.len();
-        if let Some(out_type) = &self.out_type {
-            match out_type {
-                OutType::TXT => {
-                    if let Some(out) = &mut self.out {
-                        let line =
-                            report_txt(id, sup, min_inner_sup, max_inner_sup, item, edges_list);
-                        out.write(&*line.into_bytes()).expect("ERR: MaxDFSCodeGraphResult Stream");
-
-                        // 刷新缓冲区，确保所有数据都被写出
-                        if let Err(e) = out.flush() {
-                            eprintln!("Failed to flush output after writing lines: {}", e);
-                        }
-                    }
-                }
-                OutType::JSON => {
-                    if let Some(out) = &mut self.out {
-                        let line =
-                            report_json(id, sup, min_inner_sup, max_inner_sup, item, edges_list);
-                        out.write(&*line.into_bytes()).expect("ERR: MaxDFSCodeGraphResult Stream");
-                        out.write(b",\n").expect("ERR: MaxDFSCodeGraphResult Stream");
-
-                        // 刷新缓冲区，确保所有数据都被写出
-                        if let Err(e) = out.flush() {
-                            eprintln!("Failed to flush output after writing lines: {}", e);
-                        }
-                    }
-                }
-            }
+        if let (Some(out_type), Some(out)) = (&self.out_type, &mut self.out) {
+            let graphs = &self.graphs;
+            let record = match out_type {
+                OutType::TXT =>
+                    report_txt(graphs, id, sup, min_inner_sup, max_inner_sup, item, edges_list),
+                OutType::JSON | OutType::NDJSON =>
+                    report_json(graphs, id, sup, min_inner_sup, max_inner_sup, item, edges_list),
+            };
+            out.write_record(&record).expect("ERR: MaxDFSCodeGraphResult Stream");
         }
     }
 }
 
+// 将 (gid, 顶点下标) 还原为顶点名，找不到对应的图时退化为下标
+fn vertex_name(graphs: &[Graph], gid: usize, id: usize) -> String {
+    graphs
+        .get(gid)
+        .and_then(|g| g.vertices.get(id))
+        .map_or_else(|| id.to_string(), |v| v.name.clone())
+}
+
+fn to_node_ids(graphs: &[Graph], set: &FxHashSet<(usize, usize)>) -> Vec<NodeId> {
+    set.iter().map(|&(gid, id)| NodeId { gid, nid: vertex_name(graphs, gid, id) }).collect()
+}
+
 fn report_txt(
+    graphs: &[Graph],
     id: usize,
     sup: usize,
     min_inner_sup: usize,
     max_inner_sup: usize,
-    item: &(DFSCode, Vec<FxHashSet<(usize, String)>>),
+    item: &PatternItem,
     edges_list: Vec<Vec<&Edge>>,
 ) -> String {
     let mut lines: Vec<String> = vec![];
@@ -278,17 +486,22 @@ fn report_txt(
     for (index, line) in item.1.iter().enumerate() {
         let vertex_content = line
             .iter()
-            .map(|l| format!("{}/{}", l.0, &l.1))
+            .map(|&(gid, id)| format!("{}/{}", gid, vertex_name(graphs, gid, id)))
             .collect::<Vec<String>>()
             .join(", ");
         lines.push(format!("${}| {}\n", line.len(), vertex_content));
 
+        let gid = line.iter().next().map_or(0, |l| l.0);
         let edge_content = edges_list[index]
             .iter()
             .map(|e| {
                 format!(
                     " e| {}/{}-{}-{}/{}",
-                    &e.from, &e.from_label, &e.e_label, &e.to, &e.to_label
+                    vertex_name(graphs, gid, e.from),
+                    &e.from_label,
+                    &e.e_label,
+                    vertex_name(graphs, gid, e.to),
+                    &e.to_label
                 )
             })
             .collect::<Vec<String>>()
@@ -300,55 +513,43 @@ fn report_txt(
     lines.join("")
 }
 
+// 返回 (模式 id, 模式结构)
+fn pattern_structure(dfs_code: &DFSCode, id: usize) -> (String, Structure) {
+    let graph = dfs_code.to_graph(id, false);
+    (pattern_hash(&graph), graph.to_structure())
+}
+
 fn report_json(
+    graphs: &[Graph],
     id: usize,
     sup: usize,
     min_inner_sup: usize,
     max_inner_sup: usize,
-    item: &(DFSCode, Vec<FxHashSet<(usize, String)>>),
+    item: &PatternItem,
     edges_list: Vec<Vec<&Edge>>,
 ) -> String {
-    let g = item.0.to_graph(id, false);
-
-    let mut output_vertices: Vec<OutputVertex> = Vec::new();
-    let mut output_edges: Vec<OutputEdge> = Vec::new();
-    for vertex in &g.vertices {
-        output_vertices
-            .push(OutputVertex { name: vertex.name.clone(), label: vertex.label.clone() });
-        output_edges.extend(vertex.edges.iter().map(|e| OutputEdge {
-            from: e.from.clone(),
-            to: e.to.clone(),
-            from_label: e.from_label.clone(),
-            to_label: e.to_label.clone(),
-            e_label: e.e_label.clone(),
-        }));
-    }
-
+    let (pattern_id, structure) = pattern_structure(&item.0, id);
     let mut json_result = JSONResult {
         between_sup: sup,
         inner_min_sup: min_inner_sup,
         inner_max_sup: max_inner_sup,
         total: item.1.len(),
-        structure: Structure { tid: g.id, vertices: output_vertices, edges: output_edges },
+        pattern_id,
+        structure,
         instances: vec![],
     };
 
     for (index, line) in item.1.iter().enumerate() {
-        let node_ids = line
-            .iter()
-            .map(|l| NodeId { gid: l.0, nid: l.1.clone() })
-            .collect::<Vec<NodeId>>();
-
-        let edges = edges_list[index]
-            .iter()
-            .map(|e| OutputEdge {
-                from: e.from.clone(),
-                to: e.to.clone(),
-                from_label: e.from_label.clone(),
-                to_label: e.to_label.clone(),
-                e_label: e.e_label.clone(),
-            })
-            .collect::<Vec<OutputEdge>>();
+        let node_ids = to_node_ids(graphs, line);
+
+        let gid = line.iter().next().map_or(0, |l| l.0);
+        let edges = match graphs.get(gid) {
+            Some(graph) => edges_list[index]
+                .iter()
+                .map(|e| OutputEdge::from_graph_edge(e, graph))
+                .collect::<Vec<OutputEdge>>(),
+            None => vec![],
+        };
 
         json_result
             .instances
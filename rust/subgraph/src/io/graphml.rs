@@ -0,0 +1,249 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use roxmltree::{Document, Node as XmlNode};
+
+use crate::gspan::models::{
+    graph::{Graph, GraphSetParseError},
+    vertex::Vertex,
+};
+
+const GRAPHML_NS: &str = "http://graphml.graphdrawing.org/xmlns";
+
+// 作为顶点/边标签读取的 <key> 属性名，按优先级排列
+const LABEL_ATTR_NAMES: [&str; 3] = ["label", "opType", "type"];
+
+/**
+ * 读取 GraphML，文件中的每个 <graph> 对应一张图，id 按出现顺序从 0 开始分配
+ * - 顶点/边标签取 `attr.name` 为 label、opType 或 type 的 <data>，缺省时为 `<NIL>`
+ * - 方向由 `edgedefault` 决定，单条边可以用 `directed` 属性覆盖；无向边按两个方向分别存储
+ */
+pub fn read_graphml(content: &str) -> Result<Vec<Graph>, GraphSetParseError> {
+    let doc = Document::parse(content)
+        .map_err(|e| GraphSetParseError::new(format!("Error parsing graphml : {}", e)))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "graphml" {
+        return Err(GraphSetParseError::new(String::from("Error parsing graphml : no <graphml>")));
+    }
+
+    let node_key = label_key(root, "node");
+    let edge_key = label_key(root, "edge");
+
+    let mut graphs: Vec<Graph> = Vec::new();
+    for element in root.children().filter(is_graph) {
+        let directed = element.attribute("edgedefault") != Some("undirected");
+        let mut graph = Graph::new(graphs.len(), directed);
+        graph.name = element.attribute("id").unwrap_or_default().to_string();
+
+        let mut edges: Vec<(String, String, Option<String>)> = Vec::new();
+        for child in element.children().filter(|n| n.is_element()) {
+            match child.tag_name().name() {
+                "node" => {
+                    let name = child.attribute("id").ok_or_else(|| {
+                        GraphSetParseError::new(String::from("Error parsing graphml : node id"))
+                    })?;
+                    if graph.vertex_index.contains_key(name) {
+                        return Err(GraphSetParseError::new(format!(
+                            "Error parsing graphml : duplicate node {}",
+                            name
+                        )));
+                    }
+                    let label = data_value(child, node_key).unwrap_or(Vertex::NIL_V_LABEL);
+                    graph.insert_vertex(name, label);
+                }
+                "edge" => {
+                    let (Some(from), Some(to)) =
+                        (child.attribute("source"), child.attribute("target"))
+                    else {
+                        return Err(GraphSetParseError::new(String::from(
+                            "Error parsing graphml : edge source/target",
+                        )));
+                    };
+                    let edge_directed = match child.attribute("directed") {
+                        Some(value) => value == "true",
+                        None => directed,
+                    };
+                    let e_label = data_value(child, edge_key).map(str::to_string);
+                    // 无向边按两个方向分别存储
+                    if !edge_directed && from != to {
+                        edges.push((to.to_string(), from.to_string(), e_label.clone()));
+                    }
+                    edges.push((from.to_string(), to.to_string(), e_label));
+                }
+                _ => {}
+            }
+        }
+
+        for (from, to, _) in &edges {
+            for name in [from, to] {
+                if !graph.vertex_index.contains_key(name) {
+                    return Err(GraphSetParseError::new(format!(
+                        "Error parsing graphml : unknown node {}",
+                        name
+                    )));
+                }
+            }
+        }
+        graph.build_edge(edges);
+
+        graphs.push(graph);
+    }
+    Ok(graphs)
+}
+
+fn is_graph(node: &XmlNode) -> bool {
+    node.is_element() && node.tag_name().name() == "graph"
+}
+
+fn label_key<'a>(root: XmlNode<'a, 'a>, domain: &str) -> Option<&'a str> {
+    let keys: Vec<(&str, &str)> = root
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "key")
+        .filter(|n| matches!(n.attribute("for"), Some(d) if d == domain || d == "all"))
+        .filter_map(|n| Some((n.attribute("id")?, n.attribute("attr.name")?)))
+        .collect();
+    LABEL_ATTR_NAMES
+        .iter()
+        .find_map(|name| keys.iter().find(|(_, attr)| attr == name).map(|(id, _)| *id))
+}
+
+fn data_value<'a>(element: XmlNode<'a, 'a>, key: Option<&str>) -> Option<&'a str> {
+    let key = key?;
+    element
+        .children()
+        .find(|n| {
+            n.is_element() && n.tag_name().name() == "data" && n.attribute("key") == Some(key)
+        })
+        .map(|n| n.text().unwrap_or_default().trim())
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/**
+ * 写出 GraphML，顶点 id 使用顶点名，标签写入 `label` 属性
+ * 无向图中成对存储的反向边只写出一次，可由 [`read_graphml`] 原样读回
+ */
+pub fn write_graphml(graphs: &[Graph]) -> String {
+    let mut lines: Vec<String> = vec![
+        String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+        format!(r#"<graphml xmlns="{}">"#, GRAPHML_NS),
+        String::from(r#"  <key id="v_label" for="node" attr.name="label" attr.type="string"/>"#),
+        String::from(r#"  <key id="e_label" for="edge" attr.name="label" attr.type="string"/>"#),
+    ];
+    for graph in graphs {
+        let id = if graph.name.is_empty() { format!("g{}", graph.id) } else { escape(&graph.name) };
+        let edgedefault = if graph.directed { "directed" } else { "undirected" };
+        lines.push(format!(r#"  <graph id="{}" edgedefault="{}">"#, id, edgedefault));
+        for vertex in &graph.vertices {
+            lines.push(format!(
+                r#"    <node id="{}"><data key="v_label">{}</data></node>"#,
+                escape(&vertex.name),
+                escape(&vertex.label)
+            ));
+        }
+        for (index, edge) in graph.output_edges().iter().enumerate() {
+            lines.push(format!(
+                r#"    <edge id="e{}" source="{}" target="{}"><data key="e_label">{}</data></edge>"#,
+                index,
+                escape(graph.vertex_name(edge.from)),
+                escape(graph.vertex_name(edge.to)),
+                escape(&edge.e_label)
+            ));
+        }
+        lines.push(String::from("  </graph>"));
+    }
+    lines.push(String::from("</graphml>"));
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gspan::models::graph::fixture::edge_triples;
+
+    #[test]
+    fn test_read_graphml() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d0" for="node" attr.name="opType" attr.type="string"/>
+  <key id="d1" for="edge" attr.name="label" attr.type="string"/>
+  <graph id="first" edgedefault="directed">
+    <node id="conv"><data key="d0">Conv</data></node>
+    <node id="relu"><data key="d0">Relu</data></node>
+    <node id="add"/>
+    <edge source="conv" target="relu"><data key="d1">0</data></edge>
+    <edge source="relu" target="add" directed="false"/>
+  </graph>
+  <graph id="second" edgedefault="undirected">
+    <node id="a"><data key="d0">A</data></node>
+    <node id="b"><data key="d0">B</data></node>
+    <edge source="a" target="b"/>
+  </graph>
+</graphml>"#;
+        let graphs = read_graphml(content).unwrap();
+        assert_eq!(2, graphs.len());
+
+        assert_eq!("first", graphs[0].name);
+        assert_eq!("Conv", graphs[0].find_vertex("conv").unwrap().label);
+        assert_eq!(Vertex::NIL_V_LABEL, graphs[0].find_vertex("add").unwrap().label);
+        assert_eq!(
+            vec![
+                ("add".to_string(), "relu".to_string(), "<NIL>".to_string()),
+                ("conv".to_string(), "relu".to_string(), "0".to_string()),
+                ("relu".to_string(), "add".to_string(), "<NIL>".to_string()),
+            ],
+            edge_triples(&graphs[0])
+        );
+        assert!(graphs[0].directed);
+
+        assert_eq!(1, graphs[1].id);
+        assert!(!graphs[1].directed);
+        assert_eq!(2, graphs[1].edge_size);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for directed in [true, false] {
+            let mut graph = Graph::new(0, directed);
+            graph.name = String::from("a&b");
+            graph.insert_vertex("x", "Mul<2>");
+            graph.insert_vertex("y", "Add");
+            graph.build_edge_by_direction(vec![
+                ("x".to_string(), "y".to_string(), Some("0:1".to_string())),
+                ("x".to_string(), "y".to_string(), Some("0:0".to_string())),
+            ]);
+
+            let graphs = read_graphml(&write_graphml(&[graph.clone()])).unwrap();
+            assert_eq!(1, graphs.len());
+            assert_eq!(graph.name, graphs[0].name);
+            assert_eq!(directed, graphs[0].directed);
+            assert_eq!("Mul<2>", graphs[0].find_vertex("x").unwrap().label);
+            assert_eq!(edge_triples(&graph), edge_triples(&graphs[0]));
+        }
+    }
+
+    #[test]
+    fn test_invalid_graphml() {
+        assert!(read_graphml("<graph/>").is_err());
+        assert!(read_graphml("<graphml><graph>").is_err());
+        assert!(
+            read_graphml(
+                r#"<graphml><graph><node id="a"/><edge source="a" target="b"/></graph></graphml>"#
+            )
+            .is_err()
+        );
+    }
+}
@@ -1,6 +1,10 @@
 /*
  * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
  */
+pub mod graphml;
 pub mod model_graph;
 pub mod node;
 pub mod output;
+pub mod pattern;
+pub mod transaction;
+pub mod writer;
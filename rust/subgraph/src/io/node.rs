@@ -11,4 +11,6 @@ pub struct Node {
     // pub op_type: String,
     pub opType: String,
     pub input: Vec<String>,
+    #[serde(default)]
+    pub output: Vec<String>,
 }
@@ -3,6 +3,8 @@
  */
 use serde::{Deserialize, Serialize};
 
+use crate::gspan::models::{edge::Edge as GraphEdge, graph::Graph};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Vertex {
     pub name: String,
@@ -13,13 +15,30 @@ pub struct Vertex {
 pub struct Edge {
     pub from: String,
     pub to: String,
+    #[serde(default)]
     pub from_label: String,
+    #[serde(default)]
     pub to_label: String,
+    #[serde(default)]
     pub e_label: String,
 }
 
+impl Edge {
+    // 图中的边只记录顶点下标，需要借助所属的图还原顶点名
+    pub fn from_graph_edge(e: &GraphEdge, graph: &Graph) -> Self {
+        Edge {
+            from: graph.vertex_name(e.from).to_string(),
+            to: graph.vertex_name(e.to).to_string(),
+            from_label: e.from_label.clone(),
+            to_label: e.to_label.clone(),
+            e_label: e.e_label.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Structure {
+    #[serde(default)]
     pub tid: usize,
     pub vertices: Vec<Vertex>,
     pub edges: Vec<Edge>,
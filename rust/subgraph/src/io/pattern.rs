@@ -0,0 +1,119 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gspan::{
+        canonical::{dfs_code_text, min_dfs_code, pattern_hash},
+        models::{edge::Edge, graph::Graph},
+    },
+    io::output::Structure,
+};
+
+/// 模式导出格式
+/// - `Json`: 独立的模式文件，见 [`PatternFile`]
+/// - `Dot`: Graphviz DOT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternFormat {
+    #[default]
+    Json,
+    Dot,
+}
+
+/// 独立保存的模式
+/// - `id`: 规范编码的哈希，同一模式在不同模型、不同运行中相同
+/// - `dfs_code`: 规范编码文本，可直接作为查询模式使用
+/// - `structure`: 按规范编码重新编号的模式图，顶点名为发现序
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PatternFile {
+    pub id: String,
+    pub dfs_code: String,
+    pub structure: Structure,
+}
+
+impl PatternFile {
+    pub fn from_graph(graph: &Graph) -> PatternFile {
+        let dfs_code = min_dfs_code(graph);
+        let canonical = dfs_code.to_graph(0, true);
+        PatternFile {
+            id: pattern_hash(graph),
+            dfs_code: dfs_code_text(&dfs_code),
+            structure: canonical.to_structure(),
+        }
+    }
+
+    pub fn export(&self, format: PatternFormat) -> String {
+        match format {
+            PatternFormat::Json =>
+                serde_json::to_string_pretty(self).expect("Err: Serialization failed"),
+            PatternFormat::Dot => self.to_dot(),
+        }
+    }
+
+    // 边标签为 `<NIL>` 时不输出
+    pub fn to_dot(&self) -> String {
+        let mut lines: Vec<String> = vec![format!("digraph \"pattern_{}\" {{", self.id)];
+        lines.push(String::from("  node [shape=box];"));
+        for vertex in &self.structure.vertices {
+            lines.push(format!(
+                "  \"{}\" [label=\"{}\"];",
+                escape(&vertex.name),
+                escape(&vertex.label)
+            ));
+        }
+        for edge in &self.structure.edges {
+            let mut line = format!("  \"{}\" -> \"{}\"", escape(&edge.from), escape(&edge.to));
+            if !edge.e_label.is_empty() && edge.e_label != Edge::NIL_E_LABEL {
+                line += &format!(" [label=\"{}\"]", escape(&edge.e_label));
+            }
+            lines.push(line + ";");
+        }
+        lines.push(String::from("}"));
+        lines.join("\n") + "\n"
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gspan::models::graph::fixture::build_graph;
+
+    fn residual([conv, relu, add]: [&str; 3]) -> Graph {
+        build_graph(
+            &[(conv, "Conv"), (relu, "Relu"), (add, "Add")],
+            &[(conv, relu, Edge::NIL_E_LABEL), (relu, add, "0:0"), (conv, add, "0:1")],
+        )
+    }
+
+    #[test]
+    fn test_pattern_file() {
+        let a = PatternFile::from_graph(&residual(["conv", "relu", "add"]));
+        let b = PatternFile::from_graph(&residual(["n2", "n0", "n1"]));
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.dfs_code, b.dfs_code);
+
+        let json = a.export(PatternFormat::Json);
+        let parsed: PatternFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(a.id, parsed.id);
+        assert_eq!(3, parsed.structure.vertices.len());
+        assert_eq!(3, parsed.structure.edges.len());
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = PatternFile::from_graph(&residual(["conv", "relu", "add"])).to_dot();
+        assert!(dot.starts_with("digraph \"pattern_"));
+        assert!(dot.contains("[label=\"Conv\"]"));
+        assert!(dot.contains("[label=\"0:1\"];"));
+        // `<NIL>` 边标签不输出
+        let plain = dot.lines().filter(|l| l.contains("->") && !l.contains("[label")).count();
+        assert_eq!(1, plain);
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
@@ -0,0 +1,148 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use crate::gspan::models::graph::{Graph, GraphSetParseError};
+
+/**
+ * 读取 gSpan 事务格式（多图）：
+ * ```text
+ * t # 0
+ * v 0 A
+ * v 1 B
+ * e 0 1 x
+ * t # -1
+ * ```
+ * - `e` 行支持经典的 `e from to e_label`、省略标签的 `e from to`，以及
+ *   [`Graph::to_str_repr`] 输出的 `e from to from_label to_label e_label`
+ * - `t # -1` 表示结束；图的 id 按出现顺序从 0 开始分配，与挖掘过程中的 gid 一致
+ * - `directed` 为 false 时每条边按两个方向分别存储
+ */
+pub fn read_transactions(content: &str, directed: bool) -> Result<Vec<Graph>, GraphSetParseError> {
+    let mut graphs: Vec<Graph> = Vec::new();
+    let mut edges: Vec<(String, String, Option<String>)> = Vec::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let error = |message: &str| {
+            GraphSetParseError::new(format!("line {}: {} : {}", line_no + 1, message, line))
+        };
+        match fields.first() {
+            None => continue,
+            Some(&"t") => {
+                if let Some(graph) = graphs.last_mut() {
+                    graph.build_edge_by_direction(std::mem::take(&mut edges));
+                }
+                if fields.get(2).is_some_and(|id| id.starts_with('-')) {
+                    return Ok(graphs);
+                }
+                graphs.push(Graph::new(graphs.len(), directed));
+            }
+            Some(&"v") => {
+                let graph = graphs.last_mut().ok_or_else(|| error("vertex before 't'"))?;
+                let [_, name, label] = fields[..] else {
+                    return Err(error("invalid vertex"));
+                };
+                if graph.vertex_index.contains_key(name) {
+                    return Err(error("duplicate vertex"));
+                }
+                graph.insert_vertex(name, label);
+            }
+            Some(&"e") => {
+                let graph = graphs.last().ok_or_else(|| error("edge before 't'"))?;
+                let (from, to, e_label) = match fields[..] {
+                    [_, from, to] => (from, to, None),
+                    [_, from, to, e_label] | [_, from, to, _, _, e_label] => {
+                        (from, to, Some(e_label.to_string()))
+                    }
+                    _ => return Err(error("invalid edge")),
+                };
+                for name in [from, to] {
+                    if !graph.vertex_index.contains_key(name) {
+                        return Err(error("unknown vertex"));
+                    }
+                }
+                edges.push((from.to_string(), to.to_string(), e_label));
+            }
+            // 兼容挖掘结果中实例行等其它内容
+            Some(_) => continue,
+        }
+    }
+    if let Some(graph) = graphs.last_mut() {
+        graph.build_edge_by_direction(edges);
+    }
+    Ok(graphs)
+}
+
+/**
+ * 写出经典 gSpan 事务格式，顶点按其在图中的下标编号，以 `t # -1` 结束
+ * 无向图中成对存储的反向边只写出一次，可由 [`read_transactions`] 原样读回
+ */
+pub fn write_transactions(graphs: &[Graph]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for graph in graphs {
+        lines.push(format!("t # {}", graph.id));
+        for vertex in &graph.vertices {
+            lines.push(format!("v {} {}", vertex.id, vertex.label));
+        }
+        for edge in graph.output_edges() {
+            lines.push(format!("e {} {} {}", edge.from, edge.to, edge.e_label));
+        }
+    }
+    lines.push(String::from("t # -1"));
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gspan::models::graph::fixture::edge_triples;
+
+    const CLASSIC: &str =
+        "t # 0\nv 0 2\nv 1 3\nv 2 2\ne 0 1 4\ne 1 2 4\nt # 1\nv 0 2\nv 1 3\ne 0 1 4\nt # -1\n";
+
+    #[test]
+    fn test_read_classic_undirected() {
+        let graphs = read_transactions(CLASSIC, false).unwrap();
+        assert_eq!(2, graphs.len());
+        assert_eq!(vec![0, 1], graphs.iter().map(|g| g.id).collect::<Vec<usize>>());
+        assert_eq!(3, graphs[0].vertices.len());
+        // 无向边按两个方向存储
+        assert_eq!(4, graphs[0].edge_size);
+        assert_eq!(2, graphs[1].edge_size);
+        assert_eq!("3", graphs[0].find_vertex("1").unwrap().label);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for directed in [true, false] {
+            let graphs = read_transactions(CLASSIC, directed).unwrap();
+            let text = write_transactions(&graphs);
+            assert_eq!(CLASSIC, text);
+            let again = read_transactions(&text, directed).unwrap();
+            for (a, b) in graphs.iter().zip(again.iter()) {
+                assert_eq!(edge_triples(a), edge_triples(b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_str_repr() {
+        let mut graph = Graph::new(0, true);
+        graph.insert_vertex("conv", "Conv");
+        graph.insert_vertex("relu", "Relu");
+        graph.build_edge(vec![("conv".to_string(), "relu".to_string(), None)]);
+
+        let graphs = read_transactions(&graph.to_str_repr(None), true).unwrap();
+        assert_eq!(1, graphs.len());
+        assert_eq!(edge_triples(&graph), edge_triples(&graphs[0]));
+    }
+
+    #[test]
+    fn test_invalid_transactions() {
+        assert!(read_transactions("v 0 A\n", true).is_err());
+        assert!(read_transactions("t # 0\nv 0\n", true).is_err());
+        assert!(read_transactions("t # 0\nv 0 A\nv 0 B\n", true).is_err());
+        assert!(read_transactions("t # 0\nv 0 A\ne 0 1 x\n", true).is_err());
+        assert!(read_transactions("t # 0\nv 0 A\nv 1 A\ne 0 1 x y\n", true).is_err());
+    }
+}
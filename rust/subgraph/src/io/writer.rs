@@ -0,0 +1,154 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use std::io::{self, Write};
+
+use crate::gspan::result::OutType;
+
+/**
+ * 结果输出流，按输出类型为每条记录加上分隔：
+ * - `JSON`: 自行写出 `[`、`,` 与 `]`，没有结果时输出 `[]`
+ * - `NDJSON`: 每行一条记录
+ * - `TXT`: 原样写出
+ *
+ * `finish` 只生效一次，`Drop` 时会自动调用，因此挖掘中途取消也能得到合法的输出
+ */
+pub struct ResultWriter {
+    out: Box<dyn Write + Send + Sync>,
+    out_type: OutType,
+    count: usize,
+    finished: bool,
+}
+
+impl ResultWriter {
+    pub fn new<W: Write + Send + Sync + 'static>(out: W, out_type: OutType) -> ResultWriter {
+        ResultWriter { out: Box::new(out), out_type, count: 0, finished: false }
+    }
+
+    // 只能在写出第一条记录前修改
+    pub fn set_out_type(&mut self, out_type: OutType) {
+        if self.count == 0 {
+            self.out_type = out_type;
+        }
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    pub fn write_record(&mut self, record: &str) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::other("ResultWriter already finished"));
+        }
+        match self.out_type {
+            OutType::TXT => self.out.write_all(record.as_bytes())?,
+            OutType::JSON => {
+                self.out.write_all(if self.count == 0 { b"[\n" } else { b",\n" })?;
+                self.out.write_all(record.as_bytes())?;
+            }
+            OutType::NDJSON => {
+                self.out.write_all(record.as_bytes())?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        self.count += 1;
+        // 刷新缓冲区，确保所有数据都被写出
+        self.out.flush()
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if let OutType::JSON = self.out_type {
+            self.out.write_all(if self.count == 0 { b"[]\n" } else { b"\n]\n" })?;
+        }
+        self.out.flush()
+    }
+}
+
+impl Drop for ResultWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("Failed to finish result output: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+
+    use super::*;
+
+    // 可在 ResultWriter 释放后读取内容的缓冲区
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn content(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_json_array() {
+        let buffer = SharedBuffer::default();
+        let mut writer = ResultWriter::new(buffer.clone(), OutType::JSON);
+        writer.write_record(r#"{"id":0}"#).unwrap();
+        writer.write_record(r#"{"id":1}"#).unwrap();
+        writer.finish().unwrap();
+        writer.finish().unwrap();
+        assert!(writer.write_record(r#"{"id":2}"#).is_err());
+        drop(writer);
+
+        let value: Value = serde_json::from_str(&buffer.content()).unwrap();
+        assert_eq!(2, value.as_array().unwrap().len());
+        assert_eq!(1, value[1]["id"]);
+    }
+
+    #[test]
+    fn test_empty_json_array() {
+        let buffer = SharedBuffer::default();
+        drop(ResultWriter::new(buffer.clone(), OutType::JSON));
+        assert_eq!("[]\n", buffer.content());
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let buffer = SharedBuffer::default();
+        let mut writer = ResultWriter::new(buffer.clone(), OutType::NDJSON);
+        writer.write_record(r#"{"id":0}"#).unwrap();
+        writer.write_record(r#"{"id":1}"#).unwrap();
+        drop(writer);
+
+        let lines: Vec<Value> =
+            buffer.content().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(2, lines.len());
+        assert_eq!(0, lines[0]["id"]);
+    }
+
+    #[test]
+    fn test_drop_closes_array() {
+        // 模拟挖掘中途取消：未调用 finish 直接释放
+        let buffer = SharedBuffer::default();
+        let mut writer = ResultWriter::new(buffer.clone(), OutType::JSON);
+        writer.write_record(r#"{"id":0}"#).unwrap();
+        drop(writer);
+        let value: Value = serde_json::from_str(&buffer.content()).unwrap();
+        assert_eq!(1, value.as_array().unwrap().len());
+    }
+}
@@ -7,16 +7,40 @@ pub use gspan::*;
 pub mod io;
 pub use io::*;
 
+pub mod query;
+
 pub mod strategy;
-use parser::{Model, Node, parse_geir_model, parse_mindir_model, parse_onnx_model};
+use std::collections::{HashMap, HashSet};
+
+use parser::{
+    Dim, EdgeKind, Model, Node, TensorShape, dtype_size, infer_shapes, parse_anf_ir_model,
+    parse_geir_model, parse_mindir_model, parse_onnx_model, parse_tf_model,
+};
+use serde::Deserialize;
 pub use strategy::*;
 
 use crate::{
-    gspan::result::OutType,
-    io::{model_graph::ModelGraph, node::Node as ModelNode},
-    models::graph::Graph,
+    gspan::{
+        misc::SupportMode,
+        result::{OutType, PatternMode, SharedPattern},
+    },
+    io::{
+        model_graph::ModelGraph,
+        node::Node as ModelNode,
+        pattern::{PatternFile, PatternFormat},
+    },
+    models::{
+        edge::EdgeLabel,
+        graph::{Graph, GraphSetParseError},
+    },
+    query::{QueryPattern, QueryResult, query_graph},
     result::JSONResult,
-    strategy::{config::Config, gspan_mining::GSpanMining, mining_strategy::MiningStrategy},
+    strategy::{
+        config::Config,
+        fusion_report::{FusionReport, OutputTensor},
+        gspan_mining::GSpanMining,
+        mining_strategy::MiningStrategy,
+    },
 };
 
 impl From<parser::Model> for ModelGraph {
@@ -40,6 +64,48 @@ impl From<Node> for ModelNode {
             name: node.name.to_string(),
             opType: node.opType.to_string(),
             input: node.input.into_iter().map(|s| s.to_string()).collect(),
+            output: node.output.into_iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// 挖掘选项，未指定的字段使用默认值，与原有行为保持一致
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct SubgraphOptions {
+    pub edge_label: EdgeLabel,
+    pub pattern_mode: PatternMode,
+    pub support_mode: SupportMode,
+}
+
+fn mine_graphs(
+    graphs: Vec<Graph>,
+    min_support: usize,
+    min_inner_support: usize,
+    max_vertices: usize,
+    options: SubgraphOptions,
+) -> Option<Vec<JSONResult>> {
+    let gspan_mining = GSpanMining;
+
+    match Config::new_from_graphs(
+        graphs,
+        None,
+        None,
+        OutType::JSON,
+        min_support,
+        min_inner_support,
+        2,
+        max_vertices,
+    ) {
+        Ok(mut config) => {
+            config.set_pattern_mode(options.pattern_mode);
+            config.set_support_mode(options.support_mode);
+            let result = gspan_mining.run(config);
+            Some(result)
+        }
+        Err(e) => {
+            eprintln!("Failed to create config: {:?}", e);
+            None
         }
     }
 }
@@ -50,30 +116,48 @@ macro_rules! subgraph_command {
             path: &str,
             min_inner_support: usize,
             max_vertices: usize,
+            options: SubgraphOptions,
         ) -> Option<Vec<JSONResult>> {
-            let raw = $parse_func(path)?;
+            let raw = $parse_func(path).ok()?;
             let model_graph = ModelGraph::from(raw);
 
-            let graph = Graph::graph_from_model_graph(model_graph, true);
+            let graph = Graph::graph_from_model_graph(model_graph, true, options.edge_label);
 
-            let gspan_mining = GSpanMining;
+            mine_graphs(vec![graph], 1, min_inner_support, max_vertices, options)
+        }
+    };
+}
 
-            match Config::new_from_graphs(
-                vec![graph],
-                None,
-                None,
-                OutType::JSON,
-                1,
-                min_inner_support,
-                2,
-                max_vertices,
-            ) {
-                Ok(config) => {
-                    let result = gspan_mining.run(config);
-                    Some(result)
-                }
+subgraph_command!(subgraphs_mindir, parse_mindir_model);
+subgraph_command!(subgraphs_geir, parse_geir_model);
+subgraph_command!(subgraphs_onnx, parse_onnx_model);
+subgraph_command!(subgraphs_anf_ir, parse_anf_ir_model);
+subgraph_command!(subgraphs_tf, parse_tf_model);
+
+/// 模式查询选项，`edge_label` 需与模式中的边标签取值方式一致，`limit` 为最多返回的实例数
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct QueryOptions {
+    pub edge_label: EdgeLabel,
+    pub limit: Option<usize>,
+}
+
+macro_rules! query_command {
+    ($func_name:ident, $parse_func:ident) => {
+        pub fn $func_name(
+            path: &str,
+            pattern: &QueryPattern,
+            options: QueryOptions,
+        ) -> Option<QueryResult> {
+            let raw = $parse_func(path).ok()?;
+            let model_graph = ModelGraph::from(raw);
+
+            let graph = Graph::graph_from_model_graph(model_graph, true, options.edge_label);
+
+            match query_graph(&graph, pattern, options.limit) {
+                Ok(result) => Some(result),
                 Err(e) => {
-                    eprintln!("Failed to create config: {:?}", e);
+                    eprintln!("Failed to query pattern: {}", e);
                     None
                 }
             }
@@ -81,6 +165,269 @@ macro_rules! subgraph_command {
     };
 }
 
-subgraph_command!(subgraphs_mindir, parse_mindir_model);
-subgraph_command!(subgraphs_geir, parse_geir_model);
-subgraph_command!(subgraphs_onnx, parse_onnx_model);
+query_command!(query_mindir, parse_mindir_model);
+query_command!(query_geir, parse_geir_model);
+query_command!(query_onnx, parse_onnx_model);
+query_command!(query_anf_ir, parse_anf_ir_model);
+query_command!(query_tf, parse_tf_model);
+
+/// 融合候选报告：挖掘单个模型，按覆盖度对模式排序
+/// `outputs` 为各算子被消费的输出张量（以算子名为键），为空时不估算节省的内存流量
+pub fn fusion_report_model_graph(
+    model_graph: ModelGraph,
+    outputs: HashMap<String, Vec<OutputTensor>>,
+    min_inner_support: usize,
+    max_vertices: usize,
+    options: SubgraphOptions,
+) -> Option<FusionReport> {
+    let graph = Graph::graph_from_model_graph(model_graph, true, options.edge_label);
+    let results =
+        mine_graphs(vec![graph.clone()], 1, min_inner_support, max_vertices, options)?;
+    Some(FusionReport::build(&results, &[graph], &[outputs]))
+}
+
+/// 由形状推导后的数据边整理各算子被消费的输出张量（以算子名为键），
+/// 控制边不传递张量，不计入；形状未推导出或不是静态形状的张量大小未知
+fn output_tensors(model: &Model) -> HashMap<String, Vec<OutputTensor>> {
+    let mut outputs: HashMap<String, Vec<OutputTensor>> = HashMap::new();
+    for edge in model.typed_edges.iter().filter(|edge| edge.kind == EdgeKind::Data) {
+        let tensors = outputs.entry(edge.source.to_string()).or_default();
+        let index = match tensors.iter().position(|t| t.output == edge.output) {
+            Some(index) => index,
+            None => {
+                tensors.push(OutputTensor {
+                    output: edge.output,
+                    bytes: edge.shape.as_ref().and_then(tensor_size),
+                    consumers: HashSet::new(),
+                });
+                tensors.len() - 1
+            }
+        };
+        tensors[index].consumers.insert(edge.target.to_string());
+    }
+    outputs
+}
+
+/// 张量的字节数，存在非静态维度或元素类型没有固定大小时为 `None`
+fn tensor_size(shape: &TensorShape) -> Option<u64> {
+    let numel: Option<u64> = shape
+        .dims
+        .iter()
+        .map(|dim| match dim {
+            Dim::Static(value) => u64::try_from(*value).ok(),
+            _ => None,
+        })
+        .product();
+    Some(numel? * dtype_size(&shape.dtype)?)
+}
+
+macro_rules! fusion_report_command {
+    ($func_name:ident, $parse_func:ident) => {
+        pub fn $func_name(
+            path: &str,
+            min_inner_support: usize,
+            max_vertices: usize,
+            options: SubgraphOptions,
+        ) -> Option<FusionReport> {
+            let mut raw = $parse_func(path).ok()?;
+            infer_shapes(&mut raw);
+            let outputs = output_tensors(&raw);
+            let model_graph = ModelGraph::from(raw);
+
+            fusion_report_model_graph(
+                model_graph,
+                outputs,
+                min_inner_support,
+                max_vertices,
+                options,
+            )
+        }
+    };
+}
+
+fusion_report_command!(fusion_report_mindir, parse_mindir_model);
+fusion_report_command!(fusion_report_geir, parse_geir_model);
+fusion_report_command!(fusion_report_onnx, parse_onnx_model);
+fusion_report_command!(fusion_report_anf_ir, parse_anf_ir_model);
+fusion_report_command!(fusion_report_tf, parse_tf_model);
+
+/// 将模式导出为独立的 JSON 模式文件或 Graphviz DOT，模式可以是挖掘结果中的 `structure` 或 DFS 编码
+pub fn export_pattern(
+    pattern: &QueryPattern,
+    format: PatternFormat,
+) -> Result<String, GraphSetParseError> {
+    let graph = pattern.to_graph()?;
+    Ok(PatternFile::from_graph(&graph).export(format))
+}
+
+/// 多模型挖掘：按传入顺序为每个模型分配 gid，挖掘至少出现在 `min_models` 个模型中的公共模式
+/// `models` 为 (模型名, 模型) 列表，模型名用于在结果中标识包含该模式的模型
+pub fn subgraphs_models(
+    models: Vec<(String, Model)>,
+    min_models: usize,
+    max_vertices: usize,
+    options: SubgraphOptions,
+) -> Option<Vec<SharedPattern>> {
+    let model_graphs =
+        models.into_iter().map(|(name, model)| (name, ModelGraph::from(model))).collect();
+    subgraphs_model_graphs(model_graphs, min_models, max_vertices, options)
+}
+
+pub fn subgraphs_model_graphs(
+    model_graphs: Vec<(String, ModelGraph)>,
+    min_models: usize,
+    max_vertices: usize,
+    options: SubgraphOptions,
+) -> Option<Vec<SharedPattern>> {
+    let mut names = Vec::with_capacity(model_graphs.len());
+    let mut graphs = Vec::with_capacity(model_graphs.len());
+    for (gid, (name, model_graph)) in model_graphs.into_iter().enumerate() {
+        let mut graph = Graph::graph_from_model_graph(model_graph, true, options.edge_label);
+        graph.id = gid;
+        names.push(name);
+        graphs.push(graph);
+    }
+
+    let results = mine_graphs(graphs, min_models, 1, max_vertices, options)?;
+    Some(results.into_iter().map(|r| SharedPattern::from_result(r, &names)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // 按 (名称, 算子) 列表构造链式模型
+    fn chain_model(name: &str, ops: &[&str]) -> (String, ModelGraph) {
+        let nodes: HashMap<String, ModelNode> = ops
+            .iter()
+            .enumerate()
+            .map(|(i, op)| {
+                let node_name = format!("{}_{}", name, i);
+                let node = ModelNode {
+                    name: node_name.clone(),
+                    opType: op.to_string(),
+                    input: vec![],
+                    output: vec![],
+                };
+                (node_name, node)
+            })
+            .collect();
+        let edges = (1..ops.len())
+            .map(|i| (format!("{}_{}", name, i - 1), format!("{}_{}", name, i)))
+            .collect();
+        let model_graph =
+            ModelGraph { name: name.to_string(), nodes, edges, parameters: HashMap::new() };
+        (name.to_string(), model_graph)
+    }
+
+    #[test]
+    fn test_shared_patterns_across_models() {
+        let models = vec![
+            chain_model("resnet", &["Conv", "Mul", "Relu", "Tanh"]),
+            chain_model("vit", &["Add", "Conv", "Mul", "Relu"]),
+            chain_model("bert", &["Gelu", "MatMul", "Softmax"]),
+        ];
+
+        let patterns = subgraphs_model_graphs(models, 2, 4, SubgraphOptions {
+            pattern_mode: PatternMode::Maximal,
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Conv -> Mul -> Relu 只出现在前两个模型中
+        assert_eq!(1, patterns.len());
+        let shared = &patterns[0];
+        assert_eq!(2, shared.pattern.between_sup);
+        assert_eq!(3, shared.pattern.structure.vertices.len());
+        assert_eq!(
+            vec!["resnet", "vit"],
+            shared.models.iter().map(|m| m.name.as_str()).collect::<Vec<&str>>()
+        );
+        for instance in &shared.pattern.instances {
+            let gid = instance.node_ids[0].gid;
+            let prefix = &shared.models.iter().find(|m| m.gid == gid).unwrap().name;
+            assert!(instance.node_ids.iter().all(|n| n.nid.starts_with(prefix.as_str())));
+        }
+    }
+
+    #[test]
+    fn test_pattern_id_stable_across_models() {
+        // 两个模型中的 Conv -> Mul -> Relu 顶点名与位置都不同，模式 id 应相同
+        let pattern_id = |name: &str, ops: &[&str]| {
+            let (_, model_graph) = chain_model(name, ops);
+            let graph = Graph::graph_from_model_graph(model_graph, true, EdgeLabel::Nil);
+            let results = mine_graphs(vec![graph], 1, 1, 3, SubgraphOptions {
+                pattern_mode: PatternMode::All,
+                ..Default::default()
+            })
+            .unwrap();
+            let result = results
+                .into_iter()
+                .find(|r| {
+                    let labels: Vec<&str> =
+                        r.structure.vertices.iter().map(|v| v.label.as_str()).collect();
+                    labels.len() == 3 && labels.contains(&"Conv") && labels.contains(&"Relu")
+                })
+                .unwrap();
+            let exported = export_pattern(&QueryPattern::Json(result.structure), PatternFormat::Json)
+                .unwrap();
+            let file: PatternFile = serde_json::from_str(&exported).unwrap();
+            assert_eq!(result.pattern_id, file.id);
+            result.pattern_id
+        };
+        assert_eq!(
+            pattern_id("resnet", &["Conv", "Mul", "Relu"]),
+            pattern_id("vit", &["Add", "Conv", "Mul", "Relu"])
+        );
+    }
+
+    #[test]
+    fn test_output_tensors() {
+        use parser::Edge;
+
+        let edge = |source: &str, output: usize, target: &str, dims: Option<Vec<Dim>>| Edge {
+            output,
+            shape: dims.map(|dims| TensorShape::new("Float", dims)),
+            ..Edge::new(source.into(), target.into())
+        };
+        let mut control = edge("split", 0, "relu", None);
+        control.kind = EdgeKind::Control;
+        let model = Model {
+            name: "test".into(),
+            nodes: Default::default(),
+            edges: vec![],
+            typed_edges: vec![
+                edge("split", 0, "add", Some(vec![Dim::Static(2), Dim::Static(3)])),
+                edge("split", 0, "mul", Some(vec![Dim::Static(2), Dim::Static(3)])),
+                edge("split", 1, "mul", Some(vec![Dim::Static(2), Dim::Unknown])),
+                control,
+            ],
+            parameters: Default::default(),
+            value_info: Default::default(),
+            info: Default::default(),
+        };
+
+        let outputs = output_tensors(&model);
+        assert_eq!(vec!["split"], outputs.keys().collect::<Vec<_>>());
+        let tensors = &outputs["split"];
+        assert_eq!(2, tensors.len());
+        assert_eq!((0, Some(24)), (tensors[0].output, tensors[0].bytes));
+        assert_eq!(HashSet::from(["add".to_string(), "mul".to_string()]), tensors[0].consumers);
+        assert_eq!((1, None), (tensors[1].output, tensors[1].bytes));
+        assert_eq!(HashSet::from(["mul".to_string()]), tensors[1].consumers);
+    }
+
+    #[test]
+    fn test_shared_patterns_min_models() {
+        let models = vec![
+            chain_model("resnet", &["Conv", "BatchNorm", "Relu"]),
+            chain_model("vit", &["Conv", "BatchNorm", "Relu"]),
+            chain_model("bert", &["MatMul", "Add", "Gelu"]),
+        ];
+
+        let patterns = subgraphs_model_graphs(models, 3, 4, SubgraphOptions::default()).unwrap();
+        assert!(patterns.is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+pub mod vf2;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gspan::models::{
+        dfs_code::DFSCode,
+        graph::{Graph, GraphSetParseError},
+    },
+    io::output::{Instance, Structure},
+    query::vf2::Vf2Matcher,
+};
+
+/// 待查询的模式图
+/// - `Json`: 与挖掘结果中 `structure` 相同的格式，可以直接使用挖掘得到的模式
+/// - `DfsCode`: 每行（或以 `;` 分隔）一条边 `from to from_label e_label to_label`，如 `0 1 Conv <NIL> Relu`
+///
+/// 模式边标签为 `<NIL>` 或空时匹配任意边标签
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryPattern {
+    Json(Structure),
+    DfsCode(String),
+}
+
+impl QueryPattern {
+    pub fn to_graph(&self) -> Result<Graph, GraphSetParseError> {
+        match self {
+            QueryPattern::Json(structure) => structure_to_graph(structure),
+            QueryPattern::DfsCode(text) => Ok(parse_dfs_code(text)?.to_graph(0, true)),
+        }
+    }
+}
+
+fn structure_to_graph(structure: &Structure) -> Result<Graph, GraphSetParseError> {
+    let mut graph = Graph::new(structure.tid, true);
+    for vertex in &structure.vertices {
+        if graph.vertex_index.contains_key(&vertex.name) {
+            return Err(GraphSetParseError::new(format!("Duplicate vertex : {}", vertex.name)));
+        }
+        graph.insert_vertex(&vertex.name, &vertex.label);
+    }
+    for edge in &structure.edges {
+        for name in [&edge.from, &edge.to] {
+            if !graph.vertex_index.contains_key(name) {
+                return Err(GraphSetParseError::new(format!("Unknown vertex : {}", name)));
+            }
+        }
+    }
+    graph.build_edge(
+        structure
+            .edges
+            .iter()
+            .map(|e| (e.from.clone(), e.to.clone(), Some(e.e_label.clone())))
+            .collect(),
+    );
+    Ok(graph)
+}
+
+pub fn parse_dfs_code(text: &str) -> Result<DFSCode, GraphSetParseError> {
+    let mut dfs_code = DFSCode::new();
+    let mut labels: Vec<Option<String>> = Vec::new();
+    for line in text.split(['\n', ';']).map(str::trim).filter(|l| !l.is_empty()) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [from, to, from_label, e_label, to_label] = fields[..] else {
+            return Err(GraphSetParseError::new(format!("Invalid DFS code : {}", line)));
+        };
+        let (Ok(from), Ok(to)) = (from.parse::<usize>(), to.parse::<usize>()) else {
+            return Err(GraphSetParseError::new(format!("Invalid vertex id : {}", line)));
+        };
+        // 同一顶点在不同边中的标签必须一致
+        for (id, label) in [(from, from_label), (to, to_label)] {
+            if labels.len() <= id {
+                labels.resize(id + 1, None);
+            }
+            match &labels[id] {
+                Some(existing) if existing != label => {
+                    return Err(GraphSetParseError::new(format!(
+                        "Conflicting labels for vertex {} : {} / {}",
+                        id, existing, label
+                    )));
+                }
+                _ => labels[id] = Some(label.to_string()),
+            }
+        }
+        dfs_code.push(
+            from,
+            to,
+            from_label.to_string(),
+            e_label.to_string(),
+            to_label.to_string(),
+        );
+    }
+    if dfs_code.dfs_vec.is_empty() {
+        return Err(GraphSetParseError::new(String::from("Empty DFS code")));
+    }
+    Ok(dfs_code)
+}
+
+/// 模式查询结果，`instances` 中每个实例的 `node_ids` 与 `structure.vertices` 按位置一一对应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub total: usize,
+    pub structure: Structure,
+    pub instances: Vec<Instance>,
+}
+
+pub fn query_graph(
+    target: &Graph,
+    pattern: &QueryPattern,
+    limit: Option<usize>,
+) -> Result<QueryResult, GraphSetParseError> {
+    let pattern = pattern.to_graph()?;
+    let mut matcher = Vf2Matcher::new(&pattern, target);
+    matcher.set_limit(limit);
+    let instances = matcher
+        .find_all()
+        .iter()
+        .map(|embedding| embedding.to_instance(target))
+        .collect::<Vec<Instance>>();
+    Ok(QueryResult { total: instances.len(), structure: pattern.to_structure(), instances })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target() -> Graph {
+        let mut graph = Graph::new(0, true);
+        for (name, label) in [("ln", "LayerNorm"), ("mm", "MatMul"), ("add", "Add"), ("gelu", "Gelu")] {
+            graph.insert_vertex(name, label);
+        }
+        graph.build_edge(
+            [("ln", "mm"), ("mm", "add"), ("add", "gelu")]
+                .iter()
+                .map(|(f, t)| (f.to_string(), t.to_string(), None))
+                .collect(),
+        );
+        graph
+    }
+
+    #[test]
+    fn test_query_dfs_code() {
+        let pattern = QueryPattern::DfsCode(String::from(
+            "0 1 LayerNorm <NIL> MatMul; 1 2 MatMul <NIL> Add\n2 3 Add <NIL> Gelu",
+        ));
+        let result = query_graph(&target(), &pattern, None).unwrap();
+        assert_eq!(1, result.total);
+        let names: Vec<&str> =
+            result.instances[0].node_ids.iter().map(|n| n.nid.as_str()).collect();
+        assert_eq!(vec!["ln", "mm", "add", "gelu"], names);
+        assert_eq!(3, result.instances[0].edges.len());
+    }
+
+    #[test]
+    fn test_query_json() {
+        let json = r#"{"json": {
+            "vertices": [{"name": "a", "label": "MatMul"}, {"name": "b", "label": "Add"}],
+            "edges": [{"from": "a", "to": "b"}]
+        }}"#;
+        let pattern: QueryPattern = serde_json::from_str(json).unwrap();
+        let result = query_graph(&target(), &pattern, None).unwrap();
+        assert_eq!(1, result.total);
+        assert_eq!("a", result.structure.vertices[0].name);
+        assert_eq!("mm", result.instances[0].node_ids[0].nid);
+        assert_eq!("add", result.instances[0].node_ids[1].nid);
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(parse_dfs_code("").is_err());
+        assert!(parse_dfs_code("0 1 Conv Relu").is_err());
+        assert!(parse_dfs_code("x 1 Conv <NIL> Relu").is_err());
+        assert!(parse_dfs_code("0 1 Conv <NIL> Relu; 1 2 Add <NIL> Relu").is_err());
+
+        let json = r#"{"json": {"vertices": [{"name": "a", "label": "Add"}], "edges": [{"from": "a", "to": "b"}]}}"#;
+        let pattern: QueryPattern = serde_json::from_str(json).unwrap();
+        assert!(query_graph(&target(), &pattern, None).is_err());
+    }
+}
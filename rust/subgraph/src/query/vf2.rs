@@ -0,0 +1,457 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use rustc_hash::FxHashSet;
+
+use crate::{
+    gspan::models::{edge::Edge, graph::Graph},
+    io::output::{Edge as OutputEdge, Instance, NodeId},
+};
+
+/**
+ * 以顶点下标表示的邻接表
+ * succ/pred 记录每条出边/入边，succ_nodes/pred_nodes 为去重后的相邻顶点，用于前瞻剪枝
+ */
+struct IndexedGraph<'a> {
+    labels: Vec<&'a str>,
+    succ: Vec<Vec<(usize, &'a Edge)>>,
+    pred: Vec<Vec<(usize, &'a Edge)>>,
+    succ_nodes: Vec<Vec<usize>>,
+    pred_nodes: Vec<Vec<usize>>,
+}
+
+impl<'a> IndexedGraph<'a> {
+    fn new(graph: &'a Graph) -> IndexedGraph<'a> {
+        let n = graph.vertices.len();
+        let mut succ: Vec<Vec<(usize, &Edge)>> = vec![vec![]; n];
+        let mut pred: Vec<Vec<(usize, &Edge)>> = vec![vec![]; n];
+        for vertex in &graph.vertices {
+            for e in &vertex.edges {
+                succ[e.from].push((e.to, e));
+                pred[e.to].push((e.from, e));
+            }
+        }
+        let dedup = |adj: &Vec<Vec<(usize, &Edge)>>| -> Vec<Vec<usize>> {
+            adj.iter()
+                .map(|edges| {
+                    let mut nodes: Vec<usize> = edges.iter().map(|(v, _)| *v).collect();
+                    nodes.sort_unstable();
+                    nodes.dedup();
+                    nodes
+                })
+                .collect()
+        };
+        IndexedGraph {
+            labels: graph.vertices.iter().map(|v| v.label.as_str()).collect(),
+            succ_nodes: dedup(&succ),
+            pred_nodes: dedup(&pred),
+            succ,
+            pred,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+}
+
+// 模式边标签为空或 `<NIL>` 时匹配任意边标签
+fn e_label_matches(pattern_label: &str, target_label: &str) -> bool {
+    pattern_label.is_empty() || pattern_label == Edge::NIL_E_LABEL || pattern_label == target_label
+}
+
+/**
+ * VF2 的一侧状态
+ * core: 当前映射；in_depth/out_depth: 顶点进入 T_in/T_out 时的搜索深度，0 表示不在其中
+ * 顶点属于 T_out 当且仅当 out_depth > 0 且尚未映射，T_in 同理
+ */
+struct Side {
+    core: Vec<Option<usize>>,
+    in_depth: Vec<usize>,
+    out_depth: Vec<usize>,
+}
+
+impl Side {
+    fn new(n: usize) -> Side {
+        Side { core: vec![None; n], in_depth: vec![0; n], out_depth: vec![0; n] }
+    }
+
+    fn in_t_out(&self, v: usize) -> bool {
+        self.out_depth[v] > 0 && self.core[v].is_none()
+    }
+
+    fn in_t_in(&self, v: usize) -> bool {
+        self.in_depth[v] > 0 && self.core[v].is_none()
+    }
+
+    fn add(&mut self, graph: &IndexedGraph, v: usize, other: usize, depth: usize) {
+        self.core[v] = Some(other);
+        for d in [&mut self.in_depth[v], &mut self.out_depth[v]] {
+            if *d == 0 {
+                *d = depth;
+            }
+        }
+        for &u in &graph.succ_nodes[v] {
+            if self.out_depth[u] == 0 {
+                self.out_depth[u] = depth;
+            }
+        }
+        for &u in &graph.pred_nodes[v] {
+            if self.in_depth[u] == 0 {
+                self.in_depth[u] = depth;
+            }
+        }
+    }
+
+    fn remove(&mut self, graph: &IndexedGraph, v: usize, depth: usize) {
+        self.core[v] = None;
+        for u in std::iter::once(&v).chain(&graph.succ_nodes[v]).chain(&graph.pred_nodes[v]) {
+            if self.in_depth[*u] == depth {
+                self.in_depth[*u] = 0;
+            }
+            if self.out_depth[*u] == depth {
+                self.out_depth[*u] = 0;
+            }
+        }
+    }
+
+    // 前瞻计数：(在 T_in 中, 在 T_out 中, 未映射) 的相邻顶点数
+    fn count(&self, nodes: &[usize]) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for &u in nodes {
+            if self.core[u].is_some() {
+                continue;
+            }
+            if self.in_t_in(u) {
+                counts.0 += 1;
+            }
+            if self.in_t_out(u) {
+                counts.1 += 1;
+            }
+            counts.2 += 1;
+        }
+        counts
+    }
+}
+
+/// 一次匹配：`vertices[i]` 为模式第 i 个顶点对应的目标图顶点下标，`edges` 为匹配到的目标图边
+pub struct Embedding<'a> {
+    pub vertices: Vec<usize>,
+    pub edges: Vec<&'a Edge>,
+}
+
+/**
+ * VF2 子图同构（单态）搜索：模式中的每条边都必须在目标图中存在，目标图中可以有额外的边
+ * 顶点标签必须相等；模式边标签为空或 `<NIL>` 时匹配任意边标签
+ * 同一组目标边只因模式自同构而重复出现的匹配只保留一次
+ */
+pub struct Vf2Matcher<'a> {
+    pattern: IndexedGraph<'a>,
+    target: IndexedGraph<'a>,
+    limit: Option<usize>,
+}
+
+impl<'a> Vf2Matcher<'a> {
+    pub fn new(pattern: &'a Graph, target: &'a Graph) -> Vf2Matcher<'a> {
+        Vf2Matcher {
+            pattern: IndexedGraph::new(pattern),
+            target: IndexedGraph::new(target),
+            limit: None,
+        }
+    }
+
+    // 设置最多返回的匹配数，None 表示不限制
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    pub fn find_all(&self) -> Vec<Embedding<'a>> {
+        let mut embeddings = Vec::new();
+        // 上限为 0 时不记录任何匹配
+        let no_room = self.limit == Some(0);
+        if no_room || self.pattern.len() == 0 || self.pattern.len() > self.target.len() {
+            return embeddings;
+        }
+        let mut pattern_side = Side::new(self.pattern.len());
+        let mut target_side = Side::new(self.target.len());
+        let mut seen: FxHashSet<Vec<usize>> = FxHashSet::default();
+        self.search(1, &mut pattern_side, &mut target_side, &mut seen, &mut embeddings);
+        embeddings
+    }
+
+    // 返回 true 表示已达到数量上限，停止搜索
+    fn search(
+        &self,
+        depth: usize,
+        p: &mut Side,
+        t: &mut Side,
+        seen: &mut FxHashSet<Vec<usize>>,
+        embeddings: &mut Vec<Embedding<'a>>,
+    ) -> bool {
+        if depth > self.pattern.len() {
+            let embedding = self.build_embedding(p);
+            let mut key: Vec<usize> = embedding.edges.iter().map(|e| e.id).collect();
+            key.sort_unstable();
+            let mut vertices = embedding.vertices.clone();
+            vertices.sort_unstable();
+            key.push(usize::MAX);
+            key.extend(vertices);
+            if seen.insert(key) {
+                embeddings.push(embedding);
+            }
+            return self.limit.is_some_and(|limit| embeddings.len() >= limit);
+        }
+
+        let (n, candidates) = self.next_candidates(p, t);
+        for m in candidates {
+            if !self.feasible(n, m, p, t) {
+                continue;
+            }
+            p.add(&self.pattern, n, m, depth);
+            t.add(&self.target, m, n, depth);
+            let stop = self.search(depth + 1, p, t, seen, embeddings);
+            p.remove(&self.pattern, n, depth);
+            t.remove(&self.target, m, depth);
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+
+    /**
+     * 选择下一个待匹配的模式顶点及其候选目标顶点
+     * 优先选择 T_out 中的顶点，其候选只能是已映射前驱的像的后继；其次 T_in；
+     * 都为空时（第一个顶点或模式不连通）候选为全部未映射的目标顶点
+     */
+    fn next_candidates(&self, p: &Side, t: &Side) -> (usize, Vec<usize>) {
+        let unmapped = |v: &usize| t.core[*v].is_none();
+        if let Some(n) = (0..self.pattern.len()).find(|&v| p.in_t_out(v)) {
+            let anchor = self.pattern.pred_nodes[n].iter().find_map(|&u| p.core[u]).unwrap();
+            return (n, self.target.succ_nodes[anchor].iter().copied().filter(unmapped).collect());
+        }
+        if let Some(n) = (0..self.pattern.len()).find(|&v| p.in_t_in(v)) {
+            let anchor = self.pattern.succ_nodes[n].iter().find_map(|&u| p.core[u]).unwrap();
+            return (n, self.target.pred_nodes[anchor].iter().copied().filter(unmapped).collect());
+        }
+        let n = (0..self.pattern.len()).find(|&v| p.core[v].is_none()).unwrap();
+        (n, (0..self.target.len()).filter(unmapped).collect())
+    }
+
+    fn feasible(&self, n: usize, m: usize, p: &Side, t: &Side) -> bool {
+        if self.pattern.labels[n] != self.target.labels[m] {
+            return false;
+        }
+        // 与已映射顶点之间的边必须在目标图中存在
+        let has_edge = |edges: &[(usize, &Edge)], other: usize, e_label: &str| {
+            edges.iter().any(|(v, e)| *v == other && e_label_matches(e_label, &e.e_label))
+        };
+        for (u, e) in &self.pattern.succ[n] {
+            let image = if *u == n { Some(m) } else { p.core[*u] };
+            if image.is_some_and(|image| !has_edge(&self.target.succ[m], image, &e.e_label)) {
+                return false;
+            }
+        }
+        for (u, e) in &self.pattern.pred[n] {
+            let image = if *u == n { Some(m) } else { p.core[*u] };
+            if image.is_some_and(|image| !has_edge(&self.target.pred[m], image, &e.e_label)) {
+                return false;
+            }
+        }
+        // 前瞻：模式相邻顶点在 T_in/T_out/未映射中的数量不能多于目标图
+        for (pattern_nodes, target_nodes) in [
+            (&self.pattern.succ_nodes[n], &self.target.succ_nodes[m]),
+            (&self.pattern.pred_nodes[n], &self.target.pred_nodes[m]),
+        ] {
+            let (p_in, p_out, p_new) = p.count(pattern_nodes);
+            let (t_in, t_out, t_new) = t.count(target_nodes);
+            if p_in > t_in || p_out > t_out || p_new > t_new {
+                return false;
+            }
+        }
+        true
+    }
+
+    // 为每条模式边挑选一条尚未使用的目标边，模式中的重边对应目标中不同的边
+    fn build_embedding(&self, p: &Side) -> Embedding<'a> {
+        let vertices: Vec<usize> = p.core.iter().map(|c| c.unwrap()).collect();
+        let mut used: FxHashSet<usize> = FxHashSet::default();
+        let mut edges = Vec::new();
+        for (from, out) in self.pattern.succ.iter().enumerate() {
+            for (to, e) in out {
+                let chosen = self.target.succ[vertices[from]].iter().find(|(v, target_edge)| {
+                    *v == vertices[*to]
+                        && !used.contains(&target_edge.id)
+                        && e_label_matches(&e.e_label, &target_edge.e_label)
+                });
+                if let Some((_, target_edge)) = chosen {
+                    used.insert(target_edge.id);
+                    edges.push(*target_edge);
+                }
+            }
+        }
+        Embedding { vertices, edges }
+    }
+}
+
+impl Embedding<'_> {
+    pub fn to_instance(&self, target: &Graph) -> Instance {
+        let node_ids = self
+            .vertices
+            .iter()
+            .map(|&v| NodeId { gid: target.id, nid: target.vertices[v].name.clone() })
+            .collect::<Vec<NodeId>>();
+        let edges = self
+            .edges
+            .iter()
+            .map(|e| OutputEdge::from_graph_edge(e, target))
+            .collect::<Vec<OutputEdge>>();
+        Instance { node_num: node_ids.len(), node_ids, edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gspan::models::graph::fixture::build_graph;
+
+    fn matched_names(pattern: &Graph, target: &Graph) -> Vec<Vec<String>> {
+        let mut names: Vec<Vec<String>> = Vf2Matcher::new(pattern, target)
+            .find_all()
+            .iter()
+            .map(|m| m.vertices.iter().map(|&v| target.vertices[v].name.clone()).collect())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_chain_occurrences() {
+        let target = build_graph(
+            &[
+                ("ln0", "LayerNorm"),
+                ("mm0", "MatMul"),
+                ("add0", "Add"),
+                ("gelu0", "Gelu"),
+                ("ln1", "LayerNorm"),
+                ("mm1", "MatMul"),
+                ("add1", "Add"),
+                ("relu1", "Relu"),
+            ],
+            &[
+                ("ln0", "mm0", "<NIL>"),
+                ("mm0", "add0", "<NIL>"),
+                ("add0", "gelu0", "<NIL>"),
+                ("gelu0", "ln1", "<NIL>"),
+                ("ln1", "mm1", "<NIL>"),
+                ("mm1", "add1", "<NIL>"),
+                ("add1", "relu1", "<NIL>"),
+            ],
+        );
+        let pattern = build_graph(
+            &[("0", "LayerNorm"), ("1", "MatMul"), ("2", "Add"), ("3", "Gelu")],
+            &[("0", "1", "<NIL>"), ("1", "2", "<NIL>"), ("2", "3", "<NIL>")],
+        );
+        assert_eq!(vec![vec!["ln0", "mm0", "add0", "gelu0"]], matched_names(&pattern, &target));
+
+        let pattern = build_graph(
+            &[("0", "LayerNorm"), ("1", "MatMul"), ("2", "Add")],
+            &[("0", "1", "<NIL>"), ("1", "2", "<NIL>")],
+        );
+        assert_eq!(
+            vec![vec!["ln0", "mm0", "add0"], vec!["ln1", "mm1", "add1"]],
+            matched_names(&pattern, &target)
+        );
+    }
+
+    #[test]
+    fn test_direction_and_edge_label() {
+        let target = build_graph(
+            &[("a", "Const"), ("b", "Const"), ("sub", "Sub")],
+            &[("a", "sub", "0"), ("b", "sub", "1")],
+        );
+        // 方向相反的模式不匹配
+        let reversed = build_graph(&[("0", "Sub"), ("1", "Const")], &[("0", "1", "<NIL>")]);
+        assert!(matched_names(&reversed, &target).is_empty());
+
+        // 指定输入序号时只匹配对应的操作数
+        let second = build_graph(&[("0", "Const"), ("1", "Sub")], &[("0", "1", "1")]);
+        assert_eq!(vec![vec!["b", "sub"]], matched_names(&second, &target));
+
+        // `<NIL>` 匹配任意边标签
+        let any = build_graph(&[("0", "Const"), ("1", "Sub")], &[("0", "1", "<NIL>")]);
+        assert_eq!(vec![vec!["a", "sub"], vec!["b", "sub"]], matched_names(&any, &target));
+    }
+
+    #[test]
+    fn test_automorphism_deduplicated() {
+        // 菱形：x -> {y, z} -> w，模式中的 y、z 可以互换，只保留一次
+        let diamond = build_graph(
+            &[("x", "Split"), ("y", "Relu"), ("z", "Relu"), ("w", "Add")],
+            &[("x", "y", "<NIL>"), ("x", "z", "<NIL>"), ("y", "w", "<NIL>"), ("z", "w", "<NIL>")],
+        );
+        let matcher = Vf2Matcher::new(&diamond, &diamond);
+        let embeddings = matcher.find_all();
+        assert_eq!(1, embeddings.len());
+        assert_eq!(4, embeddings[0].edges.len());
+    }
+
+    #[test]
+    fn test_non_induced_and_limit() {
+        // 目标图中额外的边不影响匹配
+        let target = build_graph(
+            &[("a", "Relu"), ("b", "Relu"), ("c", "Relu")],
+            &[("a", "b", "<NIL>"), ("b", "c", "<NIL>"), ("a", "c", "<NIL>")],
+        );
+        let pattern = build_graph(&[("0", "Relu"), ("1", "Relu")], &[("0", "1", "<NIL>")]);
+        assert_eq!(3, matched_names(&pattern, &target).len());
+
+        let mut matcher = Vf2Matcher::new(&pattern, &target);
+        matcher.set_limit(Some(2));
+        assert_eq!(2, matcher.find_all().len());
+        matcher.set_limit(Some(0));
+        assert!(matcher.find_all().is_empty());
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // 与枚举全部 (A, B) 组合后逐边检查的结果一致
+        let target = build_graph(
+            &[("0", "A"), ("1", "B"), ("2", "A"), ("3", "B"), ("4", "C"), ("5", "A")],
+            &[
+                ("0", "1", "<NIL>"),
+                ("1", "2", "<NIL>"),
+                ("2", "3", "<NIL>"),
+                ("3", "4", "<NIL>"),
+                ("1", "4", "<NIL>"),
+                ("5", "1", "<NIL>"),
+                ("5", "3", "<NIL>"),
+            ],
+        );
+        let pattern = build_graph(
+            &[("0", "A"), ("1", "B"), ("2", "C")],
+            &[("0", "1", "<NIL>"), ("1", "2", "<NIL>")],
+        );
+        let found = matched_names(&pattern, &target);
+        let mut expected = vec![];
+        for a in ["0", "2", "5"] {
+            for b in ["1", "3"] {
+                let edges = [(a, b), (b, "4")];
+                let all_exist = edges.iter().all(|(f, t)| {
+                    target
+                        .find_vertex(f)
+                        .unwrap()
+                        .edges
+                        .iter()
+                        .any(|e| target.vertex_name(e.to) == *t)
+                });
+                if all_exist {
+                    expected.push(vec![a.to_string(), b.to_string(), "4".to_string()]);
+                }
+            }
+        }
+        expected.sort();
+        assert_eq!(expected, found);
+        assert_eq!(4, found.len());
+    }
+}
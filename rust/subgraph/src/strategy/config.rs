@@ -3,7 +3,11 @@
  */
 use std::{fs, path::Path};
 
-use crate::gspan::{models::graph::Graph, result::OutType};
+use crate::gspan::{
+    misc::SupportMode,
+    models::{edge::EdgeLabel, graph::Graph},
+    result::{OutType, PatternMode},
+};
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -31,10 +35,13 @@ pub struct Config {
     process_path: Option<String>,
     output_path: Option<String>,
     output_type: OutType,
-    min_support: usize,       // 相同结构在不同图中出现的最小次数
-    min_inner_support: usize, // 相同结构在图内部中出现的最小次数
-    min_vertices: usize,      // Minimum number of vertices
-    max_vertices: usize,      // Maximum number of vertices
+    min_support: usize,        // 相同结构在不同图中出现的最小次数
+    min_inner_support: usize,  // 相同结构在图内部中出现的最小次数
+    min_vertices: usize,       // Minimum number of vertices
+    max_vertices: usize,       // Maximum number of vertices
+    edge_label: EdgeLabel,     // 边标签的取值方式
+    pattern_mode: PatternMode, // 结果模式的筛选方式
+    support_mode: SupportMode, // 图内支持度的计算方式
 }
 
 impl Config {
@@ -69,6 +76,31 @@ impl Config {
     pub fn get_max_vertices(&self) -> usize {
         self.max_vertices
     }
+
+    pub fn get_edge_label(&self) -> EdgeLabel {
+        self.edge_label
+    }
+
+    // 仅对 InputSource::File 生效，InputSource::Graphs 的边标签在构图时已确定
+    pub fn set_edge_label(&mut self, edge_label: EdgeLabel) {
+        self.edge_label = edge_label;
+    }
+
+    pub fn get_pattern_mode(&self) -> PatternMode {
+        self.pattern_mode
+    }
+
+    pub fn set_pattern_mode(&mut self, pattern_mode: PatternMode) {
+        self.pattern_mode = pattern_mode;
+    }
+
+    pub fn get_support_mode(&self) -> SupportMode {
+        self.support_mode
+    }
+
+    pub fn set_support_mode(&mut self, support_mode: SupportMode) {
+        self.support_mode = support_mode;
+    }
 }
 
 impl Config {
@@ -132,6 +164,9 @@ impl Config {
             min_inner_support,
             min_vertices,
             max_vertices,
+            edge_label: EdgeLabel::default(),
+            pattern_mode: PatternMode::default(),
+            support_mode: SupportMode::default(),
         })
     }
 
@@ -186,6 +221,9 @@ impl Config {
             min_inner_support,
             min_vertices,
             max_vertices,
+            edge_label: EdgeLabel::default(),
+            pattern_mode: PatternMode::default(),
+            support_mode: SupportMode::default(),
         })
     }
 }
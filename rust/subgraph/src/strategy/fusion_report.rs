@@ -0,0 +1,264 @@
+/*
+ * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
+ */
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gspan::{models::graph::Graph, result::JSONResult};
+
+/// 融合报告的输出格式
+/// - `Json`: [`FusionReport`] 整体序列化
+/// - `Csv`: 每个候选一行，带表头
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/**
+ * 一个融合候选（挖掘出的模式）
+ * - `ops`: 模式中的算子类型，按 gSpan 挖掘时 DFS 编码的顶点编号排列
+ * - `coverage`: 实例数 × 模式顶点数
+ * - `op_share`: 被该模式的实例覆盖的算子（去重后）占全部算子的比例
+ * - `est_bytes_saved`: 融合后不再需要读写全局内存的中间张量字节数之和，
+ *   只有所有实例的中间张量大小都已知时才给出
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FusionCandidate {
+    pub rank: usize,
+    pub pattern_id: String,
+    pub ops: Vec<String>,
+    pub vertices: usize,
+    pub instances: usize,
+    pub coverage: usize,
+    pub op_share: f64,
+    pub est_bytes_saved: Option<u64>,
+}
+
+/// 算子的一个输出张量：`output` 为其在算子输出中的序号，`bytes` 未知时为 `None`
+#[derive(Debug, Clone, Default)]
+pub struct OutputTensor {
+    pub output: usize,
+    pub bytes: Option<u64>,
+    pub consumers: HashSet<String>,
+}
+
+/// 按覆盖度降序排列的融合候选表
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FusionReport {
+    pub total_ops: usize,
+    pub candidates: Vec<FusionCandidate>,
+}
+
+impl FusionReport {
+    /**
+     * 由挖掘结果与参与挖掘的图生成报告，`graphs` 需按 gid 排列
+     * `outputs` 按 gid 给出每个算子被消费的输出张量（以算子名为键），
+     * 为空或某个中间张量大小未知时 `est_bytes_saved` 为 `None`
+     */
+    pub fn build(
+        results: &[JSONResult],
+        graphs: &[Graph],
+        outputs: &[HashMap<String, Vec<OutputTensor>>],
+    ) -> FusionReport {
+        let total_ops: usize = graphs.iter().map(|g| g.vertices.len()).sum();
+        let mut candidates: Vec<FusionCandidate> = results
+            .iter()
+            .map(|result| {
+                let vertices = result.structure.vertices.len();
+                let covered: HashSet<(usize, &str)> = result
+                    .instances
+                    .iter()
+                    .flat_map(|i| i.node_ids.iter())
+                    .map(|n| (n.gid, n.nid.as_str()))
+                    .collect();
+                let est_bytes_saved = result
+                    .instances
+                    .iter()
+                    .map(|instance| {
+                        let gid = instance.node_ids.first()?.gid;
+                        let names: Vec<&str> =
+                            instance.node_ids.iter().map(|n| n.nid.as_str()).collect();
+                        bytes_saved(&names, outputs.get(gid)?)
+                    })
+                    .sum::<Option<u64>>();
+                FusionCandidate {
+                    rank: 0,
+                    pattern_id: result.pattern_id.clone(),
+                    ops: result.structure.vertices.iter().map(|v| v.label.clone()).collect(),
+                    vertices,
+                    instances: result.instances.len(),
+                    coverage: result.instances.len() * vertices,
+                    op_share: if total_ops == 0 {
+                        0.0
+                    } else {
+                        covered.len() as f64 / total_ops as f64
+                    },
+                    est_bytes_saved,
+                }
+            })
+            .collect();
+
+        // 覆盖度相同时按覆盖比例、模式 id 排序，保证输出稳定
+        candidates.sort_by(|a, b| {
+            b.coverage
+                .cmp(&a.coverage)
+                .then(b.op_share.total_cmp(&a.op_share))
+                .then_with(|| a.pattern_id.cmp(&b.pattern_id))
+        });
+        for (rank, candidate) in candidates.iter_mut().enumerate() {
+            candidate.rank = rank + 1;
+        }
+        FusionReport { total_ops, candidates }
+    }
+
+    pub fn export(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Json =>
+                serde_json::to_string_pretty(self).expect("Err: Serialization failed"),
+            ReportFormat::Csv => self.to_csv(),
+        }
+    }
+
+    // 算子类型以 `;` 连接，未知的节省字节数输出为空
+    pub fn to_csv(&self) -> String {
+        let mut lines: Vec<String> = vec![String::from(
+            "rank,pattern_id,ops,vertices,instances,coverage,op_share,est_bytes_saved",
+        )];
+        for c in &self.candidates {
+            lines.push(format!(
+                "{},{},{},{},{},{},{:.6},{}",
+                c.rank,
+                csv_field(&c.pattern_id),
+                csv_field(&c.ops.join(";")),
+                c.vertices,
+                c.instances,
+                c.coverage,
+                c.op_share,
+                c.est_bytes_saved.map_or(String::new(), |b| b.to_string())
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/**
+ * 一个实例融合后节省的内存流量：对实例内算子的每个被实例内部消费的输出张量，
+ * 每个内部消费者少读一次；没有外部消费者时该张量也不必写回
+ */
+fn bytes_saved(names: &[&str], outputs: &HashMap<String, Vec<OutputTensor>>) -> Option<u64> {
+    let names: HashSet<&str> = names.iter().copied().collect();
+    let mut saved = 0;
+    for tensor in names.iter().filter_map(|name| outputs.get(*name)).flatten() {
+        let internal =
+            tensor.consumers.iter().filter(|c| names.contains(c.as_str())).count() as u64;
+        if internal == 0 {
+            continue;
+        }
+        let write = if internal as usize == tensor.consumers.len() { 1 } else { 0 };
+        saved += tensor.bytes? * (internal + write);
+    }
+    Some(saved)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gspan::{
+        gspan::GSpan,
+        result::{OutType, PatternMode},
+    };
+
+    // 两个 Conv -> Mul -> Relu 块，其后接一个 Tanh，第二个块的 Conv 同时输出到 Tanh
+    fn model() -> Graph {
+        let mut graph = Graph::new(0, true);
+        let mut edges = vec![];
+        for block in 0..2 {
+            let name = |op: &str| format!("{}_{}", op, block);
+            for op in ["Conv", "Mul", "Relu"] {
+                graph.insert_vertex(&name(op), op);
+            }
+            edges.push((name("Conv"), name("Mul"), None));
+            edges.push((name("Mul"), name("Relu"), None));
+        }
+        graph.insert_vertex("Tanh_0", "Tanh");
+        edges.push((String::from("Relu_1"), String::from("Tanh_0"), None));
+        edges.push((String::from("Conv_1"), String::from("Tanh_0"), None));
+        graph.build_edge(edges);
+        graph
+    }
+
+    fn mine(graph: &Graph) -> Vec<JSONResult> {
+        let mut gspan = GSpan::new(vec![graph.clone()], 1, 2, 2, 3, true);
+        gspan.set_pattern_mode(PatternMode::Maximal);
+        gspan.run::<std::io::Sink>(OutType::JSON, None, None).1.get_result()
+    }
+
+    #[test]
+    fn test_ranking_and_share() {
+        let graph = model();
+        let report = FusionReport::build(&mine(&graph), &[graph], &[]);
+        assert_eq!(7, report.total_ops);
+        let top = &report.candidates[0];
+        assert_eq!(1, top.rank);
+        assert_eq!(3, top.vertices);
+        assert_eq!(2, top.instances);
+        assert_eq!(6, top.coverage);
+        assert!((top.op_share - 6.0 / 7.0).abs() < 1e-9);
+        assert_eq!(None, top.est_bytes_saved);
+        assert!(report.candidates.windows(2).all(|w| w[0].coverage >= w[1].coverage));
+    }
+
+    #[test]
+    fn test_bytes_saved() {
+        let graph = model();
+        let tensor = |output: usize, bytes: u64, consumers: &[&str]| OutputTensor {
+            output,
+            bytes: Some(bytes),
+            consumers: consumers.iter().map(|c| c.to_string()).collect(),
+        };
+        let mut outputs: HashMap<String, Vec<OutputTensor>> = HashMap::new();
+        for vertex in &graph.vertices {
+            let consumers: Vec<&str> =
+                vertex.edges.iter().map(|e| graph.vertex_name(e.to)).collect();
+            if !consumers.is_empty() {
+                outputs.insert(vertex.name.clone(), vec![tensor(0, 100, &consumers)]);
+            }
+        }
+        // 第二个块的 Conv 由另一个输出供 Tanh 读取
+        outputs.insert(
+            String::from("Conv_1"),
+            vec![tensor(0, 100, &["Mul_1"]), tensor(1, 1000, &["Tanh_0"])],
+        );
+        let report = FusionReport::build(&mine(&graph), &[graph], &[outputs]);
+        // 每个实例中 Conv、Mul 的输出各省一次读和一次写，只供 Tanh 读取的输出不计入
+        assert_eq!(Some(400 + 400), report.candidates[0].est_bytes_saved);
+    }
+
+    #[test]
+    fn test_csv() {
+        let graph = model();
+        let report = FusionReport::build(&mine(&graph), &[graph], &[]);
+        let csv = report.export(ReportFormat::Csv);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("rank,pattern_id,ops"));
+        let first: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(8, first.len());
+        assert_eq!("1", first[0]);
+        assert_eq!(report.candidates[0].pattern_id, first[1]);
+        assert_eq!("", first[7]);
+        assert_eq!(report.candidates.len() + 1, csv.lines().count());
+        assert_eq!("\"a,b\"", csv_field("a,b"));
+    }
+}
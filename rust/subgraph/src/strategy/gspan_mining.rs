@@ -2,8 +2,8 @@
  * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
  */
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufWriter, Read, Seek, Write},
+    fs::File,
+    io::BufWriter,
     sync::mpsc::{self, Receiver, Sender},
     thread,
     time::Instant,
@@ -11,11 +11,7 @@ use std::{
 
 use super::mining_strategy::MiningStrategy;
 use crate::{
-    gspan::{
-        gspan::GSpan,
-        models::graph::Graph,
-        result::{JSONResult, OutType},
-    },
+    gspan::{gspan::GSpan, misc::EXACT_MIS_LIMIT, models::graph::Graph, result::JSONResult},
     result::OutSource,
     strategy::config::InputSource,
 };
@@ -27,9 +23,9 @@ impl MiningStrategy for GSpanMining {
         let now = Instant::now();
         let graphs = match args.get_input_source() {
             InputSource::File(input_file) => {
-                let graph = Graph::graph_from_file(&input_file, false);
-                match graph {
-                    Ok(graph) => vec![graph],
+                let graphs = Graph::graphs_from_file(&input_file, false, args.get_edge_label());
+                match graphs {
+                    Ok(graphs) => graphs,
                     Err(err) => panic!("{}", err.to_string()),
                 }
             }
@@ -46,7 +42,7 @@ impl MiningStrategy for GSpanMining {
         println!("Took {}ms", alpha);
 
         println!("Mining subgraphs..");
-        let gspan = GSpan::new(
+        let mut gspan = GSpan::new(
             graphs,
             args.get_min_support(),
             args.get_min_inner_support(),
@@ -54,6 +50,8 @@ impl MiningStrategy for GSpanMining {
             args.get_max_vertices(),
             true,
         );
+        gspan.set_pattern_mode(args.get_pattern_mode());
+        gspan.set_support_mode(args.get_support_mode());
 
         let process_writer: Option<BufWriter<File>> = match args.get_process_path() {
             Some(file) => Some(BufWriter::new(File::create(file).unwrap())),
@@ -75,10 +73,15 @@ impl MiningStrategy for GSpanMining {
             result.get_value_len(),
             result.get_sum_subgraphs()
         );
+        if result.is_mis_fallback() {
+            eprintln!(
+                "Warning: more than {} instances in a graph, exact MIS support fell back to greedy",
+                EXACT_MIS_LIMIT
+            );
+        }
         println!("Took {}ms", delta - alpha);
         println!("Total Took {}ms", delta);
 
-        fix_json_file(args.get_output_path(), args.get_output_type());
         result.get_result()
     }
 
@@ -86,9 +89,9 @@ impl MiningStrategy for GSpanMining {
         let now = Instant::now();
         let graphs = match args.get_input_source() {
             InputSource::File(file) => {
-                let graph = Graph::graph_from_file(&file, false);
-                match graph {
-                    Ok(graph) => vec![graph],
+                let graphs = Graph::graphs_from_file(&file, false, args.get_edge_label());
+                match graphs {
+                    Ok(graphs) => graphs,
                     Err(err) => panic!("{}", err.to_string()),
                 }
             }
@@ -105,7 +108,7 @@ impl MiningStrategy for GSpanMining {
         println!("Took {}ms", alpha);
 
         println!("Mining subgraphs..");
-        let gspan = GSpan::new(
+        let mut gspan = GSpan::new(
             graphs,
             args.get_min_support(),
             args.get_min_inner_support(),
@@ -113,6 +116,8 @@ impl MiningStrategy for GSpanMining {
             args.get_max_vertices(),
             true,
         );
+        gspan.set_pattern_mode(args.get_pattern_mode());
+        gspan.set_support_mode(args.get_support_mode());
 
         // let gspan = match args.get_output_path() {
         //     Some(file) => GSpan::new_with_out_path(graphs, args.get_min_support(),
@@ -151,41 +156,17 @@ impl MiningStrategy for GSpanMining {
             result.drop_sender();
         });
 
-        fix_json_file(args.get_output_path(), args.get_output_type());
         rx
     }
 }
 
-fn fix_json_file(output_path: &Option<String>, output_type: &OutType) {
-    match &output_type {
-        OutType::JSON => {
-            if let Some(filename) = &output_path {
-                // 打开文件并读取内容
-                let mut file = OpenOptions::new().read(true).write(true).open(filename).unwrap();
-
-                let mut contents = String::new();
-                file.read_to_string(&mut contents).unwrap();
-
-                // 修改内容
-                contents.pop();
-                contents.pop();
-                let new_contents = format!("[{}]", contents);
-
-                // 清空文件并重置文件位置
-                file.set_len(0).unwrap();
-                file.seek(std::io::SeekFrom::Start(0)).unwrap();
-
-                file.write_all(new_contents.as_bytes()).unwrap();
-            }
-        }
-        _ => {}
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{gspan::result::OutType, strategy::Config};
+    use crate::{
+        gspan::{models::edge::EdgeLabel, result::OutType},
+        strategy::Config,
+    };
 
     #[test]
     fn test_run_lenet_graph() {
@@ -218,7 +199,8 @@ mod tests {
     #[test]
     fn test_run_lenet_graph_parsed() {
         // JSON 文件路径
-        let graph = Graph::graph_from_file(r#"tests\json\lenet.json"#, true).unwrap();
+        let graph =
+            Graph::graph_from_file(r#"tests\json\lenet.json"#, true, EdgeLabel::Nil).unwrap();
 
         let gspan_mining = GSpanMining;
 
@@ -262,4 +244,66 @@ mod tests {
             Err(e) => eprintln!("Failed to create config: {:?}", e),
         };
     }
+
+    // 三张图，其中两张包含 A - B - C
+    const TRANSACTIONS: &str = "t # 0\nv 0 A\nv 1 B\nv 2 C\ne 0 1 x\ne 1 2 x\n\
+                                t # 1\nv 0 C\nv 1 B\nv 2 A\nv 3 D\ne 2 1 x\ne 1 0 x\ne 0 3 x\n\
+                                t # 2\nv 0 A\nv 1 D\ne 0 1 x\nt # -1\n";
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(name).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_run_transaction_file() {
+        let path = temp_path("subgraph_transaction_test.txt");
+        std::fs::write(&path, TRANSACTIONS).unwrap();
+
+        let config = Config::new(&path, None, None, OutType::JSON, 2, 1, 1, 3).unwrap();
+        let result = GSpanMining.run(config);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.iter().all(|r| r.between_sup >= 2));
+        assert!(result.iter().any(|r| r.structure.vertices.len() == 3 && r.between_sup == 2));
+    }
+
+    #[test]
+    fn test_run_streaming_output() {
+        let input = temp_path("subgraph_streaming_test.txt");
+        std::fs::write(&input, TRANSACTIONS).unwrap();
+
+        // JSON 输出为完整的数组
+        let output = temp_path("subgraph_streaming_test.json");
+        let config = Config::new(&input, None, Some(&output), OutType::JSON, 2, 1, 1, 3).unwrap();
+        let result = GSpanMining.run(config);
+        let content = std::fs::read_to_string(&output).unwrap();
+        let array: Vec<JSONResult> = serde_json::from_str(&content).unwrap();
+        assert_eq!(result.len(), array.len());
+
+        // NDJSON 每行一个结果
+        let output = temp_path("subgraph_streaming_test.ndjson");
+        let config = Config::new(&input, None, Some(&output), OutType::NDJSON, 2, 1, 1, 3).unwrap();
+        let result = GSpanMining.run(config);
+        let content = std::fs::read_to_string(&output).unwrap();
+        let lines = content
+            .lines()
+            .map(|l| serde_json::from_str::<JSONResult>(l).unwrap())
+            .collect::<Vec<JSONResult>>();
+        assert_eq!(result.len(), lines.len());
+
+        // 没有结果时输出空数组
+        let output = temp_path("subgraph_streaming_empty.json");
+        let config = Config::new(&input, None, Some(&output), OutType::JSON, 4, 1, 1, 3).unwrap();
+        assert!(GSpanMining.run(config).is_empty());
+        assert_eq!("[]", std::fs::read_to_string(&output).unwrap().trim());
+
+        for name in [
+            "subgraph_streaming_test.txt",
+            "subgraph_streaming_test.json",
+            "subgraph_streaming_test.ndjson",
+            "subgraph_streaming_empty.json",
+        ] {
+            std::fs::remove_file(temp_path(name)).unwrap();
+        }
+    }
 }
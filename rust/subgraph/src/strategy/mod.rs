@@ -2,6 +2,7 @@
  * Copyright (c), Huawei Technologies Co., Ltd. 2025-2025. All rights reserved.
  */
 pub mod config;
+pub mod fusion_report;
 pub mod gspan_mining;
 pub mod mining_strategy;
 